@@ -0,0 +1,102 @@
+use crate::apps::ProcessInfo;
+use crate::profile::{self, ProfileError};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use toml::Spanned;
+
+/// Rules picking a profile based on the running application, e.g.
+///
+/// ```toml
+/// default_profile = "desktop.toml"
+///
+/// [[rules]]
+/// process = "eldenring.exe"
+/// profile = "souls.toml"
+/// ```
+///
+/// Relative profile paths are resolved against the directory of the config file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AppConfig {
+    /// Used while no rule matches, the built-in default profile is used if this is unset
+    pub default_profile: Option<PathBuf>,
+    /// Checked in order, the first matching rule wins
+    pub rules: Vec<AppRule>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppRule {
+    pub matcher: AppMatcher,
+    pub profile: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppMatcher {
+    /// Executable name, compared case-insensitively (e.g. `eldenring.exe`)
+    Process(String),
+    /// Full path of the executable
+    Path(PathBuf),
+}
+
+impl AppRule {
+    pub fn matches(&self, process: &ProcessInfo) -> bool {
+        match &self.matcher {
+            AppMatcher::Process(name) => {
+                process.name.eq_ignore_ascii_case(name)
+                    || process
+                        .path
+                        .as_ref()
+                        .and_then(|path| path.file_name())
+                        .is_some_and(|file_name| file_name.to_string_lossy().eq_ignore_ascii_case(name))
+            }
+            AppMatcher::Path(path) => process.path.as_ref() == Some(path),
+        }
+    }
+}
+
+/// The config as written, rules keep their span so matcher errors can point at them
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawAppConfig {
+    default_profile: Option<PathBuf>,
+    rules: Vec<Spanned<RawAppRule>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAppRule {
+    process: Option<String>,
+    path: Option<PathBuf>,
+    profile: PathBuf,
+}
+
+/// Reads and validates a config, resolving its profile paths
+pub fn load_config(path: &Path) -> Result<AppConfig, ProfileError> {
+    let text: String = profile::read_text(path)?;
+    let raw: RawAppConfig = profile::from_toml(&text, Some(path))?;
+
+    let base: &Path = path.parent().unwrap_or(Path::new(""));
+    let mut rules: Vec<AppRule> = Vec::with_capacity(raw.rules.len());
+    for rule in raw.rules {
+        let offset: usize = rule.span().start;
+        let rule: RawAppRule = rule.into_inner();
+        let matcher: AppMatcher = match (rule.process, rule.path) {
+            (Some(name), None) => AppMatcher::Process(name),
+            (None, Some(path)) => AppMatcher::Path(path),
+            (Some(_), Some(_)) => {
+                return Err(profile::invalid_at(&text, offset, Some(path), "a rule can't set both `process` and `path`"));
+            }
+            (None, None) => {
+                return Err(profile::invalid_at(&text, offset, Some(path), "a rule needs either `process` or `path`"));
+            }
+        };
+        rules.push(AppRule {
+            matcher,
+            profile: base.join(rule.profile),
+        });
+    }
+
+    Ok(AppConfig {
+        default_profile: raw.default_profile.map(|default_profile| base.join(default_profile)),
+        rules,
+    })
+}
@@ -0,0 +1,7 @@
+mod config;
+mod process;
+mod switcher;
+
+pub use self::config::{AppConfig, AppMatcher, AppRule, load_config};
+pub use self::process::{ProcessInfo, candidate_processes};
+pub use self::switcher::AppSwitcher;
@@ -0,0 +1,108 @@
+use std::path::PathBuf;
+
+/// A running process a rule can match against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    /// Executable name as the process was started, e.g. `eldenring.exe`
+    pub name: String,
+    /// Full path of the executable, if it could be read
+    pub path: Option<PathBuf>,
+}
+
+/// Processes whose profile should be active.
+///
+/// On Windows this is the process owning the foreground window. Linux has no portable notion of a
+/// foreground window, so every running process is returned instead.
+pub fn candidate_processes() -> Vec<ProcessInfo> {
+    #[cfg(target_os = "linux")]
+    {
+        procfs::running_processes()
+    }
+    #[cfg(windows)]
+    {
+        win32::foreground_process().into_iter().collect()
+    }
+    #[cfg(not(any(target_os = "linux", windows)))]
+    {
+        Vec::new()
+    }
+}
+
+/// Last component of a path, accepting both separators since Proton games report Windows paths
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+#[cfg(target_os = "linux")]
+mod procfs {
+    use super::{ProcessInfo, file_name};
+    use std::fs;
+    use std::path::PathBuf;
+
+    pub fn running_processes() -> Vec<ProcessInfo> {
+        let Ok(entries) = fs::read_dir("/proc") else {
+            return Vec::new();
+        };
+
+        entries
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().bytes().all(|b| b.is_ascii_digit()))
+            .filter_map(|entry| {
+                let dir: PathBuf = entry.path();
+                // argv[0] rather than `comm`, which is truncated and is `wine64-preloader` for Proton games
+                let cmdline: Vec<u8> = fs::read(dir.join("cmdline")).ok()?;
+                let argv0: String = String::from_utf8_lossy(cmdline.split(|b| *b == 0).next()?).into_owned();
+                let name: String = if argv0.is_empty() {
+                    fs::read_to_string(dir.join("comm")).ok()?.trim_end().to_string()
+                } else {
+                    file_name(&argv0).to_string()
+                };
+
+                Some(ProcessInfo {
+                    name,
+                    path: fs::read_link(dir.join("exe")).ok(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(windows)]
+mod win32 {
+    use super::{ProcessInfo, file_name};
+    use std::path::PathBuf;
+    use windows::Win32::Foundation::{CloseHandle, HANDLE, HWND};
+    use windows::Win32::System::Threading::{
+        OpenProcess, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION, QueryFullProcessImageNameW,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+    use windows::core::PWSTR;
+
+    pub fn foreground_process() -> Option<ProcessInfo> {
+        unsafe {
+            let window: HWND = GetForegroundWindow();
+            if window.is_invalid() {
+                return None;
+            }
+
+            let mut pid: u32 = 0;
+            GetWindowThreadProcessId(window, Some(&mut pid as *mut u32));
+            if pid == 0 {
+                return None;
+            }
+
+            let process: HANDLE = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+            let mut buffer: [u16; 1024] = [0; 1024];
+            let mut len: u32 = buffer.len() as u32;
+            let result: windows::core::Result<()> = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut len);
+            CloseHandle(process).ok();
+            result.ok()?;
+
+            let path: String = String::from_utf16_lossy(&buffer[..len as usize]);
+            Some(ProcessInfo {
+                name: file_name(&path).to_string(),
+                path: Some(PathBuf::from(path)),
+            })
+        }
+    }
+}
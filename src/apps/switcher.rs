@@ -0,0 +1,99 @@
+use crate::apps::{AppConfig, AppRule, ProcessInfo, candidate_processes};
+use crate::prelude::*;
+use crate::profile::{self, Profile};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Switches profiles as applications matching an [`AppConfig`] rule appear and disappear.
+///
+/// The callback receives the path of the newly active profile (`None` for the built-in default)
+/// and the loaded profile. A profile that fails to load is logged and skipped, leaving the
+/// previous one active until the matched application changes again.
+pub struct AppSwitcher {
+    thread: Option<JoinHandle<()>>,
+    stop_flag: Arc<Mutex<bool>>,
+}
+
+impl AppSwitcher {
+    pub fn spawn<F>(config: AppConfig, on_switch: F) -> Self
+    where
+        F: Fn(Option<PathBuf>, Profile) + Send + 'static,
+    {
+        let stop_flag: Arc<Mutex<bool>> = Arc::new(Mutex::new(false));
+        let thread_stop_flag: Arc<Mutex<bool>> = stop_flag.clone();
+
+        trace!("Entering thread `app_switch`...");
+        let thread: JoinHandle<()> = thread::Builder::new()
+            .name("app_switch".into())
+            .spawn(move || {
+                trace!("Entered thread");
+
+                // Index of the matched rule, `None` for the default profile. The outer `None`
+                // forces a switch on the first poll.
+                let mut active: Option<Option<usize>> = None;
+                loop {
+                    if *thread_stop_flag.lock().unwrap() {
+                        break;
+                    }
+
+                    let processes: Vec<ProcessInfo> = candidate_processes();
+                    let matched: Option<usize> = matching_rule(&config.rules, &processes);
+                    if active != Some(matched) {
+                        active = Some(matched);
+                        let path: Option<PathBuf> = match matched {
+                            Some(index) => Some(config.rules[index].profile.clone()),
+                            None => config.default_profile.clone(),
+                        };
+                        match &path {
+                            Some(path) => match profile::load(path) {
+                                Ok(profile) => {
+                                    info!("Switching to profile `{}` ({})", profile.name, path.display());
+                                    on_switch(Some(path.clone()), profile);
+                                }
+                                Err(err) => error!("Keeping the current profile, failed to switch: {}", err),
+                            },
+                            None => {
+                                info!("Switching to the default profile");
+                                on_switch(None, Profile::default());
+                            }
+                        }
+                    }
+
+                    thread::sleep(POLL_INTERVAL);
+                }
+
+                trace!("Exiting thread...");
+            })
+            .unwrap();
+
+        Self {
+            thread: Some(thread),
+            stop_flag,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        *self.stop_flag.lock().unwrap() = true;
+        if let Some(handle) = self.thread.take() {
+            handle.join().ok();
+            trace!("Exited thread `app_switch`");
+        }
+    }
+}
+
+impl Drop for AppSwitcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Index of the first rule matching any of the processes
+fn matching_rule(rules: &[AppRule], processes: &[ProcessInfo]) -> Option<usize> {
+    rules
+        .iter()
+        .position(|rule| processes.iter().any(|process| rule.matches(process)))
+}
@@ -0,0 +1,90 @@
+use crate::deck::InputReport;
+use serde::{Deserialize, Serialize};
+
+/// Largest spread of raw gyro samples (about 6°/s) still considered resting
+const MAX_RESTING_SPREAD: i32 = 100;
+
+/// Zero-rate offset of the gyro, in raw counts
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GyroCalibration {
+    pub bias: [f32; 3],
+}
+
+impl GyroCalibration {
+    /// Removes the offset from a freshly parsed report
+    pub fn apply(&self, report: &mut InputReport) {
+        for (value, bias) in report.gyro.iter_mut().zip(self.bias) {
+            *value = (*value as f32 - bias).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        }
+    }
+}
+
+/// Collects gyro samples during `calibrate gyro` while the controller rests on a table
+#[derive(Debug, Default)]
+pub struct GyroCalibrator {
+    sum: [i64; 3],
+    samples: u32,
+    min: [i16; 3],
+    max: [i16; 3],
+}
+
+impl GyroCalibrator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_sample(&mut self, gyro: [i16; 3]) {
+        if self.samples == 0 {
+            (self.min, self.max) = (gyro, gyro);
+        }
+        for (axis, value) in gyro.into_iter().enumerate() {
+            self.sum[axis] += value as i64;
+            self.min[axis] = self.min[axis].min(value);
+            self.max[axis] = self.max[axis].max(value);
+        }
+        self.samples += 1;
+    }
+
+    pub fn finish(&self) -> Result<GyroCalibration, String> {
+        if self.samples == 0 {
+            return Err("no gyro samples were taken".into());
+        }
+        if (0..3).any(|axis| self.max[axis] as i32 - self.min[axis] as i32 > MAX_RESTING_SPREAD) {
+            return Err("the controller moved, leave it on a flat surface and try again".into());
+        }
+        Ok(GyroCalibration {
+            bias: self.sum.map(|sum| (sum as f64 / self.samples as f64) as f32),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bias_is_the_resting_average() {
+        let mut calibrator: GyroCalibrator = GyroCalibrator::new();
+        for sample in [[10, -4, 0], [12, -6, 1], [14, -5, -1]] {
+            calibrator.add_sample(sample);
+        }
+        let calibration: GyroCalibration = calibrator.finish().unwrap();
+        assert_eq!(calibration.bias, [12.0, -5.0, 0.0]);
+
+        let mut report: InputReport = InputReport::default();
+        report.gyro = [112, -5, i16::MIN];
+        calibration.apply(&mut report);
+        assert_eq!(report.gyro, [100, 0, i16::MIN]);
+    }
+
+    #[test]
+    fn moving_or_missing_samples_fail() {
+        assert!(GyroCalibrator::new().finish().is_err());
+
+        let mut calibrator: GyroCalibrator = GyroCalibrator::new();
+        calibrator.add_sample([0, 0, 0]);
+        calibrator.add_sample([0, MAX_RESTING_SPREAD as i16 + 1, 0]);
+        assert!(calibrator.finish().is_err());
+    }
+}
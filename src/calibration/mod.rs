@@ -0,0 +1,7 @@
+mod gyro;
+mod stick;
+mod store;
+
+pub use self::gyro::{GyroCalibration, GyroCalibrator};
+pub use self::stick::{StickCalibration, StickCalibrator, StickRange};
+pub use self::store::{CalibrationError, CalibrationStore, DeviceCalibration, default_path};
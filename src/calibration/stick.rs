@@ -0,0 +1,100 @@
+use crate::deck::Stick;
+use serde::{Deserialize, Serialize};
+
+/// Smallest travel accepted in every direction, anything less means the stick wasn't rotated
+const MIN_RANGE: f32 = 0.5;
+
+/// Corrects a worn or off-center stick, all values are normalized like [`Stick::normalized`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StickCalibration {
+    /// Position reported while the stick rests
+    pub center: [f32; 2],
+    pub range: StickRange,
+}
+
+/// Furthest the stick reaches from its center in every direction
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StickRange {
+    pub left: f32,
+    pub right: f32,
+    pub down: f32,
+    pub up: f32,
+}
+
+impl StickRange {
+    /// Every direction with its name, as `[left, right, down, up]`
+    pub fn directions(&self) -> [(&'static str, f32); 4] {
+        [("left", self.left), ("right", self.right), ("down", self.down), ("up", self.up)]
+    }
+}
+
+impl StickCalibration {
+    /// Re-centers the stick and stretches every direction to full travel
+    pub fn apply(&self, stick: &mut Stick) {
+        let (x, y) = stick.normalized();
+        let x: f32 = x - self.center[0];
+        let y: f32 = y - self.center[1];
+        let x: f32 = x / if x < 0.0 { self.range.left } else { self.range.right };
+        let y: f32 = y / if y < 0.0 { self.range.down } else { self.range.up };
+        stick.x = (x.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+        stick.y = (y.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+    }
+}
+
+/// Collects samples of one stick during `calibrate sticks`, first while it rests, then while it
+/// is rotated along its edge
+#[derive(Debug, Default)]
+pub struct StickCalibrator {
+    center_sum: [f64; 2],
+    center_samples: u32,
+    /// Extremes seen while rotating, as `[left, right, down, up]` before centering
+    extremes: [f32; 4],
+}
+
+impl StickCalibrator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_center_sample(&mut self, stick: &Stick) {
+        let (x, y) = stick.normalized();
+        self.center_sum[0] += x as f64;
+        self.center_sum[1] += y as f64;
+        self.center_samples += 1;
+    }
+
+    pub fn add_range_sample(&mut self, stick: &Stick) {
+        let (x, y) = stick.normalized();
+        self.extremes[0] = self.extremes[0].min(x);
+        self.extremes[1] = self.extremes[1].max(x);
+        self.extremes[2] = self.extremes[2].min(y);
+        self.extremes[3] = self.extremes[3].max(y);
+    }
+
+    pub fn finish(&self) -> Result<StickCalibration, String> {
+        if self.center_samples == 0 {
+            return Err("no samples of the resting stick were taken".into());
+        }
+        let center: [f32; 2] = [
+            (self.center_sum[0] / self.center_samples as f64) as f32,
+            (self.center_sum[1] / self.center_samples as f64) as f32,
+        ];
+        let range: StickRange = StickRange {
+            left: center[0] - self.extremes[0],
+            right: self.extremes[1] - center[0],
+            down: center[1] - self.extremes[2],
+            up: self.extremes[3] - center[1],
+        };
+        for (direction, travel) in range.directions() {
+            if travel < MIN_RANGE {
+                return Err(format!(
+                    "the stick only moved {:.0}% of the way {direction}, rotate it along its edge",
+                    travel * 100.0
+                ));
+            }
+        }
+        Ok(StickCalibration { center, range })
+    }
+}
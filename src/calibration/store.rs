@@ -0,0 +1,236 @@
+use crate::calibration::{GyroCalibration, StickCalibration};
+use crate::deck::InputReport;
+use crate::profile::{self, ProfileError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::{env, error::Error, fmt, fs, io};
+
+#[derive(Debug)]
+pub enum CalibrationError {
+    Io { path: PathBuf, source: io::Error },
+    /// The file is not valid TOML or doesn't match the format
+    Invalid {
+        path: PathBuf,
+        /// 1-based
+        line: usize,
+        /// 1-based, counted in characters
+        column: usize,
+        message: String,
+    },
+    /// A stick range that isn't a finite value above zero, e.g. from editing the file by hand
+    BadRange { path: PathBuf, key: String, value: f32 },
+}
+
+impl fmt::Display for CalibrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalibrationError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            CalibrationError::Invalid {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            CalibrationError::BadRange { path, key, value } => write!(
+                f,
+                "{}: `{}` is {}, expected a value above 0.0, run `calibrate sticks` again",
+                path.display(),
+                key,
+                value
+            ),
+        }
+    }
+}
+
+impl Error for CalibrationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CalibrationError::Io { source, .. } => Some(source),
+            CalibrationError::Invalid { .. } | CalibrationError::BadRange { .. } => None,
+        }
+    }
+}
+
+/// Calibration of every controller seen so far, keyed by serial number, e.g.
+///
+/// ```toml
+/// [FVAA12345678.left_stick]
+/// center = [0.012, -0.004]
+/// range = { left = 0.97, right = 0.99, down = 0.96, up = 0.98 }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CalibrationStore {
+    pub devices: BTreeMap<String, DeviceCalibration>,
+}
+
+/// Calibration of a single controller, parts that were never calibrated are left as they are
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeviceCalibration {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub left_stick: Option<StickCalibration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub right_stick: Option<StickCalibration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gyro: Option<GyroCalibration>,
+}
+
+impl DeviceCalibration {
+    /// Corrects a freshly parsed report
+    pub fn apply(&self, report: &mut InputReport) {
+        if let Some(calibration) = &self.left_stick {
+            calibration.apply(&mut report.left_stick);
+        }
+        if let Some(calibration) = &self.right_stick {
+            calibration.apply(&mut report.right_stick);
+        }
+        if let Some(calibration) = &self.gyro {
+            calibration.apply(report);
+        }
+    }
+}
+
+impl CalibrationStore {
+    /// Reads the store at `path`, a missing file is an empty store
+    pub fn load(path: &Path) -> Result<Self, CalibrationError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text: String = fs::read_to_string(path).map_err(|source: io::Error| CalibrationError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&text, path)
+    }
+
+    fn parse(text: &str, path: &Path) -> Result<Self, CalibrationError> {
+        let store: Self = profile::from_toml(text, Some(path)).map_err(|err: ProfileError| match err {
+            ProfileError::Io { source, .. } => CalibrationError::Io {
+                path: path.to_path_buf(),
+                source,
+            },
+            ProfileError::Invalid {
+                line, column, message, ..
+            } => CalibrationError::Invalid {
+                path: path.to_path_buf(),
+                line,
+                column,
+                message,
+            },
+        })?;
+
+        for (serial, device) in &store.devices {
+            for (stick, calibration) in [("left_stick", &device.left_stick), ("right_stick", &device.right_stick)] {
+                let Some(calibration) = calibration else {
+                    continue;
+                };
+                let bad: Option<(&str, f32)> = calibration
+                    .range
+                    .directions()
+                    .into_iter()
+                    .find(|(_, value)| !value.is_finite() || *value <= 0.0);
+                if let Some((direction, value)) = bad {
+                    return Err(CalibrationError::BadRange {
+                        path: path.to_path_buf(),
+                        key: format!("{serial}.{stick}.range.{direction}"),
+                        value,
+                    });
+                }
+            }
+        }
+        Ok(store)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), CalibrationError> {
+        let io_error = |source: io::Error| CalibrationError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let text: String = toml::to_string(self).map_err(|err: toml::ser::Error| io_error(io::Error::other(err)))?;
+        fs::write(path, text).map_err(io_error)
+    }
+
+    /// Calibration of the controller with `serial`, empty if it was never calibrated
+    pub fn device(&self, serial: &str) -> DeviceCalibration {
+        self.devices.get(serial).cloned().unwrap_or_default()
+    }
+
+    pub fn device_mut(&mut self, serial: &str) -> &mut DeviceCalibration {
+        self.devices.entry(serial.to_string()).or_default()
+    }
+}
+
+/// `windecon/calibration.toml` in the user's config directory (`%APPDATA%` on Windows,
+/// `$XDG_CONFIG_HOME` or `~/.config` elsewhere)
+pub fn default_path() -> Option<PathBuf> {
+    let config_dir: PathBuf = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)?
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?
+    };
+    Some(config_dir.join("windecon").join("calibration.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATH: &str = "calibration.toml";
+
+    fn parse(text: &str) -> Result<CalibrationStore, CalibrationError> {
+        CalibrationStore::parse(text, Path::new(PATH))
+    }
+
+    #[test]
+    fn parses_saved_calibrations() {
+        let store: CalibrationStore = parse(
+            r#"
+            [FVAA1.left_stick]
+            center = [0.01, -0.02]
+            range = { left = 0.9, right = 1.0, down = 0.8, up = 0.95 }
+
+            [FVAA1.gyro]
+            bias = [1.0, -2.0, 3.5]
+            "#,
+        )
+        .unwrap();
+        let device: DeviceCalibration = store.device("FVAA1");
+        assert_eq!(device.left_stick.unwrap().range.down, 0.8);
+        assert_eq!(device.right_stick, None);
+        assert_eq!(device.gyro.unwrap().bias, [1.0, -2.0, 3.5]);
+        assert_eq!(store.device("other"), DeviceCalibration::default());
+    }
+
+    #[test]
+    fn rejects_empty_stick_ranges() {
+        let err: CalibrationError = parse(
+            r#"
+            [FVAA1.right_stick]
+            center = [0.0, 0.0]
+            range = { left = 0.9, right = 1.0, down = 0.0, up = 0.95 }
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "calibration.toml: `FVAA1.right_stick.range.down` is 0, expected a value above 0.0, run `calibrate sticks` \
+             again"
+        );
+    }
+
+    #[test]
+    fn locates_format_errors() {
+        let err: CalibrationError = parse("[FVAA1.gyro]\nbias = [1.0, 2.0]\n").unwrap_err();
+        let CalibrationError::Invalid { line, column, .. } = err else {
+            panic!("{err:?}");
+        };
+        assert_eq!((line, column), (2, 8));
+    }
+}
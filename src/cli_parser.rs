@@ -1,9 +1,10 @@
 #![allow(unused_assignments)]
 
-use clap::{ArgMatches, Command, arg, crate_authors, value_parser};
+use clap::{Arg, ArgMatches, Command, arg, crate_authors, value_parser};
 use once_cell::sync::Lazy;
-use std::{cmp, env, error::Error, process};
+use std::{cmp, env, error::Error, path::PathBuf, process, time::Duration};
 use crate::ENV_VARS;
+use crate::deck::DeckButton;
 
 static LONG_VERSION: Lazy<String> = Lazy::new(|| {
     format!(
@@ -17,29 +18,98 @@ static LONG_VERSION: Lazy<String> = Lazy::new(|| {
 
 static SHORT_VERSION: Lazy<String> = Lazy::new(|| format!(" v{}", env!("CARGO_PKG_VERSION"),));
 
+/// Vendor and product ID of the Steam Deck controller
+pub const DECK_VID: u16 = 0x28DE;
+pub const DECK_PID: u16 = 0x1205;
+
+/// `--vid` and `--pid` defaults, written the way they are parsed
+static DEFAULT_VID: Lazy<String> = Lazy::new(|| format!("{DECK_VID:04x}"));
+static DEFAULT_PID: Lazy<String> = Lazy::new(|| format!("{DECK_PID:04x}"));
+
 /// Struct to describe passed command-line arguments
 #[derive(Debug)]
-#[allow(dead_code)]
 pub struct Args {
     pub verbose: u8,
+    pub device: DeviceSelector,
+    pub profile: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    /// Overrides [`crate::calibration::default_path`]
+    pub calibration: Option<PathBuf>,
+    pub command: Subcommand,
+}
+
+/// Which controller to open
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceSelector {
+    pub vid: u16,
+    pub pid: u16,
+    /// Picks between several connected controllers, the first one is used if unset
+    pub serial: Option<String>,
+}
+
+/// Report ID and timeout of the control transfers of `feature send` and `feature get`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureTransfer {
+    pub report_id: u8,
+    pub timeout: Duration,
+}
+
+/// Output format of `dump`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// One JSON object of decoded fields per line
+    Jsonl,
+    /// Decoded fields with a header line
+    Csv,
+    /// Timestamp, sequence number and the raw bytes
+    Hex,
+}
+
+/// What to do once the arguments are parsed
+#[derive(Debug)]
+pub enum Subcommand {
+    /// Map the controller to the emulated outputs, also used when no subcommand is given
+    Run,
+    /// List connected controllers, as JSON with `json`
+    List { json: bool },
+    /// Show the state of the controller live
+    Monitor,
+    /// Send a feature report and print the response
+    FeatureSend { request: Vec<u8>, transfer: FeatureTransfer },
+    /// Read a feature report without sending one first
+    FeatureGet(FeatureTransfer),
+    /// Validate a profile and report the first error
+    ProfileCheck(PathBuf),
+    /// Record a macro from the controller into the profile given with `--profile`
+    RecordMacro { name: String, stop: DeckButton },
+    /// Measure the center and range of both sticks and save them for the connected controller
+    CalibrateSticks,
+    /// Measure the resting offset of the gyro and save it for the connected controller
+    CalibrateGyro,
+    /// Save the raw input reports of the controller to a file
+    Record { path: PathBuf, duration: Option<Duration> },
+    /// Run a recording through the mapping and print the resulting output events
+    Replay { path: PathBuf },
+    /// Stream every input report to stdout, `fields` limits the decoded fields written
+    Dump {
+        format: DumpFormat,
+        fields: Option<Vec<String>>,
+        duration: Option<Duration>,
+        count: Option<u64>,
+    },
+    /// Check that everything needed to use the controller is in place
+    Doctor,
 }
 
 impl Args {
     pub fn parse() -> Result<Args, Box<dyn Error>> {
         // Possible arguments
         // verbose: `get_count("verbose")`
-        let matches: ArgMatches = Self::command()
-            .ignore_errors(true)
-            .arg(
-                arg!(
-                    -v --verbose ... "Turns on verbose logging (Max level of 3, level 3 is not recommended)"
-                )
-                .value_parser(value_parser!(u8).range(0..=3)),
-            )
-            .arg(arg!(
-                --"debug-info" "Prints out debug info about binary"
-            ))
-            .get_matches();
+        // vid, pid: `get_one::<u16>("vid")`, `get_one::<u16>("pid")`
+        // serial: `get_one::<String>("serial")`
+        // profile, config, calibration: `get_one::<PathBuf>(..)`
+        // Subcommands are listed in `Subcommand`, `parse_subcommand` maps them
+        let matches: ArgMatches = Self::command().get_matches();
 
         if matches.get_flag("debug-info") {
             println!("{}", Self::debug_info());
@@ -52,9 +122,93 @@ impl Args {
             matches.get_count("verbose")
         };
 
-        Ok(Args { verbose })
+        Ok(Args {
+            verbose,
+            device: DeviceSelector {
+                vid: *matches.get_one::<u16>("vid").unwrap(),
+                pid: *matches.get_one::<u16>("pid").unwrap(),
+                serial: matches.get_one::<String>("serial").cloned(),
+            },
+            profile: matches.get_one::<PathBuf>("profile").cloned(),
+            config: matches.get_one::<PathBuf>("config").cloned(),
+            calibration: matches.get_one::<PathBuf>("calibration").cloned(),
+            command: Self::parse_subcommand(&matches)?,
+        })
+    }
+
+    fn parse_subcommand(matches: &ArgMatches) -> Result<Subcommand, Box<dyn Error>> {
+        let command: Subcommand = match matches.subcommand() {
+            None | Some(("run", _)) => Subcommand::Run,
+            Some(("list", list_matches)) => Subcommand::List {
+                json: list_matches.get_flag("json"),
+            },
+            Some(("monitor", _)) => Subcommand::Monitor,
+            Some(("feature", feature_matches)) => match feature_matches.subcommand() {
+                Some(("send", send_matches)) => Subcommand::FeatureSend {
+                    request: send_matches.get_many::<u8>("BYTES").unwrap().copied().collect(),
+                    transfer: Self::parse_feature_transfer(send_matches),
+                },
+                Some(("get", get_matches)) => Subcommand::FeatureGet(Self::parse_feature_transfer(get_matches)),
+                _ => unreachable!("`feature` requires a subcommand"),
+            },
+            Some(("profile", profile_matches)) => match profile_matches.subcommand() {
+                Some(("check", check_matches)) => {
+                    Subcommand::ProfileCheck(check_matches.get_one::<PathBuf>("PATH").unwrap().clone())
+                }
+                Some(("record-macro", record_matches)) => Subcommand::RecordMacro {
+                    name: record_matches.get_one::<String>("NAME").unwrap().clone(),
+                    stop: *record_matches.get_one::<DeckButton>("stop").unwrap(),
+                },
+                _ => unreachable!("`profile` requires a subcommand"),
+            },
+            Some(("calibrate", calibrate_matches)) => match calibrate_matches.subcommand() {
+                Some(("sticks", _)) => Subcommand::CalibrateSticks,
+                Some(("gyro", _)) => Subcommand::CalibrateGyro,
+                _ => unreachable!("`calibrate` requires a subcommand"),
+            },
+            Some(("record", record_matches)) => Subcommand::Record {
+                path: record_matches.get_one::<PathBuf>("PATH").unwrap().clone(),
+                duration: record_matches.get_one::<Duration>("duration").copied(),
+            },
+            Some(("replay", replay_matches)) => Subcommand::Replay {
+                path: replay_matches.get_one::<PathBuf>("PATH").unwrap().clone(),
+            },
+            Some(("dump", dump_matches)) => Subcommand::Dump {
+                format: match dump_matches.get_one::<String>("format").unwrap().as_str() {
+                    "csv" => DumpFormat::Csv,
+                    "hex" => DumpFormat::Hex,
+                    _ => DumpFormat::Jsonl,
+                },
+                fields: dump_matches.get_many::<String>("fields").map(|fields| fields.cloned().collect()),
+                duration: dump_matches.get_one::<Duration>("duration").copied(),
+                count: dump_matches.get_one::<u64>("count").copied(),
+            },
+            Some(("doctor", _)) => Subcommand::Doctor,
+            Some((name, _)) => unreachable!("unknown subcommand `{name}`"),
+        };
+        Ok(command)
+    }
+
+    /// `--report-id` and `--timeout` of `feature send` and `feature get`
+    fn feature_transfer_args() -> [Arg; 2] {
+        [
+            arg!(--"report-id" <ID> "Report ID of the control transfers, in hex")
+                .default_value("0")
+                .value_parser(parse_hex_u8),
+            arg!(--timeout <MS> "Timeout of each control transfer in milliseconds")
+                .default_value("100")
+                .value_parser(value_parser!(u64).range(1..)),
+        ]
+    }
+
+    fn parse_feature_transfer(matches: &ArgMatches) -> FeatureTransfer {
+        FeatureTransfer {
+            report_id: *matches.get_one::<u8>("report-id").unwrap(),
+            timeout: Duration::from_millis(*matches.get_one::<u64>("timeout").unwrap()),
+        }
     }
 
+    /// The full command line interface, every argument and subcommand included
     pub fn command() -> Command {
         // crate_name!() = env!("CARGO_PKG_NAME");
         // crate_version!() = env!("CARGO_PKG_VERSION");
@@ -66,7 +220,123 @@ impl Args {
             .about(env!("CARGO_PKG_DESCRIPTION"))
             .long_about(env!("CARGO_PKG_DESCRIPTION"))
             .version(SHORT_VERSION.as_str())
-            .long_version(LONG_VERSION.as_str());
+            .long_version(LONG_VERSION.as_str())
+            .arg(
+                arg!(
+                    -v --verbose ... "Turns on verbose logging (Max level of 3, level 3 is not recommended)"
+                )
+                .value_parser(value_parser!(u8).range(0..=3))
+                .global(true),
+            )
+            .arg(arg!(
+                --"debug-info" "Prints out debug info about binary"
+            ))
+            .arg(
+                arg!(--vid <VID> "Vendor ID of the controller, in hex")
+                    .default_value(DEFAULT_VID.as_str())
+                    .value_parser(parse_hex_u16)
+                    .global(true),
+            )
+            .arg(
+                arg!(--pid <PID> "Product ID of the controller, in hex")
+                    .default_value(DEFAULT_PID.as_str())
+                    .value_parser(parse_hex_u16)
+                    .global(true),
+            )
+            .arg(arg!(--serial <SERIAL> "Serial number of the controller to use when several are connected").global(true))
+            .arg(
+                arg!(
+                    --profile <PATH> "Loads bindings and settings from a TOML profile"
+                )
+                .value_parser(value_parser!(PathBuf))
+                .global(true),
+            )
+            .arg(
+                arg!(
+                    --config <PATH> "Switches profiles based on the running application, ignored if --profile is set"
+                )
+                .value_parser(value_parser!(PathBuf))
+                .global(true),
+            )
+            .arg(
+                arg!(--calibration <PATH> "Calibration file to use instead of the one in the config directory")
+                    .value_parser(value_parser!(PathBuf))
+                    .global(true),
+            )
+            .subcommand(Command::new("run").about("Maps the controller to the emulated outputs (default)"))
+            .subcommand(
+                Command::new("list")
+                    .about("Lists connected controllers with their HID interfaces")
+                    .arg(arg!(--json "Prints the list as JSON")),
+            )
+            .subcommand(Command::new("monitor").about("Shows the state of the controller live"))
+            .subcommand(
+                Command::new("feature")
+                    .about("Talks to the controller with raw feature reports")
+                    .subcommand_required(true)
+                    .subcommand(
+                        Command::new("send")
+                            .about("Sends a feature report and prints the response")
+                            .arg(arg!(<BYTES> ... "Report bytes in hex, e.g. `85 00`").value_parser(parse_hex_u8))
+                            .args(Self::feature_transfer_args()),
+                    )
+                    .subcommand(
+                        Command::new("get")
+                            .about("Reads a feature report without sending one first")
+                            .args(Self::feature_transfer_args()),
+                    ),
+            )
+            .subcommand(
+                Command::new("profile")
+                    .about("Manages profiles")
+                    .subcommand_required(true)
+                    .subcommand(
+                        Command::new("check")
+                            .about("Validates a profile without connecting to the controller")
+                            .arg(arg!(<PATH> "Profile to validate").value_parser(value_parser!(PathBuf))),
+                    )
+                    .subcommand(
+                        Command::new("record-macro")
+                            .about("Records a macro from the controller and saves it into the profile given with --profile")
+                            .arg(arg!(<NAME> "Name of the macro, an existing macro with this name is replaced"))
+                            .arg(
+                                arg!(--stop <BUTTON> "Deck button that ends the recording, it is not recorded itself")
+                                    .default_value("qam")
+                                    .value_parser(|s: &str| s.parse::<DeckButton>()),
+                            ),
+                    ),
+            )
+            .subcommand(
+                Command::new("calibrate")
+                    .about("Calibrates the connected controller, results are saved per serial number")
+                    .subcommand_required(true)
+                    .subcommand(Command::new("sticks").about("Measures the center and range of both sticks"))
+                    .subcommand(Command::new("gyro").about("Measures the gyro drift while the controller rests")),
+            )
+            .subcommand(
+                Command::new("record")
+                    .about("Saves the raw input reports of the controller to a file, until Ctrl+C or --duration")
+                    .arg(arg!(<PATH> "File to write the recording to").value_parser(value_parser!(PathBuf)))
+                    .arg(arg!(--duration <SECONDS> "Stops recording after this many seconds").value_parser(parse_seconds)),
+            )
+            .subcommand(
+                Command::new("replay")
+                    .about("Runs a recording through the profile and prints the output events, without emulating anything")
+                    .arg(arg!(<PATH> "Recording made with `record`").value_parser(value_parser!(PathBuf))),
+            )
+            .subcommand(
+                Command::new("dump")
+                    .about("Streams every input report to stdout, until Ctrl+C, --duration or --count")
+                    .arg(
+                        arg!(--format <FORMAT> "Output format, `hex` writes the raw bytes instead of decoded fields")
+                            .default_value("jsonl")
+                            .value_parser(["jsonl", "csv", "hex"]),
+                    )
+                    .arg(arg!(--fields <FIELDS> "Comma separated decoded fields to write, all by default").value_delimiter(','))
+                    .arg(arg!(--duration <SECONDS> "Stops after this many seconds").value_parser(parse_seconds))
+                    .arg(arg!(--count <N> "Stops after this many reports").value_parser(value_parser!(u64).range(1..))),
+            )
+            .subcommand(Command::new("doctor").about("Checks that everything needed to use the controller is in place"));
         cmd
     }
 
@@ -124,3 +394,60 @@ impl Args {
         info_text
     }
 }
+
+/// Parses a hex ID with or without a `0x` prefix, e.g. `28de`
+fn parse_hex_u8(s: &str) -> Result<u8, String> {
+    let digits: &str = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+    u8::from_str_radix(digits, 16).map_err(|err| format!("`{s}` is not a hex byte: {err}"))
+}
+
+/// Parses a positive number of seconds, e.g. `2.5`
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    let seconds: f64 = s.parse().map_err(|err| format!("`{s}` is not a number: {err}"))?;
+    if seconds <= 0.0 {
+        return Err(format!("expected a positive number of seconds, found {s}"));
+    }
+    Duration::try_from_secs_f64(seconds).map_err(|err| format!("`{s}` is not a usable duration: {err}"))
+}
+
+fn parse_hex_u16(s: &str) -> Result<u16, String> {
+    let digits: &str = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+    u16::from_str_radix(digits, 16).map_err(|err| format!("`{s}` is not a 16 bit hex number: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_is_valid() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn parses_positive_seconds() {
+        assert_eq!(parse_seconds("2.5"), Ok(Duration::from_millis(2500)));
+        assert_eq!(parse_seconds("0.001"), Ok(Duration::from_millis(1)));
+        for invalid in ["0", "0.0", "-1", "-0", "nan", "inf", "1e30", "abc"] {
+            assert!(parse_seconds(invalid).is_err(), "`{invalid}` was accepted");
+        }
+    }
+
+    #[test]
+    fn defaults_to_deck_controller() {
+        let matches: ArgMatches = Args::command().try_get_matches_from(["windecon"]).unwrap();
+        assert_eq!(matches.get_one::<u16>("vid"), Some(&DECK_VID));
+        assert_eq!(matches.get_one::<u16>("pid"), Some(&DECK_PID));
+    }
+
+    #[test]
+    fn help_lists_every_subcommand_and_argument() {
+        let help: String = Args::command().render_help().to_string();
+        for name in ["run", "list", "monitor", "feature", "profile", "calibrate", "record", "dump", "doctor"] {
+            assert!(help.contains(name), "`{name}` is missing from:\n{help}");
+        }
+        for flag in ["--verbose", "--vid", "--pid", "--serial", "--profile", "--config", "--calibration"] {
+            assert!(help.contains(flag), "`{flag}` is missing from:\n{help}");
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::calibration::{CalibrationStore, DeviceCalibration, GyroCalibrator, StickCalibrator};
+use crate::cli_parser::Args;
+use crate::commands::{calibration_path, open_report_channel};
+use crate::deck::{DeckButton, InputReport};
+use crate::hid::HidDevice;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs `calibrate sticks`: samples both sticks at rest, then while they are rotated along their
+/// edges until A is pressed, and saves the result under the controller's serial number
+pub fn calibrate_sticks(args: &Args) -> Result<(), Box<dyn Error>> {
+    let path: PathBuf = calibration_path(args)?;
+    let (mut dev, receiver, serial) = open_for_calibration(args)?;
+
+    let mut left: StickCalibrator = StickCalibrator::new();
+    let mut right: StickCalibrator = StickCalibrator::new();
+    // Closed before giving up on a failed sample so the controller isn't left open
+    let sampled: Result<(), Box<dyn Error>> = sample_sticks(&receiver, &mut left, &mut right);
+    dev.close()?;
+    sampled?;
+
+    let mut store: CalibrationStore = CalibrationStore::load(&path)?;
+    let device: &mut DeviceCalibration = store.device_mut(&serial);
+    device.left_stick = Some(left.finish().map_err(|err| format!("Left stick: {err}"))?);
+    device.right_stick = Some(right.finish().map_err(|err| format!("Right stick: {err}"))?);
+    store.save(&path)?;
+    println!("Saved the calibration of {} to {}", serial, path.display());
+    Ok(())
+}
+
+/// Runs `calibrate gyro`: averages the gyro for a few seconds while the controller rests and saves
+/// the offset under the controller's serial number
+pub fn calibrate_gyro(args: &Args) -> Result<(), Box<dyn Error>> {
+    let path: PathBuf = calibration_path(args)?;
+    let (mut dev, receiver, serial) = open_for_calibration(args)?;
+    let mut calibrator: GyroCalibrator = GyroCalibrator::new();
+    let sampled: Result<(), Box<dyn Error>> = sample_gyro(&receiver, &mut calibrator);
+    dev.close()?;
+    sampled?;
+
+    let mut store: CalibrationStore = CalibrationStore::load(&path)?;
+    store.device_mut(&serial).gyro = Some(calibrator.finish()?);
+    store.save(&path)?;
+    println!("Saved the gyro calibration of {} to {}", serial, path.display());
+    Ok(())
+}
+
+/// Samples the sticks at rest, then along their edges until A is pressed
+fn sample_sticks(
+    receiver: &mpsc::Receiver<InputReport>,
+    left: &mut StickCalibrator,
+    right: &mut StickCalibrator,
+) -> Result<(), Box<dyn Error>> {
+    let timeout: Duration = Duration::from_secs(1);
+
+    println!("Let go of both sticks...");
+    thread::sleep(Duration::from_secs(2));
+    receiver.try_iter().for_each(drop);
+    let sampling_until: Instant = Instant::now() + Duration::from_secs(2);
+    while Instant::now() < sampling_until {
+        let report: InputReport = receiver.recv_timeout(timeout)?;
+        left.add_center_sample(&report.left_stick);
+        right.add_center_sample(&report.right_stick);
+    }
+
+    println!("Slowly rotate both sticks along their edges a few times, then press A");
+    loop {
+        let report: InputReport = receiver.recv_timeout(timeout)?;
+        if report.is_pressed(DeckButton::A) {
+            return Ok(());
+        }
+        left.add_range_sample(&report.left_stick);
+        right.add_range_sample(&report.right_stick);
+    }
+}
+
+/// Samples the gyro for a few seconds once the controller was put down
+fn sample_gyro(receiver: &mpsc::Receiver<InputReport>, calibrator: &mut GyroCalibrator) -> Result<(), Box<dyn Error>> {
+    let timeout: Duration = Duration::from_secs(1);
+
+    println!("Put the controller down on a flat surface and don't touch it...");
+    thread::sleep(Duration::from_secs(3));
+    receiver.try_iter().for_each(drop);
+    let sampling_until: Instant = Instant::now() + Duration::from_secs(3);
+    while Instant::now() < sampling_until {
+        calibrator.add_sample(receiver.recv_timeout(timeout)?.gyro);
+    }
+    Ok(())
+}
+
+/// Opens the controller like [`open_report_channel`], along with its serial number
+fn open_for_calibration(args: &Args) -> Result<(HidDevice, mpsc::Receiver<InputReport>, String), Box<dyn Error>> {
+    let (mut dev, receiver) = open_report_channel(args)?;
+    let Some(serial) = dev.serial_number().map(str::to_string) else {
+        dev.close()?;
+        return Err("The controller didn't report a serial number".into());
+    };
+    Ok((dev, receiver, serial))
+}
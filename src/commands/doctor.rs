@@ -0,0 +1,164 @@
+use crate::apps;
+use crate::calibration::{CalibrationStore, DeviceCalibration};
+use crate::cli_parser::Args;
+use crate::commands::{calibration_path, open_report_channel};
+use crate::deck::InputReport;
+use crate::hid::SerialNotFound;
+use crate::profile;
+use rusb::{Context, Error as UsbError, UsbContext};
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Outcome of one check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// Prints the checks as they run and counts the failures
+#[derive(Default)]
+struct Checklist {
+    failures: usize,
+}
+
+impl Checklist {
+    fn report(&mut self, status: Status, message: impl AsRef<str>) {
+        let label: &str = match status {
+            Status::Ok => "[ OK ]",
+            Status::Warn => "[WARN]",
+            Status::Fail => {
+                self.failures += 1;
+                "[FAIL]"
+            }
+        };
+        println!("{} {}", label, message.as_ref());
+    }
+
+    fn hint(&self, message: impl AsRef<str>) {
+        println!("       {}", message.as_ref());
+    }
+}
+
+/// Runs `doctor`: checks the given profile and config, that the controller can be found, opened
+/// and read, and whether it is calibrated
+pub fn doctor(args: &Args) -> Result<(), Box<dyn Error>> {
+    let mut checks: Checklist = Checklist::default();
+
+    if let Some(path) = &args.profile {
+        match profile::load(path) {
+            Ok(profile) => checks.report(Status::Ok, format!("Profile `{}` is valid", profile.name)),
+            Err(err) => checks.report(Status::Fail, format!("Profile {}: {}", path.display(), err)),
+        }
+    }
+    if let Some(path) = &args.config {
+        match apps::load_config(path) {
+            Ok(config) => checks.report(Status::Ok, format!("Config has {} rule(s)", config.rules.len())),
+            Err(err) => checks.report(Status::Fail, format!("Config {}: {}", path.display(), err)),
+        }
+    }
+
+    check_controller(args, &mut checks);
+
+    if checks.failures > 0 {
+        return Err(format!("{} check(s) failed", checks.failures).into());
+    }
+    Ok(())
+}
+
+fn check_controller(args: &Args, checks: &mut Checklist) {
+    let (vid, pid) = (args.device.vid, args.device.pid);
+    let context: Context = match Context::new() {
+        Ok(context) => context,
+        Err(err) => {
+            checks.report(Status::Fail, format!("Can't initialize libusb: {err}"));
+            return;
+        }
+    };
+    let connected: usize = match context.devices() {
+        Ok(devices) => devices
+            .iter()
+            .filter_map(|device| device.device_descriptor().ok())
+            .filter(|descriptor| descriptor.vendor_id() == vid && descriptor.product_id() == pid)
+            .count(),
+        Err(err) => {
+            checks.report(Status::Fail, format!("Can't list USB devices: {err}"));
+            return;
+        }
+    };
+    if connected == 0 {
+        checks.report(Status::Fail, format!("No controller with ID {vid:04x}:{pid:04x} is connected"));
+        checks.hint("Check --vid and --pid, `windecon list` shows what is connected");
+        return;
+    }
+    checks.report(Status::Ok, format!("{connected} controller(s) with ID {vid:04x}:{pid:04x} connected"));
+
+    let (mut dev, receiver) = match open_report_channel(args) {
+        Ok(opened) => opened,
+        Err(err) => {
+            checks.report(Status::Fail, format!("Can't open the controller: {err}"));
+            match err.downcast_ref::<UsbError>() {
+                Some(UsbError::Access) => checks.hint("No permission, on Linux add a udev rule for the controller"),
+                Some(UsbError::NotSupported) => checks.hint("On Windows the interface needs the WinUSB driver"),
+                Some(UsbError::Busy) => checks.hint("Another program is using the controller"),
+                _ if err.is::<SerialNotFound>() => {
+                    checks.hint("`windecon list` shows the serial numbers of the connected controllers")
+                }
+                _ => {}
+            }
+            return;
+        }
+    };
+    let serial: Option<String> = dev.serial_number().map(str::to_string);
+    match &serial {
+        Some(serial) => checks.report(Status::Ok, format!("Opened the controller with serial number {serial}")),
+        None => checks.report(Status::Warn, "The controller has no serial number, calibrations can't be saved"),
+    }
+
+    let report: Option<InputReport> = receiver.recv_timeout(Duration::from_secs(2)).ok();
+    let _ = dev.close();
+    match report {
+        Some(report) => checks.report(Status::Ok, format!("Receiving input reports (sequence {})", report.sequence)),
+        None => {
+            checks.report(Status::Fail, "No input reports within 2 seconds");
+            checks.hint("`windecon record` with -vv shows whether anything arrives at all");
+        }
+    }
+
+    if let Some(serial) = serial {
+        check_calibration(args, &serial, checks);
+    }
+}
+
+fn check_calibration(args: &Args, serial: &str, checks: &mut Checklist) {
+    let path: PathBuf = match calibration_path(args) {
+        Ok(path) => path,
+        Err(err) => {
+            checks.report(Status::Warn, err.to_string());
+            return;
+        }
+    };
+    let calibration: DeviceCalibration = match CalibrationStore::load(&path) {
+        Ok(store) => store.device(serial),
+        Err(err) => {
+            checks.report(Status::Fail, format!("Calibration {}: {}", path.display(), err));
+            return;
+        }
+    };
+    let missing: Vec<&str> = [
+        ("left stick", calibration.left_stick.is_some()),
+        ("right stick", calibration.right_stick.is_some()),
+        ("gyro", calibration.gyro.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(part, calibrated)| (!calibrated).then_some(part))
+    .collect();
+    if missing.is_empty() {
+        checks.report(Status::Ok, "Sticks and gyro are calibrated");
+    } else {
+        checks.report(Status::Warn, format!("Not calibrated: {}", missing.join(", ")));
+        checks.hint("Run `windecon calibrate sticks` and `windecon calibrate gyro`");
+    }
+}
@@ -0,0 +1,273 @@
+use crate::cli_parser::{Args, DumpFormat};
+use crate::commands::{hex_string, new_device};
+use crate::deck::{DeckButton, InputReport};
+use crate::hid::HidDevice;
+use crate::prelude::*;
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::io::{self, StdoutLock, Write};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Every decoded field `dump` can write, in output order. Stick, trackpad and trigger values are
+/// raw counts, accel is in g and gyro in °/s around X (pitch), Y (roll) and Z (yaw).
+const FIELDS: [&str; 29] = [
+    "timestamp_us",
+    "sequence",
+    "buttons",
+    "left_stick_x",
+    "left_stick_y",
+    "left_stick_touched",
+    "right_stick_x",
+    "right_stick_y",
+    "right_stick_touched",
+    "left_pad_x",
+    "left_pad_y",
+    "left_pad_pressure",
+    "left_pad_touched",
+    "right_pad_x",
+    "right_pad_y",
+    "right_pad_pressure",
+    "right_pad_touched",
+    "left_trigger",
+    "right_trigger",
+    "accel_x",
+    "accel_y",
+    "accel_z",
+    "gyro_x",
+    "gyro_y",
+    "gyro_z",
+    "orientation_w",
+    "orientation_x",
+    "orientation_y",
+    "orientation_z",
+];
+
+/// Value of one decoded field
+enum FieldValue {
+    Int(i64),
+    Float(f32),
+    Bool(bool),
+    Buttons(Vec<DeckButton>),
+}
+
+impl FieldValue {
+    fn of(report: &InputReport, field: &str) -> Self {
+        let [accel_x, accel_y, accel_z] = report.accel_g();
+        let [gyro_x, gyro_y, gyro_z] = report.gyro_dps();
+        match field {
+            "timestamp_us" => FieldValue::Int(report.timestamp.as_micros() as i64),
+            "sequence" => FieldValue::Int(report.sequence as i64),
+            "buttons" => FieldValue::Buttons(report.pressed_buttons().collect()),
+            "left_stick_x" => FieldValue::Int(report.left_stick.x as i64),
+            "left_stick_y" => FieldValue::Int(report.left_stick.y as i64),
+            "left_stick_touched" => FieldValue::Bool(report.left_stick.touched),
+            "right_stick_x" => FieldValue::Int(report.right_stick.x as i64),
+            "right_stick_y" => FieldValue::Int(report.right_stick.y as i64),
+            "right_stick_touched" => FieldValue::Bool(report.right_stick.touched),
+            "left_pad_x" => FieldValue::Int(report.left_pad.x as i64),
+            "left_pad_y" => FieldValue::Int(report.left_pad.y as i64),
+            "left_pad_pressure" => FieldValue::Int(report.left_pad.pressure as i64),
+            "left_pad_touched" => FieldValue::Bool(report.left_pad.touched),
+            "right_pad_x" => FieldValue::Int(report.right_pad.x as i64),
+            "right_pad_y" => FieldValue::Int(report.right_pad.y as i64),
+            "right_pad_pressure" => FieldValue::Int(report.right_pad.pressure as i64),
+            "right_pad_touched" => FieldValue::Bool(report.right_pad.touched),
+            "left_trigger" => FieldValue::Int(report.left_trigger as i64),
+            "right_trigger" => FieldValue::Int(report.right_trigger as i64),
+            "accel_x" => FieldValue::Float(accel_x),
+            "accel_y" => FieldValue::Float(accel_y),
+            "accel_z" => FieldValue::Float(accel_z),
+            "gyro_x" => FieldValue::Float(gyro_x),
+            "gyro_y" => FieldValue::Float(gyro_y),
+            "gyro_z" => FieldValue::Float(gyro_z),
+            "orientation_w" => FieldValue::Int(report.orientation[0] as i64),
+            "orientation_x" => FieldValue::Int(report.orientation[1] as i64),
+            "orientation_y" => FieldValue::Int(report.orientation[2] as i64),
+            "orientation_z" => FieldValue::Int(report.orientation[3] as i64),
+            _ => unreachable!("fields are checked against `FIELDS`"),
+        }
+    }
+
+    fn json(&self) -> Value {
+        match self {
+            FieldValue::Int(value) => Value::from(*value),
+            // Goes through the shortest decimal text so `0.1` isn't written as `0.10000000149011612`
+            FieldValue::Float(value) => value.to_string().parse::<f64>().map_or(Value::Null, Value::from),
+            FieldValue::Bool(value) => Value::from(*value),
+            FieldValue::Buttons(buttons) => serde_json::to_value(buttons).unwrap_or_default(),
+        }
+    }
+
+    /// Buttons are separated by spaces, so no value ever needs quoting
+    fn csv(&self) -> String {
+        match self {
+            FieldValue::Int(value) => value.to_string(),
+            FieldValue::Float(value) => value.to_string(),
+            FieldValue::Bool(value) => value.to_string(),
+            FieldValue::Buttons(buttons) => buttons
+                .iter()
+                .filter_map(|button| serde_json::to_value(button).ok()?.as_str().map(str::to_string))
+                .collect::<Vec<String>>()
+                .join(" "),
+        }
+    }
+}
+
+/// Runs `dump`: writes every input report to stdout until `duration` passed, `count` reports
+/// were written or the process is stopped
+pub fn dump(
+    args: &Args,
+    format: DumpFormat,
+    fields: Option<&[String]>,
+    duration: Option<Duration>,
+    count: Option<u64>,
+) -> Result<(), Box<dyn Error>> {
+    let fields: Vec<&'static str> = select_fields(format, fields)?;
+
+    let (sender, receiver) = mpsc::channel::<(Duration, Vec<u8>)>();
+    let mut dev: HidDevice = new_device(args)?;
+    let started: Instant = Instant::now();
+    dev.set_on_input_received(move |data| {
+        let _ = sender.send((started.elapsed(), data));
+    });
+    dev.open()?;
+
+    let result: Result<(), Box<dyn Error>> =
+        stream(&receiver, &mut io::stdout().lock(), format, &fields, duration, count);
+    dev.close()?;
+    result
+}
+
+fn stream(
+    receiver: &mpsc::Receiver<(Duration, Vec<u8>)>,
+    out: &mut StdoutLock<'_>,
+    format: DumpFormat,
+    fields: &[&str],
+    duration: Option<Duration>,
+    count: Option<u64>,
+) -> Result<(), Box<dyn Error>> {
+    if format == DumpFormat::Csv {
+        writeln!(out, "{}", fields.join(","))?;
+    }
+
+    let mut written: u64 = 0;
+    while count.is_none_or(|count| written < count) {
+        let Ok((timestamp, data)) = receiver.recv_timeout(Duration::from_secs(1)) else {
+            return Err(format!("The controller stopped sending reports after {written} report(s)").into());
+        };
+        if duration.is_some_and(|duration| timestamp >= duration) {
+            break;
+        }
+        let report: Result<InputReport, _> = InputReport::parse(&data, timestamp);
+        let line: String = match (format, report) {
+            (DumpFormat::Hex, report) => {
+                let sequence: String = report.map_or("-".to_string(), |report| report.sequence.to_string());
+                format!("{} {} {}", timestamp.as_micros(), sequence, hex_string(&data))
+            }
+            (_, Err(err)) => {
+                trace!("Skipping input report: {}", err);
+                continue;
+            }
+            (DumpFormat::Jsonl, Ok(report)) => json_line(&report, fields),
+            (DumpFormat::Csv, Ok(report)) => csv_line(&report, fields),
+        };
+        match writeln!(out, "{line}") {
+            Ok(()) => written += 1,
+            // Whoever reads the output is done, e.g. `windecon dump | head`
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(())
+}
+
+/// The fields asked for with `--fields`, each once and in the order first asked for, or all of them
+fn select_fields(format: DumpFormat, names: Option<&[String]>) -> Result<Vec<&'static str>, String> {
+    let Some(names) = names else {
+        return Ok(FIELDS.to_vec());
+    };
+    if format == DumpFormat::Hex {
+        return Err("--fields only applies to the jsonl and csv formats".into());
+    }
+    let mut fields: Vec<&'static str> = Vec::new();
+    for name in names {
+        let field: &'static str = FIELDS
+            .into_iter()
+            .find(|field| field == name)
+            .ok_or_else(|| format!("Unknown field `{name}`, expected one of: {}", FIELDS.join(", ")))?;
+        if !fields.contains(&field) {
+            fields.push(field);
+        }
+    }
+    Ok(fields)
+}
+
+/// One JSON object with `fields` as keys, in order
+fn json_line(report: &InputReport, fields: &[&str]) -> String {
+    let object: Map<String, Value> = fields
+        .iter()
+        .map(|field| (field.to_string(), FieldValue::of(report, field).json()))
+        .collect();
+    Value::Object(object).to_string()
+}
+
+fn csv_line(report: &InputReport, fields: &[&str]) -> String {
+    let values: Vec<String> = fields.iter().map(|field| FieldValue::of(report, field).csv()).collect();
+    values.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn report() -> InputReport {
+        let mut report: InputReport = InputReport::default();
+        report.timestamp = Duration::from_micros(1500);
+        report.sequence = 7;
+        report.set_button(DeckButton::A, true);
+        report.set_button(DeckButton::L4, true);
+        report.left_stick.x = -200;
+        report.left_pad.touched = true;
+        report.accel = [0, 0, 1638];
+        report
+    }
+
+    #[test]
+    fn selects_fields_once_in_order() {
+        assert_eq!(select_fields(DumpFormat::Jsonl, None).unwrap(), FIELDS);
+        let fields: Vec<String> = names(&["sequence", "buttons", "sequence", "accel_z", "buttons"]);
+        assert_eq!(
+            select_fields(DumpFormat::Csv, Some(&fields)).unwrap(),
+            ["sequence", "buttons", "accel_z"]
+        );
+        assert!(select_fields(DumpFormat::Jsonl, Some(&names(&["nope"]))).is_err());
+        assert!(select_fields(DumpFormat::Hex, Some(&names(&["sequence"]))).is_err());
+    }
+
+    #[test]
+    fn writes_json_objects_in_field_order() {
+        let fields: [&str; 6] = ["sequence", "timestamp_us", "buttons", "left_stick_x", "left_pad_touched", "accel_z"];
+        assert_eq!(
+            json_line(&report(), &fields),
+            concat!(
+                r#"{"sequence":7,"timestamp_us":1500,"buttons":["a","l4"],"#,
+                r#""left_stick_x":-200,"left_pad_touched":true,"accel_z":0.099975586}"#
+            )
+        );
+        let line: Value = serde_json::from_str(&json_line(&report(), &FIELDS)).unwrap();
+        assert_eq!(line.as_object().unwrap().keys().collect::<Vec<&String>>(), FIELDS);
+    }
+
+    #[test]
+    fn writes_csv_rows() {
+        assert_eq!(
+            csv_line(&report(), &["sequence", "buttons", "left_pad_touched", "accel_z"]),
+            "7,a l4,true,0.099975586"
+        );
+    }
+}
@@ -0,0 +1,37 @@
+use crate::cli_parser::{Args, FeatureTransfer};
+use crate::commands::{hex_string, new_device};
+use crate::deck::FeatureResponse;
+use crate::hid::HidDevice;
+use std::error::Error;
+
+/// Runs `feature send`: sends `request` as a feature report and prints the response
+pub fn feature_send(args: &Args, request: &[u8], transfer: FeatureTransfer) -> Result<(), Box<dyn Error>> {
+    with_device(args, |dev| {
+        dev.send_feature_report(request, transfer.report_id, transfer.timeout)?;
+        dev.get_feature_report(transfer.report_id, transfer.timeout)
+    })
+}
+
+/// Runs `feature get`: reads a feature report without sending one, e.g. the response to a
+/// command sent by another program
+pub fn feature_get(args: &Args, transfer: FeatureTransfer) -> Result<(), Box<dyn Error>> {
+    with_device(args, |dev| dev.get_feature_report(transfer.report_id, transfer.timeout))
+}
+
+/// Opens the controller for `transfer`, closes it again and prints the response, decoded if the
+/// command is known and in hex otherwise
+fn with_device(
+    args: &Args,
+    transfer: impl FnOnce(&HidDevice) -> Result<(usize, Vec<u8>), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut dev: HidDevice = new_device(args)?;
+    dev.open()?;
+    let response: Result<(usize, Vec<u8>), Box<dyn Error>> = transfer(&dev);
+    dev.close()?;
+    let (_, response) = response?;
+    match FeatureResponse::parse(&response) {
+        Some(decoded) => println!("{decoded}"),
+        None => println!("{}", hex_string(&response)),
+    }
+    Ok(())
+}
@@ -0,0 +1,206 @@
+use crate::cli_parser::{Args, DECK_PID, DECK_VID};
+use crate::hid::{INPUT_REPORT_LEN, InputInterface, find_input_interface, input_endpoint};
+use rusb::{
+    ConfigDescriptor, Context, Device, DeviceDescriptor, DeviceHandle, Direction, EndpointDescriptor,
+    InterfaceDescriptor, TransferType, UsbContext,
+};
+use serde::Serialize;
+use std::error::Error;
+
+/// Controllers `list` looks for besides the one picked with `--vid` and `--pid`
+const KNOWN_CONTROLLERS: [(u16, u16, &str); 1] = [(DECK_VID, DECK_PID, "Steam Deck")];
+
+#[derive(Debug, Serialize)]
+struct DeviceInfo {
+    bus: u8,
+    address: u8,
+    vendor_id: u16,
+    product_id: u16,
+    /// Name from `KNOWN_CONTROLLERS`, `None` for other devices picked with `--vid` and `--pid`
+    known_as: Option<&'static str>,
+    product: Option<String>,
+    serial: Option<String>,
+    /// Why the device couldn't be opened, the strings and kernel drivers are unknown then
+    open_error: Option<String>,
+    /// Whether `HidDevice::open` would use this device with the current `--vid`, `--pid` and
+    /// `--serial`
+    selected: bool,
+    interfaces: Vec<InterfaceInfo>,
+}
+
+#[derive(Debug, Serialize)]
+struct InterfaceInfo {
+    number: u8,
+    setting: u8,
+    class: u8,
+    subclass: u8,
+    protocol: u8,
+    /// `None` if the platform can't tell, e.g. on Windows
+    kernel_driver: Option<bool>,
+    endpoints: Vec<EndpointInfo>,
+    /// Whether `HidDevice::open` reads the input from this interface
+    picked: bool,
+    reason: String,
+}
+
+#[derive(Debug, Serialize)]
+struct EndpointInfo {
+    address: u8,
+    direction: &'static str,
+    transfer_type: &'static str,
+    max_packet_size: u16,
+}
+
+/// Runs `list`: prints every connected controller with a known ID or the one given with `--vid`
+/// and `--pid`, with its HID interfaces and the one `HidDevice::open` reads from
+pub fn list(args: &Args, json: bool) -> Result<(), Box<dyn Error>> {
+    let context: Context = Context::new()?;
+    let mut devices: Vec<DeviceInfo> = Vec::new();
+    for device in context.devices()?.iter() {
+        let Ok(descriptor) = device.device_descriptor() else {
+            continue;
+        };
+        let id: (u16, u16) = (descriptor.vendor_id(), descriptor.product_id());
+        let known_as: Option<&'static str> = KNOWN_CONTROLLERS
+            .iter()
+            .find(|(vid, pid, _)| (*vid, *pid) == id)
+            .map(|(_, _, name)| *name);
+        if known_as.is_none() && id != (args.device.vid, args.device.pid) {
+            continue;
+        }
+        devices.push(device_info(&device, &descriptor, known_as));
+    }
+
+    // `open` takes the first matching device that opens and has the right serial number
+    if let Some(device) = devices.iter_mut().find(|device| {
+        (device.vendor_id, device.product_id) == (args.device.vid, args.device.pid)
+            && device.open_error.is_none()
+            && (args.device.serial.is_none() || args.device.serial == device.serial)
+    }) {
+        device.selected = true;
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&devices)?);
+    } else if devices.is_empty() {
+        println!("No controller with ID {:04x}:{:04x} is connected", args.device.vid, args.device.pid);
+    } else {
+        for device in &devices {
+            print_device(device);
+        }
+    }
+    Ok(())
+}
+
+fn device_info(device: &Device<Context>, descriptor: &DeviceDescriptor, known_as: Option<&'static str>) -> DeviceInfo {
+    let handle: Result<DeviceHandle<Context>, rusb::Error> = device.open();
+    let config: Result<ConfigDescriptor, rusb::Error> = device.config_descriptor(0);
+    let picked: Option<InputInterface> =
+        config.as_ref().ok().and_then(|config| find_input_interface(config, INPUT_REPORT_LEN as u16));
+
+    let mut interfaces: Vec<InterfaceInfo> = Vec::new();
+    if let Ok(config) = &config {
+        for interface_desc in config.interfaces().flat_map(|interface| interface.descriptors()) {
+            let number: u8 = interface_desc.interface_number();
+            let setting: u8 = interface_desc.setting_number();
+            let is_picked: bool = picked.is_some_and(|picked| (picked.interface, picked.setting) == (number, setting));
+            interfaces.push(InterfaceInfo {
+                number,
+                setting,
+                class: interface_desc.class_code(),
+                subclass: interface_desc.sub_class_code(),
+                protocol: interface_desc.protocol_code(),
+                kernel_driver: handle.as_ref().ok().and_then(|handle| handle.kernel_driver_active(number).ok()),
+                endpoints: interface_desc.endpoint_descriptors().map(|desc| endpoint_info(&desc)).collect(),
+                picked: is_picked,
+                reason: pick_reason(&interface_desc, is_picked),
+            });
+        }
+    }
+
+    let (product, serial, open_error) = match &handle {
+        Ok(handle) => (
+            handle.read_product_string_ascii(descriptor).ok(),
+            handle.read_serial_number_string_ascii(descriptor).ok(),
+            None,
+        ),
+        Err(err) => (None, None, Some(err.to_string())),
+    };
+    DeviceInfo {
+        bus: device.bus_number(),
+        address: device.address(),
+        vendor_id: descriptor.vendor_id(),
+        product_id: descriptor.product_id(),
+        known_as,
+        product,
+        serial,
+        open_error: open_error.or_else(|| config.err().map(|err| format!("Can't read the configuration: {err}"))),
+        selected: false,
+        interfaces,
+    }
+}
+
+fn endpoint_info(endpoint_desc: &EndpointDescriptor) -> EndpointInfo {
+    EndpointInfo {
+        address: endpoint_desc.address(),
+        direction: match endpoint_desc.direction() {
+            Direction::In => "in",
+            Direction::Out => "out",
+        },
+        transfer_type: match endpoint_desc.transfer_type() {
+            TransferType::Control => "control",
+            TransferType::Isochronous => "isochronous",
+            TransferType::Bulk => "bulk",
+            TransferType::Interrupt => "interrupt",
+        },
+        max_packet_size: endpoint_desc.max_packet_size(),
+    }
+}
+
+/// Why `HidDevice::open` picks or skips the interface, it reads from the first HID interface with
+/// an IN endpoint of `INPUT_REPORT_LEN` bytes
+fn pick_reason(interface_desc: &InterfaceDescriptor, picked: bool) -> String {
+    if picked {
+        format!("picked, first HID interface with a {INPUT_REPORT_LEN} byte IN endpoint")
+    } else if interface_desc.class_code() != 0x03 {
+        "skipped, not a HID interface".to_string()
+    } else if input_endpoint(interface_desc, INPUT_REPORT_LEN as u16).is_none() {
+        format!("skipped, no {INPUT_REPORT_LEN} byte IN endpoint")
+    } else {
+        "skipped, an earlier interface was picked".to_string()
+    }
+}
+
+fn print_device(device: &DeviceInfo) {
+    println!(
+        "Bus {:03} Device {:03}: {:04x}:{:04x} {}{}",
+        device.bus,
+        device.address,
+        device.vendor_id,
+        device.product_id,
+        device.product.as_deref().or(device.known_as).unwrap_or("<unknown product>"),
+        if device.selected { " (selected)" } else { "" },
+    );
+    println!("  Serial: {}", device.serial.as_deref().unwrap_or("unknown"));
+    if let Some(err) = &device.open_error {
+        println!("  Can't open: {err}");
+    }
+    for interface in &device.interfaces {
+        let kernel_driver: &str = match interface.kernel_driver {
+            Some(true) => "attached",
+            Some(false) => "none",
+            None => "unknown",
+        };
+        println!(
+            "  Interface {} (setting {}): class {:02x} subclass {:02x} protocol {:02x}, kernel driver {}",
+            interface.number, interface.setting, interface.class, interface.subclass, interface.protocol, kernel_driver,
+        );
+        for endpoint in &interface.endpoints {
+            println!(
+                "    Endpoint {:#04x}: {} {}, {} bytes",
+                endpoint.address, endpoint.direction, endpoint.transfer_type, endpoint.max_packet_size,
+            );
+        }
+        println!("    {}", interface.reason);
+    }
+}
@@ -0,0 +1,80 @@
+// One module per subcommand of `cli_parser::Subcommand`, `main` only dispatches to them
+
+mod calibrate;
+mod doctor;
+mod dump;
+mod feature;
+mod list;
+mod monitor;
+mod profile;
+mod record;
+mod run;
+
+pub use self::calibrate::{calibrate_gyro, calibrate_sticks};
+pub use self::doctor::doctor;
+pub use self::dump::dump;
+pub use self::feature::{feature_get, feature_send};
+pub use self::list::list;
+pub use self::monitor::monitor;
+pub use self::profile::{check_profile, record_macro};
+pub use self::record::{record, replay};
+pub use self::run::run;
+
+use crate::calibration::{self, CalibrationStore, DeviceCalibration};
+use crate::cli_parser::Args;
+use crate::deck::InputReport;
+use crate::hid::HidDevice;
+use crate::prelude::*;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Instant;
+
+/// Creates the device picked by `--vid`, `--pid` and `--serial`, it still has to be opened
+fn new_device(args: &Args) -> Result<HidDevice, Box<dyn Error>> {
+    let mut dev: HidDevice = HidDevice::new(args.device.vid, args.device.pid)?;
+    dev.set_serial_filter(args.device.serial.clone());
+    Ok(dev)
+}
+
+/// Opens the controller with its reports sent to a channel, uncalibrated
+fn open_report_channel(args: &Args) -> Result<(HidDevice, mpsc::Receiver<InputReport>), Box<dyn Error>> {
+    let (sender, receiver) = mpsc::channel::<InputReport>();
+    let mut dev: HidDevice = new_device(args)?;
+    let started: Instant = Instant::now();
+    dev.set_on_input_received(move |data| {
+        if let Ok(report) = InputReport::parse(&data, started.elapsed()) {
+            let _ = sender.send(report);
+        }
+    });
+    dev.open()?;
+    Ok((dev, receiver))
+}
+
+/// File calibrations are saved in, `--calibration` or the one in the config directory
+fn calibration_path(args: &Args) -> Result<PathBuf, Box<dyn Error>> {
+    args.calibration
+        .clone()
+        .or_else(calibration::default_path)
+        .ok_or_else(|| "Can't find the config directory for the calibration, set it with --calibration".into())
+}
+
+/// Calibration saved for the controller with this serial number, empty if it was never
+/// calibrated
+fn device_calibration(args: &Args, serial: Option<&str>) -> DeviceCalibration {
+    let (Some(serial), Ok(path)) = (serial, calibration_path(args)) else {
+        return DeviceCalibration::default();
+    };
+    match CalibrationStore::load(&path) {
+        Ok(store) => store.device(serial),
+        Err(err) => {
+            warn!("Ignoring the saved calibration: {}", err);
+            DeviceCalibration::default()
+        }
+    }
+}
+
+/// Formats bytes as space separated hex pairs, e.g. `85 00`
+fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect::<Vec<String>>().join(" ")
+}
@@ -0,0 +1,400 @@
+use crate::cli_parser::Args;
+use crate::commands::new_device;
+use crate::deck::{DeckButton, InputReport, Stick, Trackpad};
+use crate::hid::HidDevice;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::canvas::{Canvas, Circle, Points, Rectangle};
+use ratatui::widgets::{Axis, Block, Chart, Dataset, Gauge, GraphType, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// How often the screen is redrawn, the controller reports far faster than anyone can read
+const FRAME_INTERVAL: Duration = Duration::from_millis(33);
+/// Samples kept for the gyro and accel plots, 2 seconds at the 250 Hz report rate
+const HISTORY_LEN: usize = 500;
+/// Bytes per line of the raw hex pane
+const HEX_ROW_LEN: usize = 16;
+/// Smallest range of the plots, so noise at rest isn't scaled up to fill them
+const GYRO_PLOT_MIN_DPS: f64 = 10.0;
+const ACCEL_PLOT_MIN_G: f64 = 1.2;
+
+const AXIS_COLORS: [Color; 3] = [Color::Red, Color::Green, Color::Blue];
+
+/// State shown by `monitor`, updated with every report
+#[derive(Default)]
+struct Monitor {
+    report: Option<InputReport>,
+    raw: Vec<u8>,
+    /// Bytes that changed in any report since the last frame, so changes between frames still
+    /// show up
+    changed: Vec<bool>,
+    gyro: VecDeque<[f32; 3]>,
+    accel: VecDeque<[f32; 3]>,
+    /// Times of the reports within the last second
+    arrivals: VecDeque<Duration>,
+    last_sequence: Option<u32>,
+    /// Reports missing going by the sequence numbers
+    dropped: u64,
+    /// Keeps the screen as it is while reports are still counted
+    paused: bool,
+}
+
+impl Monitor {
+    fn push(&mut self, timestamp: Duration, data: Vec<u8>) {
+        self.arrivals.push_back(timestamp);
+        while self.arrivals.front().is_some_and(|arrival| timestamp - *arrival > Duration::from_secs(1)) {
+            self.arrivals.pop_front();
+        }
+        let Ok(report) = InputReport::parse(&data, timestamp) else {
+            // Not a state report, still worth looking at
+            self.push_raw(data);
+            return;
+        };
+        // A sequence number going backwards means the controller started counting over, e.g. after
+        // reconnecting, rather than billions of dropped reports
+        if let Some(last) = self.last_sequence
+            && report.sequence > last
+        {
+            self.dropped += (report.sequence - last - 1) as u64;
+        }
+        self.last_sequence = Some(report.sequence);
+        if self.paused {
+            return;
+        }
+
+        for (history, sample) in [(&mut self.gyro, report.gyro_dps()), (&mut self.accel, report.accel_g())] {
+            if history.len() == HISTORY_LEN {
+                history.pop_front();
+            }
+            history.push_back(sample);
+        }
+        self.report = Some(report);
+        self.push_raw(data);
+    }
+
+    fn push_raw(&mut self, data: Vec<u8>) {
+        if self.paused {
+            return;
+        }
+        self.changed.resize(data.len(), false);
+        for (index, byte) in data.iter().enumerate() {
+            if self.raw.get(index) != Some(byte) {
+                self.changed[index] = true;
+            }
+        }
+        self.raw = data;
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [status_area, body] = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(frame.area());
+        let [left, right] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(body);
+        let [buttons_area, sticks_area, pads_area, triggers_area] = Layout::vertical([
+            Constraint::Length(DeckButton::ALL.len().div_ceil(4) as u16 + 2),
+            Constraint::Min(6),
+            Constraint::Min(6),
+            Constraint::Length(4),
+        ])
+        .areas(left);
+        let hex_rows: u16 = self.raw.len().div_ceil(HEX_ROW_LEN).max(1) as u16;
+        let [gyro_area, accel_area, raw_area] =
+            Layout::vertical([Constraint::Min(6), Constraint::Min(6), Constraint::Length(hex_rows + 2)]).areas(right);
+
+        frame.render_widget(self.status(), status_area);
+        let report: InputReport = self.report.clone().unwrap_or_default();
+        frame.render_widget(buttons(&report), buttons_area);
+
+        let [left_stick, right_stick] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(sticks_area);
+        frame.render_widget(stick("Left stick", &report.left_stick), left_stick);
+        frame.render_widget(stick("Right stick", &report.right_stick), right_stick);
+        let [left_pad, right_pad] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(pads_area);
+        frame.render_widget(trackpad("Left pad", &report.left_pad), left_pad);
+        frame.render_widget(trackpad("Right pad", &report.right_pad), right_pad);
+
+        let [left_trigger, right_trigger] = Layout::vertical([Constraint::Length(2); 2]).areas(triggers_area);
+        frame.render_widget(trigger("L2", report.left_trigger, report.left_trigger_normalized()), left_trigger);
+        frame.render_widget(trigger("R2", report.right_trigger, report.right_trigger_normalized()), right_trigger);
+
+        render_plot(frame, gyro_area, "Gyro °/s", ["pitch", "roll", "yaw"], &self.gyro, GYRO_PLOT_MIN_DPS);
+        render_plot(frame, accel_area, "Accel g", ["x", "y", "z"], &self.accel, ACCEL_PLOT_MIN_G);
+        self.render_raw(frame, raw_area);
+    }
+
+    fn status(&self) -> Paragraph<'static> {
+        let mut spans: Vec<Span> = vec![
+            format!("#{:<10}", self.last_sequence.unwrap_or(0)).bold(),
+            format!(" {:>4} reports/s", self.arrivals.len()).into(),
+            format!("  {} dropped", self.dropped).into(),
+        ];
+        if self.paused {
+            spans.push("  PAUSED".yellow().bold());
+        }
+        spans.push("  q quit, space pause".dark_gray());
+        Paragraph::new(Line::from(spans))
+    }
+
+    /// Raw bytes of the last report, bytes that changed since the previous frame are highlighted
+    fn render_raw(&self, frame: &mut Frame, area: Rect) {
+        let lines: Vec<Line> = self
+            .raw
+            .chunks(HEX_ROW_LEN)
+            .enumerate()
+            .map(|(row, bytes)| {
+                let mut spans: Vec<Span> = vec![format!("{:02x}:", row * HEX_ROW_LEN).dark_gray()];
+                for (column, byte) in bytes.iter().enumerate() {
+                    let changed: bool = self.changed[row * HEX_ROW_LEN + column];
+                    let text: String = format!(" {byte:02x}");
+                    spans.push(if changed { text.black().on_yellow() } else { text.into() });
+                }
+                Line::from(spans)
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title("Raw report")), area);
+    }
+}
+
+/// Runs `monitor`: draws the controller state live until q, Esc or Ctrl+C is pressed
+pub fn monitor(args: &Args) -> Result<(), Box<dyn Error>> {
+    let (sender, receiver) = mpsc::channel::<(Duration, Vec<u8>)>();
+    let mut dev: HidDevice = new_device(args)?;
+    let started: Instant = Instant::now();
+    dev.set_on_input_received(move |data| {
+        let _ = sender.send((started.elapsed(), data));
+    });
+    dev.open()?;
+
+    let mut terminal: DefaultTerminal = ratatui::try_init()?;
+    let result: Result<(), Box<dyn Error>> = run_ui(&mut terminal, &receiver);
+    ratatui::restore();
+    dev.close()?;
+    result
+}
+
+fn run_ui(terminal: &mut DefaultTerminal, receiver: &mpsc::Receiver<(Duration, Vec<u8>)>) -> Result<(), Box<dyn Error>> {
+    let mut monitor: Monitor = Monitor::default();
+    loop {
+        while let Ok((timestamp, data)) = receiver.try_recv() {
+            monitor.push(timestamp, data);
+        }
+        terminal.draw(|frame| monitor.draw(frame))?;
+        if !monitor.paused {
+            monitor.changed.fill(false);
+        }
+
+        if !event::poll(FRAME_INTERVAL)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Char(' ') => monitor.paused = !monitor.paused,
+            _ => {}
+        }
+    }
+}
+
+fn buttons(report: &InputReport) -> Paragraph<'static> {
+    let lines: Vec<Line> = DeckButton::ALL
+        .chunks(4)
+        .map(|row| {
+            Line::from(
+                row.iter()
+                    .map(|button| {
+                        let text: String = format!(" {:<16}", format!("{button:?}"));
+                        if report.is_pressed(*button) {
+                            text.black().on_green()
+                        } else {
+                            text.dark_gray()
+                        }
+                    })
+                    .collect::<Vec<Span>>(),
+            )
+        })
+        .collect();
+    Paragraph::new(lines).block(Block::bordered().title("Buttons"))
+}
+
+fn stick(name: &str, stick: &Stick) -> Canvas<'static, impl Fn(&mut ratatui::widgets::canvas::Context<'_>)> {
+    let (x, y) = stick.normalized();
+    let title: String = format!(
+        "{} {:+.2} {:+.2}{}",
+        name,
+        x,
+        y,
+        if stick.touched { " touched" } else { "" }
+    );
+    Canvas::default()
+        .block(Block::bordered().title(title))
+        .marker(Marker::Braille)
+        .x_bounds([-1.0, 1.0])
+        .y_bounds([-1.0, 1.0])
+        .paint(move |ctx| {
+            ctx.draw(&Circle { x: 0.0, y: 0.0, radius: 1.0, color: Color::DarkGray });
+            ctx.draw(&Points { coords: &[(x as f64, y as f64)], color: Color::Yellow });
+        })
+}
+
+fn trackpad(name: &str, pad: &Trackpad) -> Canvas<'static, impl Fn(&mut ratatui::widgets::canvas::Context<'_>)> {
+    let (x, y) = pad.normalized();
+    let touched: bool = pad.touched;
+    let title: String = if touched {
+        format!("{} {:+.2} {:+.2} pressure {}", name, x, y, pad.pressure)
+    } else {
+        format!("{} pressure {}", name, pad.pressure)
+    };
+    Canvas::default()
+        .block(Block::bordered().title(title))
+        .marker(Marker::Braille)
+        .x_bounds([-1.0, 1.0])
+        .y_bounds([-1.0, 1.0])
+        .paint(move |ctx| {
+            ctx.draw(&Rectangle { x: -1.0, y: -1.0, width: 2.0, height: 2.0, color: Color::DarkGray });
+            if touched {
+                ctx.draw(&Points { coords: &[(x as f64, y as f64)], color: Color::Yellow });
+            }
+        })
+}
+
+fn trigger(name: &str, raw: u16, value: f32) -> Gauge<'static> {
+    Gauge::default()
+        .block(Block::default().title(format!("{name} {raw}")))
+        .gauge_style(Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))
+        .ratio(value.clamp(0.0, 1.0) as f64)
+}
+
+/// Plots the three axes of `history`, scaled to the largest value but at least `min_range`
+fn render_plot(
+    frame: &mut Frame,
+    area: Rect,
+    name: &str,
+    axes: [&str; 3],
+    history: &VecDeque<[f32; 3]>,
+    min_range: f64,
+) {
+    let points: [Vec<(f64, f64)>; 3] = [0, 1, 2].map(|axis| {
+        history
+            .iter()
+            .enumerate()
+            .map(|(index, sample)| (index as f64, sample[axis] as f64))
+            .collect()
+    });
+    let range: f64 = points
+        .iter()
+        .flatten()
+        .map(|(_, value)| value.abs() * 1.1)
+        .fold(min_range, f64::max);
+
+    let latest: [f32; 3] = history.back().copied().unwrap_or_default();
+    let mut title: Vec<Span> = vec![format!("{name} ").into()];
+    let mut datasets: Vec<Dataset> = Vec::new();
+    for axis in 0..3 {
+        title.push(Span::styled(format!(" {} {:+8.2}", axes[axis], latest[axis]), AXIS_COLORS[axis]));
+        datasets.push(
+            Dataset::default()
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(AXIS_COLORS[axis])
+                .data(&points[axis]),
+        );
+    }
+    let chart: Chart = Chart::new(datasets)
+        .block(Block::bordered().title(Line::from(title)))
+        .x_axis(Axis::default().bounds([0.0, HISTORY_LEN as f64]))
+        .y_axis(
+            Axis::default()
+                .bounds([-range, range])
+                .labels([format!("{:.1}", -range), "0".to_string(), format!("{range:.1}")]),
+        );
+    frame.render_widget(chart, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 64 byte state report with the sequence number `sequence` and `byte` at offset 60
+    fn state_report(sequence: u32, byte: u8) -> Vec<u8> {
+        let mut data: Vec<u8> = vec![0; 64];
+        data[2] = 0x09;
+        data[4..8].copy_from_slice(&sequence.to_le_bytes());
+        data[60] = byte;
+        data
+    }
+
+    fn push(monitor: &mut Monitor, ms: u64, data: Vec<u8>) {
+        monitor.push(Duration::from_millis(ms), data);
+    }
+
+    #[test]
+    fn counts_gaps_in_the_sequence_as_dropped() {
+        let mut monitor: Monitor = Monitor::default();
+        push(&mut monitor, 0, state_report(10, 0));
+        push(&mut monitor, 4, state_report(11, 0));
+        assert_eq!(monitor.dropped, 0);
+        push(&mut monitor, 8, state_report(15, 0));
+        assert_eq!(monitor.dropped, 3);
+        push(&mut monitor, 12, state_report(15, 0));
+        assert_eq!(monitor.dropped, 3);
+
+        // Starting over is a reset, counting continues from the new sequence number
+        push(&mut monitor, 16, state_report(2, 0));
+        assert_eq!(monitor.dropped, 3);
+        push(&mut monitor, 20, state_report(4, 0));
+        assert_eq!(monitor.dropped, 4);
+        assert_eq!(monitor.arrivals.len(), 6);
+    }
+
+    #[test]
+    fn paused_monitor_keeps_counting() {
+        let mut monitor: Monitor = Monitor::default();
+        push(&mut monitor, 0, state_report(1, 1));
+        monitor.paused = true;
+        push(&mut monitor, 4, state_report(3, 2));
+        assert_eq!(monitor.dropped, 1);
+        assert_eq!(monitor.report.as_ref().unwrap().sequence, 1);
+        assert_eq!(monitor.raw[60], 1);
+        assert_eq!(monitor.gyro.len(), 1);
+    }
+
+    #[test]
+    fn tracks_changed_bytes_until_cleared() {
+        let mut monitor: Monitor = Monitor::default();
+        push(&mut monitor, 0, state_report(1, 0));
+        assert!(monitor.changed[2] && monitor.changed[4]);
+        monitor.changed.fill(false);
+
+        push(&mut monitor, 4, state_report(2, 7));
+        push(&mut monitor, 8, state_report(3, 0));
+        let changed: Vec<usize> = (0..monitor.changed.len()).filter(|&index| monitor.changed[index]).collect();
+        // Byte 60 changed back but still shows up until the next frame clears it
+        assert_eq!(changed, [4, 60]);
+        assert_eq!(monitor.raw[60], 0);
+
+        // Reports that aren't state reports are still shown
+        push(&mut monitor, 12, vec![0xff; 8]);
+        assert_eq!(monitor.raw, [0xff; 8]);
+        assert_eq!(monitor.changed.len(), 8);
+        assert_eq!(monitor.report.as_ref().unwrap().sequence, 3);
+    }
+
+    #[test]
+    fn report_rate_covers_the_last_second() {
+        let mut monitor: Monitor = Monitor::default();
+        for (index, ms) in (0..1500).step_by(100).enumerate() {
+            push(&mut monitor, ms, state_report(index as u32, 0));
+        }
+        assert_eq!(monitor.arrivals.front(), Some(&Duration::from_millis(400)));
+    }
+}
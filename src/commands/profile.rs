@@ -0,0 +1,93 @@
+use crate::calibration::DeviceCalibration;
+use crate::cli_parser::Args;
+use crate::commands::{device_calibration, new_device};
+use crate::deck::{DeckButton, InputReport};
+use crate::hid::HidDevice;
+use crate::mapping::{Macro, MacroRecorder, MappingEngine};
+use crate::output::OutputEvent;
+use crate::prelude::*;
+use crate::profile::{self, Profile, ProfileError};
+use std::error::Error;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, mpsc};
+use std::time::Instant;
+
+/// Runs `profile check`. The error of an invalid profile quotes the offending line with a caret
+/// under the column.
+pub fn check_profile(path: &Path) -> Result<(), Box<dyn Error>> {
+    let err: ProfileError = match profile::load(path) {
+        Ok(profile) => {
+            println!("{}: profile `{}` is valid", path.display(), profile.name);
+            return Ok(());
+        }
+        Err(err) => err,
+    };
+    let ProfileError::Invalid {
+        line,
+        column,
+        source_line,
+        ..
+    } = &err
+    else {
+        return Err(err.into());
+    };
+    let gutter: String = " ".repeat(line.to_string().len());
+    let caret: String = " ".repeat(column - 1);
+    Err(format!("{err}\n{gutter} |\n{line} | {source_line}\n{gutter} | {caret}^").into())
+}
+
+/// Runs `profile record-macro`: maps the controller with the profile and records its outputs until
+/// `stop` is pressed, then saves them as the macro `name`
+pub fn record_macro(args: &Args, name: &str, stop: DeckButton) -> Result<(), Box<dyn Error>> {
+    let Some(path) = &args.profile else {
+        return Err("`profile record-macro` needs the profile to save into, set it with --profile".into());
+    };
+    let profile: Profile = profile::load(path)?;
+    // Taken once the recording stops so later reports are ignored
+    let session: Mutex<Option<(MappingEngine, MacroRecorder)>> =
+        Mutex::new(Some((MappingEngine::new(profile), MacroRecorder::new())));
+    let (sender, receiver) = mpsc::channel::<Macro>();
+
+    let mut dev: HidDevice = new_device(args)?;
+    let started: Instant = Instant::now();
+    // Filled in once the device is open, reports are skipped until then rather than recorded uncalibrated
+    let calibration: Arc<Mutex<Option<DeviceCalibration>>> = Arc::new(Mutex::new(None));
+    let calibration_clone: Arc<Mutex<Option<DeviceCalibration>>> = Arc::clone(&calibration);
+    dev.set_on_input_received(move |data| {
+        let Ok(mut report) = InputReport::parse(&data, started.elapsed()) else {
+            return;
+        };
+        match calibration_clone.lock().unwrap().as_ref() {
+            Some(calibration) => calibration.apply(&mut report),
+            None => return,
+        }
+        let mut session: MutexGuard<'_, Option<(MappingEngine, MacroRecorder)>> = session.lock().unwrap();
+        let Some((engine, recorder)) = session.as_mut() else {
+            return;
+        };
+        if report.is_pressed(stop) {
+            let events: Vec<OutputEvent> = engine.release_all();
+            recorder.record(&events, report.timestamp);
+            if let Some((_, recorder)) = session.take() {
+                let _ = sender.send(recorder.finish());
+            }
+            return;
+        }
+        let events: Vec<OutputEvent> = engine.process(&report);
+        recorder.record(&events, report.timestamp);
+    });
+    dev.open()?;
+    *calibration.lock().unwrap() = Some(device_calibration(args, dev.serial_number()));
+
+    info!("Recording macro `{}`, press {:?} to stop...", name, stop);
+    let recorded: Result<Macro, mpsc::RecvError> = receiver.recv();
+    dev.close()?;
+    let recorded: Macro = recorded?;
+    if recorded.steps.is_empty() {
+        return Err("Nothing was recorded, the profile was left untouched".into());
+    }
+
+    profile::save_macro(path, name, &recorded)?;
+    info!("Saved macro `{}` with {} step(s) to {}", name, recorded.steps.len(), path.display());
+    Ok(())
+}
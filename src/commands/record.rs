@@ -0,0 +1,135 @@
+use crate::calibration::DeviceCalibration;
+use crate::cli_parser::Args;
+use crate::commands::{device_calibration, hex_string, new_device};
+use crate::deck::InputReport;
+use crate::hid::HidDevice;
+use crate::mapping::MappingEngine;
+use crate::output::OutputEvent;
+use crate::prelude::*;
+use crate::profile::{self, Profile};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// First line of every recording. After it come `#` comments, e.g. `# serial: <serial>`, then one
+/// report per line: microseconds since recording started and the raw bytes in hex.
+const HEADER: &str = "# windecon recording v1";
+const SERIAL_PREFIX: &str = "# serial: ";
+
+/// Runs `record`: writes every raw input report to `path` until `duration` passed or the process
+/// is stopped. Lines are flushed as they're written, so stopping with Ctrl+C loses nothing.
+pub fn record(args: &Args, path: &Path, duration: Option<Duration>) -> Result<(), Box<dyn Error>> {
+    let (sender, receiver) = mpsc::channel::<(Duration, Vec<u8>)>();
+    let mut dev: HidDevice = new_device(args)?;
+    let started: Instant = Instant::now();
+    dev.set_on_input_received(move |data| {
+        let _ = sender.send((started.elapsed(), data));
+    });
+    dev.open()?;
+
+    let mut file: LineWriter<File> = LineWriter::new(File::create(path)?);
+    writeln!(file, "{HEADER}")?;
+    if let Some(serial) = dev.serial_number() {
+        writeln!(file, "{SERIAL_PREFIX}{serial}")?;
+    }
+    match duration {
+        Some(duration) => info!("Recording to {} for {:.1}s...", path.display(), duration.as_secs_f64()),
+        None => info!("Recording to {}, press Ctrl+C to stop...", path.display()),
+    }
+
+    let mut count: usize = 0;
+    loop {
+        let Ok((timestamp, data)) = receiver.recv_timeout(Duration::from_secs(1)) else {
+            dev.close()?;
+            return Err(format!("The controller stopped sending reports after {count} report(s)").into());
+        };
+        if duration.is_some_and(|duration| timestamp >= duration) {
+            break;
+        }
+        writeln!(file, "{} {}", timestamp.as_micros(), hex_string(&data))?;
+        count += 1;
+    }
+    dev.close()?;
+    info!("Recorded {} report(s)", count);
+    Ok(())
+}
+
+/// Runs `replay`: maps a recording with the profile from `--profile`, or the default profile, and
+/// prints the output events with their timestamps
+pub fn replay(args: &Args, path: &Path) -> Result<(), Box<dyn Error>> {
+    let recording: Recording = Recording::load(path)?;
+    let profile: Profile = match &args.profile {
+        Some(profile_path) => profile::load(profile_path)?,
+        None => Profile::default(),
+    };
+    // The calibration of the controller that made the recording, if it's this machine's
+    let calibration: DeviceCalibration = device_calibration(args, recording.serial.as_deref());
+    let mut engine: MappingEngine = MappingEngine::new(profile);
+
+    let mut last: Duration = Duration::ZERO;
+    for (timestamp, data) in &recording.reports {
+        let mut report: InputReport = match InputReport::parse(data, *timestamp) {
+            Ok(report) => report,
+            Err(err) => {
+                trace!("Skipping input report: {}", err);
+                continue;
+            }
+        };
+        calibration.apply(&mut report);
+        print_events(*timestamp, &engine.process(&report));
+        last = *timestamp;
+    }
+    print_events(last, &engine.release_all());
+    Ok(())
+}
+
+fn print_events(timestamp: Duration, events: &[OutputEvent]) {
+    for event in events {
+        println!("{:>10.3} {:?}", timestamp.as_secs_f64(), event);
+    }
+}
+
+/// A recording made by [`record`]
+struct Recording {
+    serial: Option<String>,
+    reports: Vec<(Duration, Vec<u8>)>,
+}
+
+impl Recording {
+    fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents: String = fs::read_to_string(path)?;
+        let mut lines = contents.lines().enumerate();
+        if lines.next().map(|(_, line)| line) != Some(HEADER) {
+            return Err(format!("{} is not a recording made by `record`", path.display()).into());
+        }
+
+        let mut recording: Recording = Recording {
+            serial: None,
+            reports: Vec::new(),
+        };
+        for (index, line) in lines {
+            if let Some(serial) = line.strip_prefix(SERIAL_PREFIX) {
+                recording.serial = Some(serial.to_string());
+                continue;
+            } else if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            let report: Option<(Duration, Vec<u8>)> = line.split_once(' ').and_then(|(micros, data)| {
+                let micros: u64 = micros.parse().ok()?;
+                let data: Vec<u8> = data
+                    .split_whitespace()
+                    .map(|byte| u8::from_str_radix(byte, 16))
+                    .collect::<Result<_, _>>()
+                    .ok()?;
+                Some((Duration::from_micros(micros), data))
+            });
+            let report: (Duration, Vec<u8>) =
+                report.ok_or_else(|| format!("{}:{}: expected `<microseconds> <hex bytes>`", path.display(), index + 1))?;
+            recording.reports.push(report);
+        }
+        Ok(recording)
+    }
+}
@@ -0,0 +1,179 @@
+use crate::apps::{self, AppSwitcher};
+use crate::calibration::DeviceCalibration;
+use crate::cli_parser::Args;
+use crate::commands::{device_calibration, new_device};
+use crate::deck::{self, HapticPlayer, InputReport};
+use crate::hid::HidDevice;
+use crate::mapping::MappingEngine;
+use crate::output::{OutputEvent, OutputSink, SendInputKeyboardMouse, ViGEmGamepad};
+use crate::prelude::*;
+use crate::profile::{self, LizardMode, Profile, ProfileWatcher};
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+use std::{thread, time::Duration};
+
+/// Runs `run`: maps the controller with the profile from `--profile`, the application rules from
+/// `--config` or the default profile
+pub fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    info!("Starting WinDeCon...");
+
+    let mut sink: OutputSink = OutputSink::new(Box::new(ViGEmGamepad::connect()?));
+    sink.set_keyboard_mouse(Box::new(SendInputKeyboardMouse::new()));
+    let sink: Arc<Mutex<OutputSink>> = Arc::new(Mutex::new(sink));
+    let engine: Arc<Mutex<MappingEngine>> = Arc::new(Mutex::new(MappingEngine::new(Profile::default())));
+    // Watches whichever profile file is currently active
+    let watcher: Arc<Mutex<Option<ProfileWatcher>>> = Arc::new(Mutex::new(None));
+    let mut _switcher: Option<AppSwitcher> = None;
+
+    match (&args.profile, &args.config) {
+        (Some(path), config) => {
+            if config.is_some() {
+                warn!("--profile is set, ignoring --config");
+            }
+            let profile: Profile = profile::load(path)?;
+            info!("Using profile `{}`", profile.name);
+            engine.lock().unwrap().set_profile(profile);
+            *watcher.lock().unwrap() = Some(watch_profile(path.clone(), &engine, &sink));
+        }
+        (None, Some(config_path)) => {
+            let config: apps::AppConfig = apps::load_config(config_path)?;
+            info!("Switching profiles automatically using {} rule(s)", config.rules.len());
+            let engine: Arc<Mutex<MappingEngine>> = Arc::clone(&engine);
+            let watcher: Arc<Mutex<Option<ProfileWatcher>>> = Arc::clone(&watcher);
+            let sink: Arc<Mutex<OutputSink>> = Arc::clone(&sink);
+            _switcher = Some(AppSwitcher::spawn(config, move |path, profile| {
+                let events: Vec<OutputEvent> = engine.lock().unwrap().set_profile(profile);
+                send_outputs(&sink, &events);
+                *watcher.lock().unwrap() = path.map(|path| watch_profile(path, &engine, &sink));
+            }));
+        }
+        (None, None) => info!("Using the default profile"),
+    }
+
+    let dev: Arc<Mutex<HidDevice>> = Arc::new(Mutex::new(new_device(args)?));
+
+    // Set by Ctrl+C, stops the heartbeat so the device is closed and lizard mode restored
+    let stop_flag: Arc<Mutex<bool>> = Arc::new(Mutex::new(false));
+    let stop_flag_clone: Arc<Mutex<bool>> = Arc::clone(&stop_flag);
+    ctrlc::set_handler(move || *stop_flag_clone.lock().unwrap() = true)?;
+
+    let started: Instant = Instant::now();
+    let engine_clone: Arc<Mutex<MappingEngine>> = Arc::clone(&engine);
+    let sink_clone: Arc<Mutex<OutputSink>> = Arc::clone(&sink);
+    let stop_flag_clone: Arc<Mutex<bool>> = Arc::clone(&stop_flag);
+    let haptics: HapticPlayer = HapticPlayer::spawn(&dev);
+    // Filled in once the device is open and its serial number is known, reports are skipped until
+    // then rather than mapped uncalibrated
+    let calibration: Arc<Mutex<Option<DeviceCalibration>>> = Arc::new(Mutex::new(None));
+    let calibration_clone: Arc<Mutex<Option<DeviceCalibration>>> = Arc::clone(&calibration);
+    dev.lock().unwrap().set_on_input_received(move |data| {
+        // Outputs are released once stopping, later reports would press them again
+        if *stop_flag_clone.lock().unwrap() {
+            return;
+        }
+        match InputReport::parse(&data, started.elapsed()) {
+            Ok(mut report) => {
+                match calibration_clone.lock().unwrap().as_ref() {
+                    Some(calibration) => calibration.apply(&mut report),
+                    None => return,
+                }
+                let events: Vec<OutputEvent> = engine_clone.lock().unwrap().process(&report);
+                for event in &events {
+                    if let OutputEvent::Haptic(pulse) = event {
+                        haptics.play(*pulse);
+                    }
+                }
+                if !events.is_empty() {
+                    trace!("Output events: {:?}", events);
+                    send_outputs(&sink_clone, &events);
+                }
+            }
+            Err(err) => trace!("Skipping input report: {}", err),
+        }
+    });
+    dev.lock().unwrap().open()?;
+    *calibration.lock().unwrap() = Some(device_calibration(args, dev.lock().unwrap().serial_number()));
+
+    info!("Mapping the controller, press Ctrl+C to stop...");
+
+    // Whether lizard mode was turned off and has to be restored on exit
+    let lizard_mode_off: Arc<Mutex<bool>> = Arc::new(Mutex::new(false));
+    let dev_clone: Arc<Mutex<HidDevice>> = Arc::clone(&dev);
+    let engine_clone: Arc<Mutex<MappingEngine>> = Arc::clone(&engine);
+    let lizard_mode_off_clone: Arc<Mutex<bool>> = Arc::clone(&lizard_mode_off);
+    thread::Builder::new()
+        .name("heartbeat".into())
+        .spawn(move || {
+            let mut cycle: u64 = 0;
+            while !*stop_flag.lock().unwrap() {
+                let dev: MutexGuard<'_, HidDevice> = dev_clone.lock().unwrap();
+                // Follows the active profile, which may change while running
+                let lizard_mode: LizardMode = engine_clone.lock().unwrap().profile().lizard_mode;
+                let mut lizard_mode_off: MutexGuard<'_, bool> = lizard_mode_off_clone.lock().unwrap();
+                match lizard_mode {
+                    LizardMode::Disabled => {
+                        send_feature_reports(&dev, &deck::lizard_mode_off(), "Disabling lizard mode");
+                        *lizard_mode_off = true;
+                    }
+                    LizardMode::Enabled if *lizard_mode_off => {
+                        send_feature_reports(&dev, &deck::lizard_mode_on(), "Restoring lizard mode");
+                        *lizard_mode_off = false;
+                    }
+                    LizardMode::Enabled => {}
+                }
+
+                // The haptics player shares the device, don't hold it while waiting
+                drop(lizard_mode_off);
+                drop(dev);
+
+                trace!("Cycle {cycle} complete...");
+                cycle += 1;
+                thread::sleep(Duration::from_millis(1000));
+            }
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+
+    info!("Closing!");
+    let events: Vec<OutputEvent> = engine.lock().unwrap().release_all();
+    send_outputs(&sink, &events);
+    let mut dev: MutexGuard<'_, HidDevice> = dev.lock().unwrap();
+    if *lizard_mode_off.lock().unwrap() {
+        send_feature_reports(&dev, &deck::lizard_mode_on(), "Restoring lizard mode");
+    }
+    dev.close()?;
+
+    Ok(())
+}
+
+/// Hot-reloads the profile at `path` into the engine.
+/// Swapping the profile under the engine lock keeps the device untouched, outputs the old profile
+/// held are released through `sink`.
+fn watch_profile(path: PathBuf, engine: &Arc<Mutex<MappingEngine>>, sink: &Arc<Mutex<OutputSink>>) -> ProfileWatcher {
+    let engine: Arc<Mutex<MappingEngine>> = Arc::clone(engine);
+    let sink: Arc<Mutex<OutputSink>> = Arc::clone(sink);
+    ProfileWatcher::spawn(path, move |profile| {
+        let events: Vec<OutputEvent> = engine.lock().unwrap().set_profile(profile);
+        send_outputs(&sink, &events);
+    })
+}
+
+/// Sends `events` to the emulated devices, logging failures so one bad batch doesn't stop mapping
+fn send_outputs(sink: &Mutex<OutputSink>, events: &[OutputEvent]) {
+    if let Err(err) = sink.lock().unwrap().send(events) {
+        error!("Sending output events failed: {}", err);
+    }
+}
+
+/// Sends every request in `requests`, logging failures since mapping works without them
+fn send_feature_reports(dev: &HidDevice, requests: &[Vec<u8>], what: &str) {
+    for request in requests {
+        match dev.request_feature_report(request) {
+            Ok((_, response)) => debug!("{}: {:02x?}", what, response),
+            Err(err) => error!("{} failed: {:?}", what, err),
+        }
+    }
+}
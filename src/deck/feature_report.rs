@@ -0,0 +1,198 @@
+// Command IDs and response layouts taken from SDL's Steam controller driver (`controller_constants.h`)
+
+use chrono::DateTime;
+use std::fmt;
+
+/// Settings of `SetSettingsValues`, from `ControllerSettings`
+const SETTING_LEFT_TRACKPAD_MODE: u8 = 7;
+const SETTING_RIGHT_TRACKPAD_MODE: u8 = 8;
+const SETTING_SMOOTH_ABSOLUTE_MOUSE: u8 = 24;
+const SETTING_LEFT_TRACKPAD_CLICK_PRESSURE: u8 = 52;
+const SETTING_RIGHT_TRACKPAD_CLICK_PRESSURE: u8 = 53;
+/// `TRACKPAD_NONE` of `TrackpadDPadMode`
+const TRACKPAD_NONE: u16 = 7;
+
+/// Feature report commands of the controller, the first byte of a request and of its response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureCommand {
+    ClearDigitalMappings,
+    GetAttributesValues,
+    SetDefaultDigitalMappings,
+    SetSettingsValues,
+    LoadDefaultSettings,
+    TriggerHapticPulse,
+    GetStringAttribute,
+}
+
+impl FeatureCommand {
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0x81 => Some(FeatureCommand::ClearDigitalMappings),
+            0x83 => Some(FeatureCommand::GetAttributesValues),
+            0x85 => Some(FeatureCommand::SetDefaultDigitalMappings),
+            0x87 => Some(FeatureCommand::SetSettingsValues),
+            0x8E => Some(FeatureCommand::LoadDefaultSettings),
+            0x8F => Some(FeatureCommand::TriggerHapticPulse),
+            0xAE => Some(FeatureCommand::GetStringAttribute),
+            _ => None,
+        }
+    }
+
+    pub fn id(self) -> u8 {
+        match self {
+            FeatureCommand::ClearDigitalMappings => 0x81,
+            FeatureCommand::GetAttributesValues => 0x83,
+            FeatureCommand::SetDefaultDigitalMappings => 0x85,
+            FeatureCommand::SetSettingsValues => 0x87,
+            FeatureCommand::LoadDefaultSettings => 0x8E,
+            FeatureCommand::TriggerHapticPulse => 0x8F,
+            FeatureCommand::GetStringAttribute => 0xAE,
+        }
+    }
+
+    /// Request sending this command with `payload`: `[command, payload length, payload..]`
+    pub fn request(self, payload: &[u8]) -> Vec<u8> {
+        [&[self.id(), payload.len() as u8], payload].concat()
+    }
+}
+
+/// Requests turning off the controller's own keyboard and mouse emulation ("lizard mode"): its
+/// button mappings are cleared and the trackpads stop moving the cursor and clicking. The
+/// controller turns it back on by itself after a while, so they have to be repeated.
+pub fn lizard_mode_off() -> [Vec<u8>; 2] {
+    let settings: [(u8, u16); 5] = [
+        (SETTING_SMOOTH_ABSOLUTE_MOUSE, 0),
+        (SETTING_LEFT_TRACKPAD_MODE, TRACKPAD_NONE),
+        (SETTING_RIGHT_TRACKPAD_MODE, TRACKPAD_NONE),
+        (SETTING_LEFT_TRACKPAD_CLICK_PRESSURE, 0xFFFF),
+        (SETTING_RIGHT_TRACKPAD_CLICK_PRESSURE, 0xFFFF),
+    ];
+    let payload: Vec<u8> = settings
+        .into_iter()
+        .flat_map(|(setting, value)| [[setting].as_slice(), &value.to_le_bytes()].concat())
+        .collect();
+    [
+        FeatureCommand::ClearDigitalMappings.request(&[]),
+        FeatureCommand::SetSettingsValues.request(&payload),
+    ]
+}
+
+/// Requests restoring the controller's default button mappings and settings, which turns its
+/// keyboard and mouse emulation back on
+pub fn lizard_mode_on() -> [Vec<u8>; 2] {
+    [
+        FeatureCommand::SetDefaultDigitalMappings.request(&[]),
+        FeatureCommand::LoadDefaultSettings.request(&[]),
+    ]
+}
+
+/// A decoded feature report response: `[command, payload length, payload..]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeatureResponse {
+    /// `(attribute, value)` pairs of `GetAttributesValues`
+    Attributes(Vec<(u8, u32)>),
+    /// One string attribute of `GetStringAttribute`
+    StringAttribute { attribute: u8, value: String },
+    /// Echo of a command that returns nothing, holds its payload
+    Ack { command: FeatureCommand, payload: Vec<u8> },
+}
+
+impl FeatureResponse {
+    /// `None` if the response doesn't start with a known command
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let (&id, rest) = data.split_first()?;
+        let command: FeatureCommand = FeatureCommand::from_id(id)?;
+        let (&len, rest) = rest.split_first()?;
+        let payload: &[u8] = &rest[..(len as usize).min(rest.len())];
+        let response: FeatureResponse = match command {
+            FeatureCommand::GetAttributesValues => FeatureResponse::Attributes(
+                payload
+                    .chunks_exact(5)
+                    .map(|entry| (entry[0], u32::from_le_bytes([entry[1], entry[2], entry[3], entry[4]])))
+                    .collect(),
+            ),
+            FeatureCommand::GetStringAttribute => {
+                let (&attribute, value) = payload.split_first()?;
+                let value: &[u8] = value.split(|byte| *byte == 0).next().unwrap_or_default();
+                FeatureResponse::StringAttribute {
+                    attribute,
+                    value: String::from_utf8_lossy(value).into_owned(),
+                }
+            }
+            _ => FeatureResponse::Ack {
+                command,
+                payload: payload.to_vec(),
+            },
+        };
+        Some(response)
+    }
+}
+
+/// Name of a `GetAttributesValues` attribute
+fn attribute_name(attribute: u8) -> Option<&'static str> {
+    match attribute {
+        0 => Some("unique ID"),
+        1 => Some("product ID"),
+        2 => Some("product revision"),
+        3 => Some("capabilities"),
+        4 => Some("firmware version"),
+        5 => Some("firmware build time"),
+        _ => None,
+    }
+}
+
+/// Name of a `GetStringAttribute` attribute
+fn string_attribute_name(attribute: u8) -> Option<&'static str> {
+    match attribute {
+        0 => Some("board serial"),
+        1 => Some("unit serial"),
+        _ => None,
+    }
+}
+
+impl fmt::Display for FeatureResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeatureResponse::Attributes(attributes) => {
+                write!(f, "{:?}", FeatureCommand::GetAttributesValues)?;
+                for (attribute, value) in attributes {
+                    match attribute_name(*attribute) {
+                        Some(name) => write!(f, "\n  {name}: ")?,
+                        None => write!(f, "\n  attribute {attribute}: ")?,
+                    }
+                    match (*attribute, DateTime::from_timestamp(*value as i64, 0)) {
+                        (5, Some(time)) => write!(f, "{}", time.format("%Y-%m-%d %H:%M:%S UTC"))?,
+                        _ => write!(f, "{value:#010x} ({value})")?,
+                    }
+                }
+                Ok(())
+            }
+            FeatureResponse::StringAttribute { attribute, value } => {
+                write!(f, "{:?}\n  ", FeatureCommand::GetStringAttribute)?;
+                match string_attribute_name(*attribute) {
+                    Some(name) => write!(f, "{name}: {value}"),
+                    None => write!(f, "attribute {attribute}: {value}"),
+                }
+            }
+            FeatureResponse::Ack { command, payload } if payload.is_empty() => write!(f, "{command:?}"),
+            FeatureResponse::Ack { command, payload } => write!(f, "{command:?}\n  payload: {payload:02x?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_lizard_mode_requests() {
+        let [clear, settings] = lizard_mode_off();
+        assert_eq!(clear, [0x81, 0x00]);
+        assert_eq!(
+            settings,
+            [0x87, 15, 24, 0, 0, 7, 7, 0, 8, 7, 0, 52, 0xFF, 0xFF, 53, 0xFF, 0xFF]
+        );
+        assert_eq!(lizard_mode_on(), [vec![0x85, 0x00], vec![0x8E, 0x00]]);
+    }
+
+}
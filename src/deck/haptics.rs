@@ -0,0 +1,127 @@
+// Pulse layout taken from SDL's Steam controller driver (`ID_TRIGGER_HAPTIC_PULSE`)
+
+use crate::deck::FeatureCommand;
+use crate::hid::HidDevice;
+use crate::prelude::*;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Longest "on" time of a tick at full intensity
+const MAX_TICK: Duration = Duration::from_micros(1000);
+/// How often the player thread checks whether it should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Which trackpad actuator plays a pulse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HapticSide {
+    Right = 0,
+    Left = 1,
+    Both = 2,
+}
+
+/// A train of `count` pulses, each `on` long and followed by `off`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HapticPulse {
+    pub side: HapticSide,
+    pub on: Duration,
+    pub off: Duration,
+    pub count: u16,
+}
+
+impl HapticPulse {
+    /// A single short click, scaled by `intensity` (`0.0..=1.0`)
+    pub fn tick(side: HapticSide, intensity: f32) -> Self {
+        Self {
+            side,
+            on: MAX_TICK.mul_f32(intensity.clamp(0.0, 1.0)),
+            off: Duration::ZERO,
+            count: 1,
+        }
+    }
+
+    /// Feature report that plays the pulse
+    pub fn feature_report(&self) -> Vec<u8> {
+        let micros = |duration: Duration| -> u16 { duration.as_micros().min(u16::MAX as u128) as u16 };
+        let mut report: Vec<u8> = vec![FeatureCommand::TriggerHapticPulse.id(), 7, self.side as u8];
+        report.extend_from_slice(&micros(self.on).to_le_bytes());
+        report.extend_from_slice(&micros(self.off).to_le_bytes());
+        report.extend_from_slice(&self.count.to_le_bytes());
+        report
+    }
+}
+
+/// Plays haptic pulses on a background thread, so the read pipeline never waits on a control
+/// transfer. Only holds a weak reference to the device, so the player can live in the device's
+/// own input callback.
+pub struct HapticPlayer {
+    sender: Sender<HapticPulse>,
+    thread: Option<JoinHandle<()>>,
+    stop_flag: Arc<Mutex<bool>>,
+}
+
+impl HapticPlayer {
+    pub fn spawn(dev: &Arc<Mutex<HidDevice>>) -> Self {
+        let dev: Weak<Mutex<HidDevice>> = Arc::downgrade(dev);
+        let (sender, receiver): (Sender<HapticPulse>, Receiver<HapticPulse>) = mpsc::channel();
+        let stop_flag: Arc<Mutex<bool>> = Arc::new(Mutex::new(false));
+        let thread_stop_flag: Arc<Mutex<bool>> = stop_flag.clone();
+
+        trace!("Entering thread `haptics`...");
+        let thread: JoinHandle<()> = thread::Builder::new()
+            .name("haptics".into())
+            .spawn(move || {
+                trace!("Entered thread");
+
+                loop {
+                    if *thread_stop_flag.lock().unwrap() {
+                        break;
+                    }
+                    match receiver.recv_timeout(POLL_INTERVAL) {
+                        Ok(pulse) => {
+                            let Some(dev) = dev.upgrade() else {
+                                break;
+                            };
+                            if let Err(err) = dev.lock().unwrap().request_feature_report(&pulse.feature_report()) {
+                                debug!("Failed to play haptic pulse {:?}: {}", pulse, err);
+                            }
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+
+                trace!("Exiting thread...");
+            })
+            .unwrap();
+
+        Self {
+            sender,
+            thread: Some(thread),
+            stop_flag,
+        }
+    }
+
+    /// Queues a pulse, pulses sent after the player stopped are dropped
+    pub fn play(&self, pulse: HapticPulse) {
+        let _ = self.sender.send(pulse);
+    }
+
+    pub fn stop(&mut self) {
+        *self.stop_flag.lock().unwrap() = true;
+        // The thread itself drops the player if it held the last reference to the device
+        if let Some(thread) = self.thread.take()
+            && thread.thread().id() != thread::current().id()
+        {
+            thread.join().ok();
+            trace!("Exited thread `haptics`");
+        }
+    }
+}
+
+impl Drop for HapticPlayer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
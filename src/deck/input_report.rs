@@ -0,0 +1,399 @@
+// Report layout taken from SDL's Steam Deck driver (`SteamDeckStatePacket_t`):
+// https://github.com/libsdl-org/SDL/blob/main/src/joystick/hidapi/steam/controller_structs.h
+
+use serde::{Deserialize, Serialize};
+use serde::de::{self, IntoDeserializer};
+use std::{error::Error, fmt, str::FromStr, time::Duration};
+
+/// `ucType` of a Deck controller state report
+const REPORT_TYPE_DECK_STATE: u8 = 0x09;
+/// Header (4 bytes) + state packet (56 bytes)
+const REPORT_MIN_LEN: usize = 60;
+
+const MAX_AXIS: f32 = i16::MAX as f32;
+/// Full scale of the gyro, ±2000°/s over the i16 range
+const GYRO_COUNTS_PER_DPS: f32 = 32768.0 / 2000.0;
+/// Full scale of the accelerometer, ±2g over the i16 range
+const ACCEL_COUNTS_PER_G: f32 = 32768.0 / 2.0;
+
+/// Every digital input of the Deck controller, including the capacitive touch sensors of the
+/// sticks and trackpads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeckButton {
+    A,
+    B,
+    X,
+    Y,
+    L1,
+    R1,
+    L2,
+    R2,
+    L4,
+    L5,
+    R4,
+    R5,
+    View,
+    Menu,
+    Steam,
+    Qam,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+    LeftStickClick,
+    RightStickClick,
+    LeftPadClick,
+    RightPadClick,
+    LeftStickTouch,
+    RightStickTouch,
+    LeftPadTouch,
+    RightPadTouch,
+}
+
+impl DeckButton {
+    pub const ALL: [DeckButton; 28] = [
+        DeckButton::A,
+        DeckButton::B,
+        DeckButton::X,
+        DeckButton::Y,
+        DeckButton::L1,
+        DeckButton::R1,
+        DeckButton::L2,
+        DeckButton::R2,
+        DeckButton::L4,
+        DeckButton::L5,
+        DeckButton::R4,
+        DeckButton::R5,
+        DeckButton::View,
+        DeckButton::Menu,
+        DeckButton::Steam,
+        DeckButton::Qam,
+        DeckButton::DpadUp,
+        DeckButton::DpadDown,
+        DeckButton::DpadLeft,
+        DeckButton::DpadRight,
+        DeckButton::LeftStickClick,
+        DeckButton::RightStickClick,
+        DeckButton::LeftPadClick,
+        DeckButton::RightPadClick,
+        DeckButton::LeftStickTouch,
+        DeckButton::RightStickTouch,
+        DeckButton::LeftPadTouch,
+        DeckButton::RightPadTouch,
+    ];
+
+    /// The capacitive touch sensors, these flicker more than real buttons
+    pub const TOUCH: [DeckButton; 4] = [
+        DeckButton::LeftStickTouch,
+        DeckButton::RightStickTouch,
+        DeckButton::LeftPadTouch,
+        DeckButton::RightPadTouch,
+    ];
+
+    /// Bit of this button in the 64 bit button field (`ulButtonsL | ulButtonsH << 32`)
+    fn mask(self) -> u64 {
+        match self {
+            DeckButton::R2 => 1 << 0,
+            DeckButton::L2 => 1 << 1,
+            DeckButton::R1 => 1 << 2,
+            DeckButton::L1 => 1 << 3,
+            DeckButton::Y => 1 << 4,
+            DeckButton::B => 1 << 5,
+            DeckButton::X => 1 << 6,
+            DeckButton::A => 1 << 7,
+            DeckButton::DpadUp => 1 << 8,
+            DeckButton::DpadRight => 1 << 9,
+            DeckButton::DpadLeft => 1 << 10,
+            DeckButton::DpadDown => 1 << 11,
+            DeckButton::View => 1 << 12,
+            DeckButton::Steam => 1 << 13,
+            DeckButton::Menu => 1 << 14,
+            DeckButton::L5 => 1 << 15,
+            DeckButton::R5 => 1 << 16,
+            DeckButton::LeftPadClick => 1 << 17,
+            DeckButton::RightPadClick => 1 << 18,
+            DeckButton::LeftPadTouch => 1 << 19,
+            DeckButton::RightPadTouch => 1 << 20,
+            DeckButton::LeftStickClick => 1 << 22,
+            DeckButton::RightStickClick => 1 << 26,
+            DeckButton::L4 => 1 << (32 + 9),
+            DeckButton::R4 => 1 << (32 + 10),
+            DeckButton::LeftStickTouch => 1 << (32 + 14),
+            DeckButton::RightStickTouch => 1 << (32 + 15),
+            DeckButton::Qam => 1 << (32 + 18),
+        }
+    }
+}
+
+impl FromStr for DeckButton {
+    type Err = String;
+
+    /// Parses the names used by profiles, e.g. `left_stick_click`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DeckButton::deserialize(s.into_deserializer()).map_err(|err: de::value::Error| err.to_string())
+    }
+}
+
+/// Position of an analog stick, positive Y is up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stick {
+    pub x: i16,
+    pub y: i16,
+    /// Capacitive touch sensor on top of the stick
+    pub touched: bool,
+}
+
+impl Stick {
+    /// Position scaled to `-1.0..=1.0` on both axes
+    pub fn normalized(&self) -> (f32, f32) {
+        (self.x as f32 / MAX_AXIS, self.y as f32 / MAX_AXIS)
+    }
+}
+
+/// State of one of the square trackpads, positive Y is up.
+/// The position is only meaningful while `touched` is set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Trackpad {
+    pub x: i16,
+    pub y: i16,
+    pub pressure: u16,
+    pub touched: bool,
+}
+
+impl Trackpad {
+    /// Position scaled to `-1.0..=1.0` on both axes
+    pub fn normalized(&self) -> (f32, f32) {
+        (self.x as f32 / MAX_AXIS, self.y as f32 / MAX_AXIS)
+    }
+}
+
+/// A decoded Deck controller state report
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputReport {
+    /// Time the report was received, relative to when reading started
+    pub timestamp: Duration,
+    /// Incremented by the controller for every report it sends
+    pub sequence: u32,
+    buttons: u64,
+    pub left_stick: Stick,
+    pub right_stick: Stick,
+    pub left_pad: Trackpad,
+    pub right_pad: Trackpad,
+    /// Raw trigger travel, `0..=32767`
+    pub left_trigger: u16,
+    pub right_trigger: u16,
+    /// Acceleration, see [`InputReport::accel_g`]
+    pub accel: [i16; 3],
+    /// Angular velocity around X (pitch), Y (roll) and Z (yaw), see [`InputReport::gyro_dps`]
+    pub gyro: [i16; 3],
+    /// Orientation quaternion computed by the controller firmware (W, X, Y, Z)
+    pub orientation: [i16; 4],
+}
+
+impl InputReport {
+    /// Decodes a raw report as handed out by `HidDevice::set_on_input_received`
+    pub fn parse(data: &[u8], timestamp: Duration) -> Result<Self, ReportError> {
+        if data.len() < REPORT_MIN_LEN {
+            return Err(ReportError::TooShort(data.len()));
+        } else if data[2] != REPORT_TYPE_DECK_STATE {
+            return Err(ReportError::UnknownType(data[2]));
+        }
+
+        let u16_at = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let i16_at = |offset: usize| i16::from_le_bytes([data[offset], data[offset + 1]]);
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+        };
+
+        let buttons: u64 = u32_at(8) as u64 | (u32_at(12) as u64) << 32;
+        Ok(Self {
+            timestamp,
+            sequence: u32_at(4),
+            buttons,
+            left_pad: Trackpad {
+                x: i16_at(16),
+                y: i16_at(18),
+                pressure: u16_at(56),
+                touched: buttons & DeckButton::LeftPadTouch.mask() != 0,
+            },
+            right_pad: Trackpad {
+                x: i16_at(20),
+                y: i16_at(22),
+                pressure: u16_at(58),
+                touched: buttons & DeckButton::RightPadTouch.mask() != 0,
+            },
+            accel: [i16_at(24), i16_at(26), i16_at(28)],
+            gyro: [i16_at(30), i16_at(32), i16_at(34)],
+            orientation: [i16_at(36), i16_at(38), i16_at(40), i16_at(42)],
+            left_trigger: u16_at(44),
+            right_trigger: u16_at(46),
+            left_stick: Stick {
+                x: i16_at(48),
+                y: i16_at(50),
+                touched: buttons & DeckButton::LeftStickTouch.mask() != 0,
+            },
+            right_stick: Stick {
+                x: i16_at(52),
+                y: i16_at(54),
+                touched: buttons & DeckButton::RightStickTouch.mask() != 0,
+            },
+        })
+    }
+
+    pub fn is_pressed(&self, button: DeckButton) -> bool {
+        self.buttons & button.mask() != 0
+    }
+
+    /// Used to build synthetic reports
+    pub fn set_button(&mut self, button: DeckButton, pressed: bool) {
+        if pressed {
+            self.buttons |= button.mask();
+        } else {
+            self.buttons &= !button.mask();
+        }
+    }
+
+    /// Iterates over every button that is currently held
+    pub fn pressed_buttons(&self) -> impl Iterator<Item = DeckButton> + '_ {
+        DeckButton::ALL.into_iter().filter(|button| self.is_pressed(*button))
+    }
+
+    /// Trigger travel scaled to `0.0..=1.0`
+    pub fn left_trigger_normalized(&self) -> f32 {
+        (self.left_trigger as f32 / MAX_AXIS).min(1.0)
+    }
+
+    /// Trigger travel scaled to `0.0..=1.0`
+    pub fn right_trigger_normalized(&self) -> f32 {
+        (self.right_trigger as f32 / MAX_AXIS).min(1.0)
+    }
+
+    /// Angular velocity in degrees per second
+    pub fn gyro_dps(&self) -> [f32; 3] {
+        self.gyro.map(|value| value as f32 / GYRO_COUNTS_PER_DPS)
+    }
+
+    /// Acceleration in g, gravity included
+    pub fn accel_g(&self) -> [f32; 3] {
+        self.accel.map(|value| value as f32 / ACCEL_COUNTS_PER_G)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReportError {
+    /// The report is shorter than a state report, holds the actual length
+    TooShort(usize),
+    /// The report is not a controller state report, holds its type
+    UnknownType(u8),
+}
+
+impl fmt::Display for ReportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportError::TooShort(len) => {
+                write!(f, "input report is {len} bytes long, expected at least {REPORT_MIN_LEN}")
+            }
+            ReportError::UnknownType(report_type) => write!(f, "unknown input report type {report_type:#04x}"),
+        }
+    }
+}
+
+impl Error for ReportError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 64 byte state report with every field set to a distinct value
+    fn state_report() -> Vec<u8> {
+        let mut data: Vec<u8> = vec![0; 64];
+        data[2] = REPORT_TYPE_DECK_STATE;
+        let mut put = |offset: usize, bytes: &[u8]| data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        put(4, &1234u32.to_le_bytes());
+        // A, L2, left pad touch and left stick touch, L4 and QAM in the high word
+        put(8, &((1u32 << 7) | (1 << 1) | (1 << 19)).to_le_bytes());
+        put(12, &((1u32 << 9) | (1 << 14) | (1 << 18)).to_le_bytes());
+        put(16, &(-100i16).to_le_bytes());
+        put(18, &200i16.to_le_bytes());
+        put(20, &300i16.to_le_bytes());
+        put(22, &(-400i16).to_le_bytes());
+        put(24, &16384i16.to_le_bytes());
+        put(26, &(-8192i16).to_le_bytes());
+        put(28, &0i16.to_le_bytes());
+        put(30, &1638i16.to_le_bytes());
+        put(32, &(-3277i16).to_le_bytes());
+        put(34, &16384i16.to_le_bytes());
+        put(36, &1i16.to_le_bytes());
+        put(38, &2i16.to_le_bytes());
+        put(40, &3i16.to_le_bytes());
+        put(42, &4i16.to_le_bytes());
+        put(44, &32767u16.to_le_bytes());
+        put(46, &1000u16.to_le_bytes());
+        put(48, &(-32768i16).to_le_bytes());
+        put(50, &32767i16.to_le_bytes());
+        put(52, &5i16.to_le_bytes());
+        put(54, &(-6i16).to_le_bytes());
+        put(56, &700u16.to_le_bytes());
+        put(58, &800u16.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn parses_state_report() {
+        let timestamp: Duration = Duration::from_millis(16);
+        let report: InputReport = InputReport::parse(&state_report(), timestamp).unwrap();
+
+        assert_eq!(report.timestamp, timestamp);
+        assert_eq!(report.sequence, 1234);
+        let pressed: Vec<DeckButton> = report.pressed_buttons().collect();
+        assert_eq!(
+            pressed,
+            [
+                DeckButton::A,
+                DeckButton::L2,
+                DeckButton::L4,
+                DeckButton::Qam,
+                DeckButton::LeftStickTouch,
+                DeckButton::LeftPadTouch,
+            ]
+        );
+        assert_eq!(report.left_pad, Trackpad { x: -100, y: 200, pressure: 700, touched: true });
+        assert_eq!(report.right_pad, Trackpad { x: 300, y: -400, pressure: 800, touched: false });
+        assert_eq!(report.accel, [16384, -8192, 0]);
+        assert_eq!(report.gyro, [1638, -3277, 16384]);
+        assert_eq!(report.orientation, [1, 2, 3, 4]);
+        assert_eq!((report.left_trigger, report.right_trigger), (32767, 1000));
+        assert_eq!(report.left_stick, Stick { x: -32768, y: 32767, touched: true });
+        assert_eq!(report.right_stick, Stick { x: 5, y: -6, touched: false });
+    }
+
+    #[test]
+    fn scales_motion_and_triggers() {
+        let report: InputReport = InputReport::parse(&state_report(), Duration::ZERO).unwrap();
+
+        assert_eq!(report.accel_g(), [1.0, -0.5, 0.0]);
+        let [pitch, roll, yaw] = report.gyro_dps();
+        assert!((pitch - 100.0).abs() < 0.1 && (roll + 200.0).abs() < 0.1 && (yaw - 1000.0).abs() < 0.1);
+        assert_eq!(report.left_trigger_normalized(), 1.0);
+        assert_eq!(report.left_stick.normalized().1, 1.0);
+    }
+
+    #[test]
+    fn rejects_short_report() {
+        let data: Vec<u8> = state_report();
+        assert_eq!(
+            InputReport::parse(&data[..REPORT_MIN_LEN - 1], Duration::ZERO),
+            Err(ReportError::TooShort(REPORT_MIN_LEN - 1))
+        );
+        assert_eq!(InputReport::parse(&[], Duration::ZERO), Err(ReportError::TooShort(0)));
+        assert!(InputReport::parse(&data[..REPORT_MIN_LEN], Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn rejects_other_report_types() {
+        let mut data: Vec<u8> = state_report();
+        data[2] = 0x01;
+        assert_eq!(InputReport::parse(&data, Duration::ZERO), Err(ReportError::UnknownType(0x01)));
+    }
+}
@@ -0,0 +1,7 @@
+mod feature_report;
+mod haptics;
+mod input_report;
+
+pub use self::feature_report::{FeatureCommand, FeatureResponse, lizard_mode_off, lizard_mode_on};
+pub use self::haptics::{HapticPlayer, HapticPulse, HapticSide};
+pub use self::input_report::{DeckButton, InputReport, ReportError, Stick, Trackpad};
@@ -6,16 +6,57 @@ use crate::prelude::*;
 use crate::set_priority;
 use rusb::{
     ConfigDescriptor, Context, Device, DeviceHandle, DeviceList, Direction, Error as UsbError,
-    UsbContext,
+    InterfaceDescriptor, UsbContext,
 };
 use std::sync::{Arc, Mutex, MutexGuard};
-use std::{error::Error, thread::{self, JoinHandle}, time::Duration};
+use std::{error::Error, fmt, thread::{self, JoinHandle}, time::Duration};
+
+/// Size of the input reports, `open()` reads from the endpoint with this packet size
+pub const INPUT_REPORT_LEN: usize = 64;
+
+/// Timeout of the control transfers of `request_feature_report()`
+pub const FEATURE_REPORT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Interface and endpoint `HidDevice::open()` reads the controller input from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputInterface {
+    pub config: u8,
+    pub interface: u8,
+    pub setting: u8,
+    pub endpoint: u8,
+}
+
+/// `HidDevice::open()` opened matching devices, but none has the serial number it was asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerialNotFound {
+    pub serial: String,
+    /// Serial numbers of the devices that were opened instead, `None` if one didn't report any
+    pub found: Vec<Option<String>>,
+}
+
+impl fmt::Display for SerialNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let found: Vec<&str> = self.found.iter().map(|serial| serial.as_deref().unwrap_or("none")).collect();
+        write!(
+            f,
+            "no connected controller has the serial number {}, found {}",
+            self.serial,
+            found.join(", ")
+        )
+    }
+}
+
+impl Error for SerialNotFound {}
 
 pub struct HidDevice {
     context: Arc<Mutex<Context>>,
     handle: Option<Arc<Mutex<DeviceHandle<Context>>>>,
     vid: u16,
     pid: u16,
+    /// Read from the device descriptor on `open()`
+    serial_number: Option<String>,
+    /// Serial number `open()` looks for when several matching devices are connected
+    serial_filter: Option<String>,
     config: u8,
     interface: u8,
     setting: u8,
@@ -37,11 +78,13 @@ impl HidDevice {
             handle: None,
             vid: vid,
             pid: pid,
+            serial_number: None,
+            serial_filter: None,
             config: 0,
             interface: 0,
             setting: 0,
             endpoint: 0x00,
-            input_buffer_len: 64,
+            input_buffer_len: INPUT_REPORT_LEN,
             control_buffer_len: 64,
             on_input_received: None,
             read_thread: None,
@@ -51,53 +94,61 @@ impl HidDevice {
         })
     }
 
-    pub fn open(&mut self) -> Result<(), UsbError> {
+    pub fn open(&mut self) -> Result<(), Box<dyn Error>> {
         let devices: DeviceList<Context> = self.context.lock().unwrap().devices()?;
-        let device: Device<Context> = devices
-            .iter()
-            .find(|d| {
-                if let Ok(desc) = d.device_descriptor() {
-                    desc.vendor_id() == self.vid && desc.product_id() == self.pid
-                } else {
-                    false
-                }
-            })
-            .ok_or(UsbError::NoDevice)?;
-        let handle: DeviceHandle<Context> = device.open()?;
-
-        // Grab the correct interface & input endpoint address
-        let mut found_interface: bool = false;
-        let config_desc: ConfigDescriptor = device.config_descriptor(0)?;
-        'out: for interface in config_desc.interfaces() {
-            for interface_desc in interface.descriptors() {
-                // Proceed only if interface is an HID interface
-                if interface_desc.class_code() == 0x03 {
-                    for endpoint_desc in interface_desc.endpoint_descriptors() {
-                        // Accept the interface & endpoint if they meet the requirements for the Controller Data Input
-                        if endpoint_desc.max_packet_size() == self.input_buffer_len as u16
-                            && endpoint_desc.direction() == Direction::In
-                        {
-                            self.config = config_desc.number();
-                            self.interface = interface_desc.interface_number();
-                            self.setting = interface_desc.setting_number();
-                            self.endpoint = endpoint_desc.address();
-                            found_interface = true;
-                            break 'out;
-                        }
-                    }
+        // Keeps the error of the last device that failed to open, it explains more than `NoDevice`
+        let mut opened: Result<(Device<Context>, DeviceHandle<Context>), UsbError> = Err(UsbError::NoDevice);
+        // Serial numbers of the devices that opened but didn't match the filter
+        let mut other_serials: Vec<Option<String>> = Vec::new();
+        for d in devices.iter() {
+            // Devices whose descriptor can't be read aren't candidates, don't give up on the others
+            let Ok(desc) = d.device_descriptor() else {
+                continue;
+            };
+            if desc.vendor_id() != self.vid || desc.product_id() != self.pid {
+                continue;
+            }
+            let handle: DeviceHandle<Context> = match d.open() {
+                Ok(handle) => handle,
+                Err(err) => {
+                    opened = Err(err);
+                    continue;
                 }
+            };
+            let serial: Option<String> = handle.read_serial_number_string_ascii(&desc).ok();
+            if self.serial_filter.is_none() || self.serial_filter == serial {
+                self.serial_number = serial;
+                opened = Ok((d, handle));
+                break;
             }
+            other_serials.push(serial);
         }
+        let (device, handle) = match (opened, &self.serial_filter) {
+            (Ok(opened), _) => opened,
+            (Err(_), Some(serial)) if !other_serials.is_empty() => {
+                return Err(Box::new(SerialNotFound {
+                    serial: serial.clone(),
+                    found: other_serials,
+                }));
+            }
+            (Err(err), _) => return Err(err.into()),
+        };
 
+        // Grab the correct interface & input endpoint address
         // If no matching interface was found, return an Error
         // This ensures that `self.interface` and `self.endpoint` are always valid values
-        if !found_interface {
-            return Err(UsbError::Other);
-        }
+        let config_desc: ConfigDescriptor = device.config_descriptor(0)?;
+        let input: InputInterface =
+            find_input_interface(&config_desc, self.input_buffer_len as u16).ok_or(UsbError::Other)?;
+        self.config = input.config;
+        self.interface = input.interface;
+        self.setting = input.setting;
+        self.endpoint = input.endpoint;
 
         debug!("Device Handle info:");
         debug!("  VID: {:#04x}", self.vid,);
         debug!("  PID: {:#04x}", self.pid,);
+        debug!("  Serial: {:?}", self.serial_number);
         debug!("  Config: {}", self.config);
         debug!("  Interface: {}", self.interface,);
         debug!("  Setting: {}", self.setting);
@@ -127,6 +178,17 @@ impl HidDevice {
         Ok(())
     }
 
+    /// Makes `open()` pick the device with this serial number, `None` picks the first matching
+    /// device
+    pub fn set_serial_filter(&mut self, serial: Option<String>) {
+        self.serial_filter = serial;
+    }
+
+    /// Serial number of the opened device, `None` before `open()` or if the device has none
+    pub fn serial_number(&self) -> Option<&str> {
+        self.serial_number.as_deref()
+    }
+
     /// May panic if the read thread encounters a problem joining the main thread
     pub fn close(&mut self) -> Result<(), UsbError> {
         if !self.active {
@@ -188,6 +250,17 @@ impl HidDevice {
         &self,
         request: &[u8],
     ) -> Result<(usize, Vec<u8>), Box<dyn Error>> {
+        self.send_feature_report(request, 0, FEATURE_REPORT_TIMEOUT)?;
+        self.get_feature_report(0, FEATURE_REPORT_TIMEOUT)
+    }
+
+    /// Sends a feature report (SET_REPORT) without reading the response
+    pub fn send_feature_report(
+        &self,
+        request: &[u8],
+        report_id: u8,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn Error>> {
         if !self.active {
             return Err(Box::new(UsbError::NoDevice));
         } else if request.len() > self.control_buffer_len {
@@ -208,10 +281,10 @@ impl HidDevice {
         debug!("====== WRITE USBHID PACKET ======");
         debug!("  bmRequestType: {:#02x}", request_type);
         debug!("  bRequest: 0x09");
-        debug!("  wValue: 0x0300");
+        debug!("  wValue: {:#06x}", feature_report_value(report_id));
         debug!("  wIndex: {}", self.interface as u16);
         debug!("  Data ({}): {:02x?}", request_full.len(), request_full);
-        debug!("  Timeout: 100ms");
+        debug!("  Timeout: {:?}", timeout);
         debug!("====== WRITE USBHID PACKET ======");
         debug!("Sending \"Write Control Transfer\" packet...");
         handle.write_control(
@@ -222,15 +295,30 @@ impl HidDevice {
             request_type,
             // bRequest: SET_REPORT (0x09)  --  The request function
             0x09,
-            // wValue: 0x0300  --  Idk what this specific value means
-            0x0300,
+            // wValue: 0x03XX  --  Report type Feature (0x03) in the high byte, report ID in the low byte
+            feature_report_value(report_id),
             // wIndex: 2  --  Specifies the interface number to send the packet to
             self.interface as u16,
             // Data  --  The data to send to the device
             &request_full,
-            Duration::from_millis(100),
+            timeout,
         )?;
         debug!("\"Write Control Transfer\" succeeded");
+        Ok(())
+    }
+
+    /// Reads a feature report (GET_REPORT), the response to the last one sent
+    pub fn get_feature_report(
+        &self,
+        report_id: u8,
+        timeout: Duration,
+    ) -> Result<(usize, Vec<u8>), Box<dyn Error>> {
+        if !self.active {
+            return Err(Box::new(UsbError::NoDevice));
+        }
+
+        let handle: MutexGuard<'_, DeviceHandle<Context>> =
+            self.handle.as_ref().unwrap().lock().unwrap();
 
         // Get feature report (GET_REPORT)
         let mut response: Vec<u8> = vec![0u8; self.control_buffer_len];
@@ -242,10 +330,10 @@ impl HidDevice {
         debug!("====== READ USBHID PACKET ======");
         debug!("  bmRequestType: {:#02x}", request_type);
         debug!("  bRequest: 0x09");
-        debug!("  wValue: 0x0300");
+        debug!("  wValue: {:#06x}", feature_report_value(report_id));
         debug!("  wIndex: {}", self.interface as u16);
-        debug!("  Data ({}): {:02x?}", request_full.len(), request_full);
-        debug!("  Timeout: 100ms");
+        debug!("  Data ({}): {:02x?}", response.len(), response);
+        debug!("  Timeout: {:?}", timeout);
         debug!("====== READ USBHID PACKET ======");
         debug!("Sending \"Read Control Transfer\" packet...");
         let len = handle.read_control(
@@ -256,13 +344,13 @@ impl HidDevice {
             request_type,
             // bRequest: GET_REPORT (0x01)  --  The request function
             0x01,
-            // wValue: 0x0300  --  Idk what this specific value means
-            0x0300,
+            // wValue: 0x03XX  --  Report type Feature (0x03) in the high byte, report ID in the low byte
+            feature_report_value(report_id),
             // wIndex: 2  --  Specifies the interface number to send the packet to
             self.interface as u16,
             // Data  --  The data to send to the device
             &mut response,
-            Duration::from_millis(100),
+            timeout,
         )?;
         debug!("\"Read Control Transfer\" succeeded");
 
@@ -373,6 +461,43 @@ impl HidDevice {
     }
 }
 
+/// `wValue` of a feature report control transfer
+fn feature_report_value(report_id: u8) -> u16 {
+    0x0300 | report_id as u16
+}
+
+/// Picks the first HID interface with an IN endpoint of `packet_size` bytes, the controller data
+/// input of the Deck. Its keyboard and mouse interfaces only have smaller endpoints.
+pub fn find_input_interface(config_desc: &ConfigDescriptor, packet_size: u16) -> Option<InputInterface> {
+    config_desc
+        .interfaces()
+        .flat_map(|interface| interface.descriptors())
+        .find_map(|interface_desc| {
+            Some(InputInterface {
+                config: config_desc.number(),
+                interface: interface_desc.interface_number(),
+                setting: interface_desc.setting_number(),
+                endpoint: input_endpoint(&interface_desc, packet_size)?,
+            })
+        })
+}
+
+/// Address of the endpoint `find_input_interface` accepts on this interface, `None` if the
+/// interface isn't HID or has no IN endpoint of `packet_size` bytes
+pub fn input_endpoint(interface_desc: &InterfaceDescriptor, packet_size: u16) -> Option<u8> {
+    // Proceed only if interface is an HID interface
+    if interface_desc.class_code() != 0x03 {
+        return None;
+    }
+    interface_desc
+        .endpoint_descriptors()
+        // Accept the interface & endpoint if they meet the requirements for the Controller Data Input
+        .find(|endpoint_desc| {
+            endpoint_desc.max_packet_size() == packet_size && endpoint_desc.direction() == Direction::In
+        })
+        .map(|endpoint_desc| endpoint_desc.address())
+}
+
 impl Drop for HidDevice {
     fn drop(&mut self) {
         if self.active {
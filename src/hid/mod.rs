@@ -1,3 +1,6 @@
 mod hid_device;
 
-pub use self::hid_device::HidDevice;
+pub use self::hid_device::{
+    FEATURE_REPORT_TIMEOUT, HidDevice, INPUT_REPORT_LEN, InputInterface, SerialNotFound, find_input_interface,
+    input_endpoint,
+};
@@ -1,7 +1,15 @@
+pub mod apps;
+pub mod calibration;
 pub mod cli_parser;
+pub mod commands;
+pub mod deck;
 pub mod hid;
 pub mod macros;
+pub mod mapping;
+pub mod output;
 pub mod prelude;
+pub mod processing;
+pub mod profile;
 pub mod setup;
 
 include!(concat!(env!("OUT_DIR"), "/codegen.rs"));
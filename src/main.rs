@@ -1,53 +1,29 @@
-use std::sync::{Arc, Mutex, MutexGuard};
-use std::{thread, time::Duration};
-use windecon::{cli_parser::Args, hid, prelude::*, setup};
-
-const VID: u16 = 0x28DE;
-const PID: u16 = 0x1205;
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let _args: Args = setup::setup_logger_and_args();
-    info!("Starting WinDeCon...");
-
-    let dev: Arc<Mutex<hid::HidDevice>> = Arc::new(Mutex::new(hid::HidDevice::new(VID, PID)?));
-
-    // BUG: Fix this not being called
-    // Might be caused by heartbeat not being sent
-    dev.lock().unwrap().set_on_input_received(|data| {
-        debug!("INPUT RECEIVED: {:?}", data);
-    });
-    dev.lock().unwrap().open()?;
-
-    let dev_clone: Arc<Mutex<hid::HidDevice>> = Arc::clone(&dev);
-    thread::Builder::new()
-        .name("heartbeat".into())
-        .spawn(move || {
-            for cycle in 0..1 {
-                let dev: MutexGuard<'_, hid::HidDevice> = dev_clone.lock().unwrap();
-                // Set lizard mode to True
-
-                // DEFAULT_MAPPING
-                match dev.request_feature_report(&[0x85, 0x00]) {
-                    Ok((_, response)) => info!("Response 1: {:02x?}", response),
-                    Err(err) => error!("Feature Report Request #1 returned an error: {:?}", err),
-                }
-
-                // DEFAULT_MOUSE
-                match dev.request_feature_report(&[0x8E, 0x00]) {
-                    Ok((_, response)) => info!("Response 2: {:02x?}", response),
-                    Err(err) => error!("Feature Report Request #2 returned an error: {:?}", err),
-                }
-
-                info!("Cycle {cycle} complete...");
-                thread::sleep(Duration::from_millis(1000));
-            }
-        })
-        .unwrap()
-        .join()
-        .unwrap();
-
-    info!("Closing!");
-    dev.lock().unwrap().close().unwrap();
-
-    Ok(())
+use std::process;
+use windecon::cli_parser::{Args, Subcommand};
+use windecon::{commands, setup};
+
+fn main() {
+    let args: Args = setup::setup_logger_and_args();
+
+    let result: Result<(), Box<dyn std::error::Error>> = match &args.command {
+        Subcommand::Run => commands::run(&args),
+        Subcommand::List { json } => commands::list(&args, *json),
+        Subcommand::Monitor => commands::monitor(&args),
+        Subcommand::FeatureSend { request, transfer } => commands::feature_send(&args, request, *transfer),
+        Subcommand::FeatureGet(transfer) => commands::feature_get(&args, *transfer),
+        Subcommand::ProfileCheck(path) => commands::check_profile(path),
+        Subcommand::RecordMacro { name, stop } => commands::record_macro(&args, name, *stop),
+        Subcommand::CalibrateSticks => commands::calibrate_sticks(&args),
+        Subcommand::CalibrateGyro => commands::calibrate_gyro(&args),
+        Subcommand::Record { path, duration } => commands::record(&args, path, *duration),
+        Subcommand::Replay { path } => commands::replay(&args, path),
+        Subcommand::Dump { format, fields, duration, count } => {
+            commands::dump(&args, *format, fields.as_deref(), *duration, *count)
+        }
+        Subcommand::Doctor => commands::doctor(&args),
+    };
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
 }
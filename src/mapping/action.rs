@@ -0,0 +1,113 @@
+use crate::mapping::{HeldOutputs, LayerMode};
+use crate::output::{DigitalOutput, OutputEvent};
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+
+/// What a Deck input does when it is pressed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Holds a single output for as long as the input is held
+    Output(DigitalOutput),
+    /// Presses every output in order and holds them, releasing in reverse order (e.g. `Ctrl+C`)
+    Combo(Vec<DigitalOutput>),
+    /// Stacks another layer of bindings on top of the current ones
+    Layer { layer: String, mode: LayerMode },
+    /// Plays a macro of the profile, pressing again while it plays cancels it
+    Macro(String),
+}
+
+impl Action {
+    /// Outputs held while the action is pressed, none for layers and macros
+    pub fn outputs(&self) -> &[DigitalOutput] {
+        match self {
+            Action::Output(output) => std::slice::from_ref(output),
+            Action::Combo(outputs) => outputs,
+            Action::Layer { .. } | Action::Macro(_) => &[],
+        }
+    }
+
+    pub fn press(&self, held: &mut HeldOutputs, events: &mut Vec<OutputEvent>) {
+        for output in self.outputs() {
+            held.press(*output, events);
+        }
+    }
+
+    pub fn release(&self, held: &mut HeldOutputs, events: &mut Vec<OutputEvent>) {
+        for output in self.outputs().iter().rev() {
+            held.release(*output, events);
+        }
+    }
+
+    /// Builds the action of a table like `{ layer = "aim", mode = "toggle" }` or `{ macro = "reload" }`
+    pub(crate) fn from_table(
+        layer: Option<String>,
+        mode: Option<LayerMode>,
+        macro_name: Option<String>,
+    ) -> Result<Action, String> {
+        match (layer, macro_name) {
+            (Some(layer), None) => Ok(Action::Layer {
+                layer,
+                mode: mode.unwrap_or_default(),
+            }),
+            (None, Some(_)) if mode.is_some() => Err("`mode` only applies to layers".into()),
+            (None, Some(macro_name)) => Ok(Action::Macro(macro_name)),
+            (Some(_), Some(_)) => Err("can't set both `layer` and `macro`".into()),
+            (None, None) => Err("expected either `layer` or `macro`".into()),
+        }
+    }
+}
+
+impl From<DigitalOutput> for Action {
+    fn from(output: DigitalOutput) -> Self {
+        Action::Output(output)
+    }
+}
+
+impl<'de> Deserialize<'de> for Action {
+    /// A single output (`"key:space"`), a list of outputs for a combo (`["key:left_ctrl", "key:c"]`),
+    /// a layer (`{ layer = "aim", mode = "toggle" }`) or a macro (`{ macro = "reload" }`)
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ActionVisitor;
+
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct ActionTable {
+            layer: Option<String>,
+            mode: Option<LayerMode>,
+            #[serde(rename = "macro")]
+            macro_name: Option<String>,
+        }
+
+        impl<'de> Visitor<'de> for ActionVisitor {
+            type Value = Action;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an output like `\"gamepad:a\"`, a list of outputs, a layer or a macro")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Action, E> {
+                value.parse().map(Action::Output).map_err(E::custom)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Action, A::Error> {
+                let mut outputs: Vec<DigitalOutput> = Vec::new();
+                while let Some(output) = seq.next_element()? {
+                    outputs.push(output);
+                }
+                if outputs.is_empty() {
+                    return Err(de::Error::invalid_length(0, &"at least one output"));
+                }
+                Ok(Action::Combo(outputs))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Action, A::Error> {
+                let table: ActionTable = ActionTable::deserialize(MapAccessDeserializer::new(map))?;
+                Action::from_table(table.layer, table.mode, table.macro_name).map_err(de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_any(ActionVisitor)
+    }
+}
@@ -0,0 +1,268 @@
+use crate::deck::DeckButton;
+use crate::mapping::{Action, LayerMode};
+use crate::output::DigitalOutput;
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+const DEFAULT_LONG_PRESS: Duration = Duration::from_millis(500);
+const DEFAULT_DOUBLE_PRESS: Duration = Duration::from_millis(300);
+const DEFAULT_TURBO_RATE: f32 = 10.0;
+/// Turbo rates in taps per second a profile may use, faster taps aren't seen by games anyway
+pub(crate) const MIN_TURBO_RATE: f32 = 0.1;
+pub(crate) const MAX_TURBO_RATE: f32 = 100.0;
+
+/// An action and the way the input has to be pressed to trigger it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub activator: Activator,
+    pub action: Action,
+}
+
+impl Binding {
+    pub fn regular(action: impl Into<Action>) -> Self {
+        Self {
+            activator: Activator::Regular,
+            action: action.into(),
+        }
+    }
+}
+
+impl From<Action> for Binding {
+    fn from(action: Action) -> Self {
+        Self::regular(action)
+    }
+}
+
+/// When a binding triggers its action. Evaluated on report timestamps, so all durations are
+/// measured in controller time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Activator {
+    /// Held for as long as the input is held. If the input also has a long or double press
+    /// binding, it instead taps once the press turned out to be neither.
+    Regular,
+    /// Held once the input was held for `threshold`, until it is released
+    LongPress { threshold: Duration },
+    /// Held on the second press if it starts within `window` of the first one
+    DoublePress { window: Duration },
+    /// Taps when the input is released
+    Release,
+    /// Held while the input and `with` are both pressed, in either order, suppressing the other
+    /// activators of both
+    Chord { with: DeckButton },
+    /// Repeatedly taps while the input is held, `rate` times per second
+    Turbo { rate: f32 },
+}
+
+/// Deserializes a bindings table, where every input takes a single binding or a list of them
+pub(crate) fn deserialize_map<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<DeckButton, Vec<Binding>>, D::Error> {
+    let bindings: BTreeMap<DeckButton, BindingList> = BTreeMap::deserialize(deserializer)?;
+    Ok(bindings.into_iter().map(|(button, list)| (button, list.0)).collect())
+}
+
+/// Every binding of an input. Accepts anything [`Action`] accepts, a binding table like
+/// `{ action = "key:e", activator = "long_press", threshold_ms = 400 }` or a list of binding tables.
+struct BindingList(Vec<Binding>);
+
+impl<'de> Deserialize<'de> for BindingList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BindingListVisitor;
+
+        impl<'de> Visitor<'de> for BindingListVisitor {
+            type Value = BindingList;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an output, a list of outputs, a binding table or a list of binding tables")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<BindingList, E> {
+                let output: DigitalOutput = value.parse().map_err(E::custom)?;
+                Ok(BindingList(vec![Binding::regular(output)]))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<BindingList, A::Error> {
+                let mut outputs: Vec<DigitalOutput> = Vec::new();
+                let mut bindings: Vec<Binding> = Vec::new();
+                while let Some(element) = seq.next_element()? {
+                    match element {
+                        ListElement::Output(output) => outputs.push(output),
+                        ListElement::Binding(binding) => bindings.push(binding),
+                    }
+                }
+
+                match (outputs.is_empty(), bindings.is_empty()) {
+                    (false, true) => Ok(BindingList(vec![Binding::regular(Action::Combo(outputs))])),
+                    (true, false) => Ok(BindingList(bindings)),
+                    (false, false) => Err(de::Error::custom("a list can't mix outputs and binding tables")),
+                    (true, true) => Err(de::Error::invalid_length(0, &"at least one output or binding")),
+                }
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<BindingList, A::Error> {
+                let raw: RawBinding = RawBinding::deserialize(MapAccessDeserializer::new(map))?;
+                Ok(BindingList(vec![raw.into_binding().map_err(de::Error::custom)?]))
+            }
+        }
+
+        deserializer.deserialize_any(BindingListVisitor)
+    }
+}
+
+/// An element of a list of bindings: an output of a combo, or one of several binding tables
+enum ListElement {
+    Output(DigitalOutput),
+    Binding(Binding),
+}
+
+impl<'de> Deserialize<'de> for ListElement {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ListElementVisitor;
+
+        impl<'de> Visitor<'de> for ListElementVisitor {
+            type Value = ListElement;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an output or a binding table")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<ListElement, E> {
+                value.parse().map(ListElement::Output).map_err(E::custom)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<ListElement, A::Error> {
+                let raw: RawBinding = RawBinding::deserialize(MapAccessDeserializer::new(map))?;
+                raw.into_binding().map(ListElement::Binding).map_err(de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_any(ListElementVisitor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ActivatorKind {
+    #[default]
+    Regular,
+    LongPress,
+    DoublePress,
+    Release,
+    Chord,
+    Turbo,
+}
+
+impl ActivatorKind {
+    fn name(self) -> &'static str {
+        match self {
+            ActivatorKind::Regular => "regular",
+            ActivatorKind::LongPress => "long_press",
+            ActivatorKind::DoublePress => "double_press",
+            ActivatorKind::Release => "release",
+            ActivatorKind::Chord => "chord",
+            ActivatorKind::Turbo => "turbo",
+        }
+    }
+}
+
+/// A binding table as written, every activator parameter is only valid for its own activator
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawBinding {
+    action: Option<Action>,
+    layer: Option<String>,
+    mode: Option<LayerMode>,
+    #[serde(rename = "macro")]
+    macro_name: Option<String>,
+    #[serde(default)]
+    activator: ActivatorKind,
+    threshold_ms: Option<u64>,
+    window_ms: Option<u64>,
+    with: Option<DeckButton>,
+    rate: Option<f32>,
+}
+
+impl RawBinding {
+    fn into_binding(self) -> Result<Binding, String> {
+        let action: Action = match self.action {
+            Some(_) if self.layer.is_some() || self.macro_name.is_some() || self.mode.is_some() => {
+                return Err("a binding with an `action` can't also set `layer`, `mode` or `macro`".into());
+            }
+            Some(action) => action,
+            None if self.layer.is_none() && self.macro_name.is_none() => {
+                return Err("a binding needs an `action`, a `layer` or a `macro`".into());
+            }
+            None => Action::from_table(self.layer, self.mode, self.macro_name)?,
+        };
+
+        for (set, parameter, kind) in [
+            (self.threshold_ms.is_some(), "threshold_ms", ActivatorKind::LongPress),
+            (self.window_ms.is_some(), "window_ms", ActivatorKind::DoublePress),
+            (self.with.is_some(), "with", ActivatorKind::Chord),
+            (self.rate.is_some(), "rate", ActivatorKind::Turbo),
+        ] {
+            if set && self.activator != kind {
+                return Err(format!("`{parameter}` only applies to the `{}` activator", kind.name()));
+            }
+        }
+        if self.threshold_ms == Some(0) || self.window_ms == Some(0) {
+            return Err("durations must be above 0 ms".into());
+        }
+
+        let activator: Activator = match self.activator {
+            ActivatorKind::Regular => Activator::Regular,
+            ActivatorKind::LongPress => Activator::LongPress {
+                threshold: self.threshold_ms.map(Duration::from_millis).unwrap_or(DEFAULT_LONG_PRESS),
+            },
+            ActivatorKind::DoublePress => Activator::DoublePress {
+                window: self.window_ms.map(Duration::from_millis).unwrap_or(DEFAULT_DOUBLE_PRESS),
+            },
+            ActivatorKind::Release => Activator::Release,
+            ActivatorKind::Chord => Activator::Chord {
+                with: self.with.ok_or("the `chord` activator needs a `with` button")?,
+            },
+            ActivatorKind::Turbo => {
+                let rate: f32 = self.rate.unwrap_or(DEFAULT_TURBO_RATE);
+                if !(MIN_TURBO_RATE..=MAX_TURBO_RATE).contains(&rate) {
+                    return Err(format!(
+                        "turbo rate {rate} is out of range, expected {MIN_TURBO_RATE} to {MAX_TURBO_RATE} taps a second"
+                    ));
+                }
+                Activator::Turbo { rate }
+            }
+        };
+
+        Ok(Binding { activator, action })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(table: &str) -> Result<Binding, String> {
+        let raw: RawBinding = toml::from_str(table).map_err(|err| err.to_string())?;
+        raw.into_binding()
+    }
+
+    #[test]
+    fn turbo_rate_in_range() {
+        let binding: Binding = parse("action = \"gamepad:a\"\nactivator = \"turbo\"\nrate = 20.0").unwrap();
+        assert_eq!(binding.activator, Activator::Turbo { rate: 20.0 });
+    }
+
+    #[test]
+    fn turbo_rate_too_high() {
+        assert!(parse("action = \"gamepad:a\"\nactivator = \"turbo\"\nrate = 1e10").is_err());
+    }
+
+    #[test]
+    fn turbo_rate_too_low() {
+        assert!(parse("action = \"gamepad:a\"\nactivator = \"turbo\"\nrate = 1e-30").is_err());
+        assert!(parse("action = \"gamepad:a\"\nactivator = \"turbo\"\nrate = nan").is_err());
+    }
+}
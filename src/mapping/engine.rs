@@ -0,0 +1,777 @@
+use crate::deck::{DeckButton, HapticPulse, HapticSide, InputReport};
+use crate::mapping::menu::MenuTracker;
+use crate::mapping::pad::{PadMapper, PadOutput};
+use crate::mapping::tracker::{ButtonTracker, Command};
+use crate::mapping::{
+    Action, ActionSource, Activator, Binding, HeldOutputs, LayerStack, MacroPlayer, MenuEvent, MenuInput,
+};
+use crate::output::{GamepadAxis, OutputEvent};
+use crate::processing::{
+    self, FlickStick, GyroAim, GyroProcessor, SensorFusion, TouchDebouncer, TriggerOutput, TriggerProcessor,
+};
+use crate::profile::{
+    GyroActivation, GyroMode, GyroSettings, Menu, Profile, Side, StickMode, StickSettings, TiltSettings, TrackpadMode,
+    TrackpadSettings, TriggerMode, TriggerSettings,
+};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// How long tapped actions (release activators, deferred regular presses) stay pressed, long
+/// enough for games polling at 20Hz to notice
+const TAP_DURATION: Duration = Duration::from_millis(50);
+
+/// Translates parsed input reports into output events according to a [`Profile`]
+pub struct MappingEngine {
+    profile: Profile,
+    touch: TouchDebouncer,
+    /// Activator state of every Deck button that is pressed or still settling. Each tracker
+    /// keeps the bindings it started with, so outputs are released as they were pressed even if
+    /// the profile or the active layers changed in between.
+    trackers: BTreeMap<DeckButton, ButtonTracker>,
+    /// Tapped actions waiting for their release, with the time it is due
+    taps: Vec<(Duration, ActionSource, Action)>,
+    held: HeldOutputs,
+    layers: LayerStack,
+    macros: MacroPlayer,
+    /// Mode state of the left and right trackpad
+    pads: [PadMapper; 2],
+    /// Actions held by the trackpad modes and the trigger pulls
+    analog_actions: BTreeMap<ActionSource, Vec<Action>>,
+    /// State of the left and right trigger
+    triggers: [TriggerProcessor; 2],
+    /// Flick stick state of the left and right stick
+    flicks: [FlickStick; 2],
+    gyro: GyroProcessor,
+    fusion: SensorFusion,
+    aim: GyroAim,
+    /// Whether a `toggle` activation turned the gyro on, and whether its button was held in the
+    /// previous report
+    gyro_toggled: bool,
+    gyro_button_held: bool,
+    /// Up vector tilt angles are measured from, taken from the first report
+    tilt_center: Option<[f32; 3]>,
+    /// Radial menu state of every input, indexed by `MenuInput as usize`
+    menus: [MenuTracker; 4],
+    /// Last value emitted for every axis, indexed by `GamepadAxis as usize`
+    axes: [Option<f32>; 6],
+}
+
+impl MappingEngine {
+    pub fn new(profile: Profile) -> Self {
+        Self {
+            profile,
+            touch: TouchDebouncer::new(),
+            trackers: BTreeMap::new(),
+            taps: Vec::new(),
+            held: HeldOutputs::new(),
+            layers: LayerStack::new(),
+            macros: MacroPlayer::new(),
+            pads: [PadMapper::new(), PadMapper::new()],
+            analog_actions: BTreeMap::new(),
+            triggers: [TriggerProcessor::new(), TriggerProcessor::new()],
+            flicks: [FlickStick::new(), FlickStick::new()],
+            gyro: GyroProcessor::new(),
+            fusion: SensorFusion::new(),
+            aim: GyroAim::new(),
+            gyro_toggled: false,
+            gyro_button_held: false,
+            tilt_center: None,
+            menus: MenuInput::ALL.map(MenuTracker::new),
+            axes: [None; 6],
+        }
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    pub fn layers(&self) -> &LayerStack {
+        &self.layers
+    }
+
+    pub fn gyro(&self) -> &GyroProcessor {
+        &self.gyro
+    }
+
+    pub fn fusion(&self) -> &SensorFusion {
+        &self.fusion
+    }
+
+    /// Swaps the profile and returns the events it causes. Buttons held during the swap keep their
+    /// old bindings until released, active layers the new profile doesn't define are skipped.
+    /// Everything else bound by the old profile starts over: open radial menus close without
+    /// selecting, playing macros are cancelled, and the trackpad modes, trigger pulls, flick
+    /// sticks and gyro release what they held.
+    pub fn set_profile(&mut self, profile: Profile) -> Vec<OutputEvent> {
+        let mut events: Vec<OutputEvent> = Vec::new();
+        for input in MenuInput::ALL {
+            let overlay: bool = bound_menu(&self.profile, input).is_some_and(|menu| menu.overlay);
+            if let Some(event) = self.menus[input as usize].close()
+                && overlay
+            {
+                events.push(OutputEvent::Menu(event));
+            }
+        }
+        self.macros.cancel_all(&mut self.held, &mut events);
+        let sources: Vec<ActionSource> = self.analog_actions.keys().copied().collect();
+        for source in sources {
+            self.set_analog_actions(source, Vec::new(), Duration::ZERO, &mut events);
+        }
+        self.pads = [PadMapper::new(), PadMapper::new()];
+        self.triggers = [TriggerProcessor::new(), TriggerProcessor::new()];
+        self.flicks = [FlickStick::new(), FlickStick::new()];
+        self.aim.reset();
+        self.gyro_toggled = false;
+        self.profile = profile;
+        events
+    }
+
+    /// Processes one report and returns the resulting output events, in order.
+    ///
+    /// Buttons that are already tracked go first so releases happen before presses, then new
+    /// presses bound to layers, then every other new press so a layer button pressed in the same
+    /// report as a regular button already applies to it.
+    pub fn process(&mut self, report: &InputReport) -> Vec<OutputEvent> {
+        let mut report: InputReport = report.clone();
+        self.touch.apply(&mut report, Duration::from_millis(self.profile.touch_debounce_ms));
+        let report: &InputReport = &report;
+        let mut events: Vec<OutputEvent> = Vec::new();
+        let now: Duration = report.timestamp;
+        let rate: [f32; 3] = self.gyro.update(report, self.profile.gyro.auto_calibrate);
+        self.fusion.update_report(self.profile.gyro.filter, report, rate);
+
+        let due: Vec<(Duration, ActionSource, Action)>;
+        (due, self.taps) = self.taps.drain(..).partition(|(at, _, _)| *at <= now);
+        for (_, source, action) in due {
+            self.release_action(source, &action, &mut events);
+        }
+
+        let mut tracked: Vec<DeckButton> = self.trackers.keys().copied().collect();
+        tracked.sort_by_key(|button| report.is_pressed(*button));
+        for button in tracked {
+            self.update(button, report, &mut events);
+        }
+
+        let pressed: Vec<DeckButton> = report
+            .pressed_buttons()
+            .filter(|button| !self.trackers.contains_key(button))
+            .collect();
+        for layer_pass in [true, false] {
+            for button in &pressed {
+                if self.trackers.contains_key(button) {
+                    continue;
+                }
+                let Some(bindings) = self.resolve(*button) else {
+                    continue;
+                };
+                let binds_layer: bool = bindings
+                    .iter()
+                    .any(|binding| matches!(binding.action, Action::Layer { .. }));
+                if binds_layer == layer_pass {
+                    let tracker: ButtonTracker = ButtonTracker::new(bindings.to_vec(), self.chord_partners(*button));
+                    self.trackers.insert(*button, tracker);
+                    self.update(*button, report, &mut events);
+                }
+            }
+        }
+        self.trackers.retain(|_, tracker| !tracker.is_idle());
+        self.macros.advance(now, &mut self.held, &mut events);
+
+        let mut motion: (i32, i32) = (0, 0);
+        let mut scroll: (i32, i32) = (0, 0);
+        let mut pad_sticks: [Option<(f32, f32)>; 2] = [None; 2];
+        for (index, side, pad, click_button, haptic_side) in [
+            (0, Side::Left, &report.left_pad, DeckButton::LeftPadClick, HapticSide::Left),
+            (1, Side::Right, &report.right_pad, DeckButton::RightPadClick, HapticSide::Right),
+        ] {
+            let settings: &TrackpadSettings = match index {
+                0 => &self.profile.trackpads.left,
+                _ => &self.profile.trackpads.right,
+            };
+            let output: PadOutput = self.pads[index].update(settings, pad, report.is_pressed(click_button), now);
+            if output.ticks > 0 && settings.haptic_ticks && self.profile.haptics.enabled {
+                events.push(OutputEvent::Haptic(HapticPulse::tick(haptic_side, self.profile.haptics.intensity)));
+            }
+            self.set_analog_actions(ActionSource::Pad(side), output.actions, now, &mut events);
+            motion = (motion.0 + output.motion.0, motion.1 + output.motion.1);
+            scroll = (scroll.0 + output.scroll.0, scroll.1 + output.scroll.1);
+            if let Some((stick, position)) = output.stick {
+                pad_sticks[stick as usize] = Some(position);
+            }
+        }
+        let mut gyro_stick: Option<(Side, (f32, f32))> = None;
+        if self.gyro_active(report) {
+            let settings: &GyroSettings = &self.profile.gyro;
+            let gravity: [f32; 3] = self.fusion.gravity();
+            match settings.mode {
+                GyroMode::Mouse => {
+                    let (x, y) = self.aim.mouse(settings, rate, gravity, now);
+                    motion = (motion.0 + x, motion.1 + y);
+                }
+                GyroMode::Stick => gyro_stick = Some((settings.stick, self.aim.stick(settings, rate, gravity, now))),
+                GyroMode::None => {}
+            }
+        } else {
+            self.aim.reset();
+        }
+        for (index, settings, stick) in [
+            (0, &self.profile.sticks.left, &report.left_stick),
+            (1, &self.profile.sticks.right, &report.right_stick),
+        ] {
+            if settings.mode == StickMode::FlickStick {
+                motion.0 += self.flicks[index].update(&settings.flick, stick.normalized(), now);
+            } else {
+                self.flicks[index].reset();
+            }
+        }
+        if motion != (0, 0) {
+            events.push(OutputEvent::MouseMove { x: motion.0, y: motion.1 });
+        }
+        if scroll != (0, 0) {
+            events.push(OutputEvent::Scroll { x: scroll.0, y: scroll.1 });
+        }
+
+        let left_pad: (f32, f32) =
+            processing::rotate_clockwise(report.left_pad.normalized(), self.profile.trackpads.left.rotation);
+        let right_pad: (f32, f32) =
+            processing::rotate_clockwise(report.right_pad.normalized(), self.profile.trackpads.right.rotation);
+        for (input, touched, position, click_button) in [
+            (MenuInput::LeftPad, report.left_pad.touched, left_pad, DeckButton::LeftPadClick),
+            (MenuInput::RightPad, report.right_pad.touched, right_pad, DeckButton::RightPadClick),
+            (MenuInput::LeftStick, true, report.left_stick.normalized(), DeckButton::LeftStickClick),
+            (MenuInput::RightStick, true, report.right_stick.normalized(), DeckButton::RightStickClick),
+        ] {
+            self.update_menu(input, touched, position, click_button, report, &mut events);
+        }
+
+        // A touched trackpad in joystick mode takes over the stick from the physical one
+        let mut sticks: [(f32, f32); 2] = [
+            pad_sticks[Side::Left as usize]
+                .unwrap_or_else(|| stick_output(&self.profile.sticks.left, report.left_stick.normalized())),
+            pad_sticks[Side::Right as usize]
+                .unwrap_or_else(|| stick_output(&self.profile.sticks.right, report.right_stick.normalized())),
+        ];
+        // The gyro adds to the stick so stick turning and gyro fine aim work together
+        if let Some((side, (x, y))) = gyro_stick {
+            let stick: &mut (f32, f32) = &mut sticks[side as usize];
+            let (x, y) = (stick.0 + x, stick.1 + y);
+            let magnitude: f32 = x.hypot(y).max(1.0);
+            *stick = (x / magnitude, y / magnitude);
+        }
+        let mut trigger_values: [f32; 2] = [0.0; 2];
+        for (index, side, travel) in [
+            (0, Side::Left, report.left_trigger_normalized()),
+            (1, Side::Right, report.right_trigger_normalized()),
+        ] {
+            let settings: &TriggerSettings = match index {
+                0 => &self.profile.triggers.left,
+                _ => &self.profile.triggers.right,
+            };
+            let output: TriggerOutput = self.triggers[index].update(settings, travel);
+            if settings.mode == TriggerMode::Gamepad {
+                trigger_values[index] = output.value;
+            }
+            let pulls: [(ActionSource, Option<Action>); 2] = [
+                (ActionSource::SoftPull(side), settings.soft_pull.clone().filter(|_| output.soft_pull)),
+                (ActionSource::FullPull(side), settings.full_pull.clone().filter(|_| output.full_pull)),
+            ];
+            for (source, action) in pulls {
+                self.set_analog_actions(source, action.into_iter().collect(), now, &mut events);
+            }
+        }
+
+        let [(left_x, left_y), (right_x, right_y)] = sticks;
+        let mut axes: [f32; 6] = [left_x, left_y, right_x, right_y, trigger_values[0], trigger_values[1]];
+        self.apply_tilt(report, &mut axes);
+        for axis in GamepadAxis::ALL {
+            self.set_axis(axis, axes[axis as usize], &mut events);
+        }
+
+        events
+    }
+
+    /// Releases every held output, stops every macro, drops every layer and centers every axis,
+    /// e.g. before the device is closed
+    pub fn release_all(&mut self) -> Vec<OutputEvent> {
+        let mut events: Vec<OutputEvent> = Vec::new();
+        self.macros.cancel_all(&mut self.held, &mut events);
+        self.trackers.clear();
+        self.taps.clear();
+        self.layers.clear();
+        self.pads = [PadMapper::new(), PadMapper::new()];
+        self.analog_actions.clear();
+        self.triggers = [TriggerProcessor::new(), TriggerProcessor::new()];
+        self.flicks = [FlickStick::new(), FlickStick::new()];
+        self.aim.reset();
+        self.gyro_toggled = false;
+        self.held.release_all(&mut events);
+        for axis in GamepadAxis::ALL {
+            self.set_axis(axis, 0.0, &mut events);
+        }
+        events
+    }
+
+    /// The bindings of `button` with the current layers, checked from the top of the stack down
+    /// to the base bindings
+    fn resolve(&self, button: DeckButton) -> Option<&[Binding]> {
+        self.layers
+            .names()
+            .filter_map(|name| self.profile.layers.get(name))
+            .find_map(|layer| layer.bindings.get(&button))
+            .or_else(|| self.profile.bindings.get(&button))
+            .map(Vec::as_slice)
+    }
+
+    /// Buttons with a chord binding that uses `button` as `with`, going by the bindings a
+    /// tracked button captured and the current layers for the rest
+    fn chord_partners(&self, button: DeckButton) -> Vec<DeckButton> {
+        DeckButton::ALL
+            .into_iter()
+            .filter(|other| {
+                let bindings: &[Binding] = match self.trackers.get(other) {
+                    Some(tracker) => tracker.bindings(),
+                    None => self.resolve(*other).unwrap_or_default(),
+                };
+                bindings
+                    .iter()
+                    .any(|binding| binding.activator == Activator::Chord { with: button })
+            })
+            .collect()
+    }
+
+    fn update(&mut self, button: DeckButton, report: &InputReport, events: &mut Vec<OutputEvent>) {
+        let Some(tracker) = self.trackers.get_mut(&button) else {
+            return;
+        };
+        let now: Duration = report.timestamp;
+        let commands: Vec<Command> = tracker.update(report.is_pressed(button), now, |other| {
+            report.is_pressed(other)
+        });
+
+        for command in commands {
+            let (Command::Press(index) | Command::Release(index) | Command::Tap(index)) = command;
+            let action: Action = self.trackers[&button].binding(index).action.clone();
+            match command {
+                Command::Press(_) => self.press_action(button.into(), &action, now, events),
+                Command::Release(_) => self.release_action(button.into(), &action, events),
+                // A tapped macro plays to the end rather than being cancelled by the tap's release
+                Command::Tap(_) => self.tap_action(button.into(), action, now, events),
+            }
+        }
+    }
+
+    /// Presses the action and releases it after [`TAP_DURATION`]. A tapped macro plays to the end
+    /// rather than being cancelled by the tap's release.
+    fn tap_action(&mut self, source: ActionSource, action: Action, now: Duration, events: &mut Vec<OutputEvent>) {
+        self.press_action(source, &action, now, events);
+        if !matches!(action, Action::Macro(_)) {
+            self.taps.push((now + TAP_DURATION, source, action));
+        }
+    }
+
+    /// Runs the radial menu of `input` if it is in `radial_menu` mode. Hovering another slice plays
+    /// a haptic tick, selecting one taps its action.
+    fn update_menu(
+        &mut self,
+        input: MenuInput,
+        touched: bool,
+        position: (f32, f32),
+        click_button: DeckButton,
+        report: &InputReport,
+        events: &mut Vec<OutputEvent>,
+    ) {
+        let side: HapticSide = match input {
+            MenuInput::LeftPad | MenuInput::LeftStick => HapticSide::Left,
+            MenuInput::RightPad | MenuInput::RightStick => HapticSide::Right,
+        };
+        let Some(menu) = bound_menu(&self.profile, input) else {
+            self.menus[input as usize].close();
+            return;
+        };
+        let tracker: &mut MenuTracker = &mut self.menus[input as usize];
+
+        // A stick counts as touching once it leaves the menu's center
+        let touching: bool = match input {
+            MenuInput::LeftPad | MenuInput::RightPad => touched,
+            MenuInput::LeftStick | MenuInput::RightStick => position.0.hypot(position.1) >= menu.deadzone,
+        };
+        let menu_events: Vec<MenuEvent> = tracker.update(menu, touching, position, report.is_pressed(click_button));
+        if menu_events.is_empty() {
+            return;
+        }
+        let menu: Menu = menu.clone();
+        for event in menu_events {
+            match event {
+                MenuEvent::Hovered(..) if self.profile.haptics.enabled => {
+                    events.push(OutputEvent::Haptic(HapticPulse::tick(side, self.profile.haptics.intensity)));
+                }
+                MenuEvent::Selected(_, slice) => {
+                    if let Some(action) = menu.slices.get(slice) {
+                        self.tap_action(ActionSource::Menu(input), action.clone(), report.timestamp, events);
+                    }
+                }
+                _ => {}
+            }
+            if menu.overlay {
+                events.push(OutputEvent::Menu(event));
+            }
+        }
+    }
+
+    fn press_action(&mut self, source: ActionSource, action: &Action, now: Duration, events: &mut Vec<OutputEvent>) {
+        match action {
+            Action::Layer { layer, mode } => {
+                let priority: i32 = self.profile.layers.get(layer).map(|layer| layer.priority).unwrap_or(0);
+                self.layers.activate(layer, *mode, priority, source);
+            }
+            // Pressing the input again while its macro plays cancels it
+            Action::Macro(_) if self.macros.is_playing(source) => {
+                self.macros.cancel(source, &mut self.held, events);
+            }
+            Action::Macro(name) => {
+                if let Some(macro_) = self.profile.macros.get(name) {
+                    self.macros.start(source, macro_, now);
+                }
+                self.layers.consume_one_shots();
+            }
+            _ => {
+                action.press(&mut self.held, events);
+                self.layers.consume_one_shots();
+            }
+        }
+    }
+
+    fn release_action(&mut self, source: ActionSource, action: &Action, events: &mut Vec<OutputEvent>) {
+        match action {
+            Action::Layer { .. } => self.layers.release(source),
+            Action::Macro(_) => self.macros.release(source, &mut self.held, events),
+            _ => action.release(&mut self.held, events),
+        }
+    }
+
+    /// Presses and releases actions attributed to `source` so exactly `actions` are held
+    fn set_analog_actions(
+        &mut self,
+        source: ActionSource,
+        actions: Vec<Action>,
+        now: Duration,
+        events: &mut Vec<OutputEvent>,
+    ) {
+        let held: Vec<Action> = self.analog_actions.remove(&source).unwrap_or_default();
+        for action in held.iter().rev().filter(|action| !actions.contains(action)) {
+            self.release_action(source, action, events);
+        }
+        for action in actions.iter().filter(|action| !held.contains(action)) {
+            self.press_action(source, action, now, events);
+        }
+        if !actions.is_empty() {
+            self.analog_actions.insert(source, actions);
+        }
+    }
+
+    /// Adds the tilt outputs of the profile to `axes`, indexed by `GamepadAxis as usize`
+    fn apply_tilt(&mut self, report: &InputReport, axes: &mut [f32; 6]) {
+        let tilt: &TiltSettings = &self.profile.tilt;
+        if tilt.roll.is_none() && tilt.pitch.is_none() {
+            return;
+        }
+        let gravity: [f32; 3] = self.fusion.gravity();
+        if tilt.recenter.is_some_and(|button| report.is_pressed(button)) {
+            self.tilt_center = Some(gravity);
+        }
+        let (roll, pitch) = processing::tilt_angles(gravity, *self.tilt_center.get_or_insert(gravity));
+        for (axis, angle) in [(&tilt.roll, roll), (&tilt.pitch, pitch)] {
+            let Some(axis) = axis else {
+                continue;
+            };
+            let value: f32 = processing::tilt_output(axis, angle);
+            let output: &mut f32 = &mut axes[axis.output as usize];
+            *output = if axis.output.is_trigger() {
+                output.max(value)
+            } else {
+                (*output + value).clamp(-1.0, 1.0)
+            };
+        }
+    }
+
+    /// Evaluates the gyro activation of the profile for this report
+    fn gyro_active(&mut self, report: &InputReport) -> bool {
+        match self.profile.gyro.activation {
+            GyroActivation::Always => true,
+            GyroActivation::LeftStickTouch => report.left_stick.touched,
+            GyroActivation::RightStickTouch => report.right_stick.touched,
+            GyroActivation::Hold(button) => report.is_pressed(button),
+            GyroActivation::Toggle(button) => {
+                let pressed: bool = report.is_pressed(button);
+                if pressed && !self.gyro_button_held {
+                    self.gyro_toggled = !self.gyro_toggled;
+                }
+                self.gyro_button_held = pressed;
+                self.gyro_toggled
+            }
+        }
+    }
+
+    fn set_axis(&mut self, axis: GamepadAxis, value: f32, events: &mut Vec<OutputEvent>) {
+        let last: &mut Option<f32> = &mut self.axes[axis as usize];
+        if *last != Some(value) {
+            *last = Some(value);
+            events.push(OutputEvent::Axis(axis, value));
+        }
+    }
+}
+
+/// The menu `input` opens, if it is in `radial_menu` mode
+fn bound_menu(profile: &Profile, input: MenuInput) -> Option<&Menu> {
+    let name: Option<&String> = match input {
+        MenuInput::LeftPad => (profile.trackpads.left.mode == TrackpadMode::RadialMenu)
+            .then_some(profile.trackpads.left.menu.as_ref())
+            .flatten(),
+        MenuInput::RightPad => (profile.trackpads.right.mode == TrackpadMode::RadialMenu)
+            .then_some(profile.trackpads.right.menu.as_ref())
+            .flatten(),
+        MenuInput::LeftStick => (profile.sticks.left.mode == StickMode::RadialMenu)
+            .then_some(profile.sticks.left.menu.as_ref())
+            .flatten(),
+        MenuInput::RightStick => (profile.sticks.right.mode == StickMode::RadialMenu)
+            .then_some(profile.sticks.right.menu.as_ref())
+            .flatten(),
+    };
+    name.and_then(|name| profile.menus.get(name))
+}
+
+/// Position of the emulated stick driven by a Deck stick, centered if the stick doesn't drive it
+fn stick_output(settings: &StickSettings, (x, y): (f32, f32)) -> (f32, f32) {
+    match settings.mode {
+        StickMode::Gamepad => processing::process_stick(settings, x, y),
+        StickMode::None | StickMode::RadialMenu | StickMode::FlickStick => (0.0, 0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{DigitalOutput, GamepadButton, Key};
+
+    const A: DigitalOutput = DigitalOutput::Gamepad(GamepadButton::A);
+    const KEY_R: DigitalOutput = DigitalOutput::Key(Key::R);
+    const DPAD_LEFT: DigitalOutput = DigitalOutput::Gamepad(GamepadButton::DpadLeft);
+
+    fn report(ms: u64, buttons: &[DeckButton]) -> InputReport {
+        let mut report: InputReport = InputReport::default();
+        report.timestamp = Duration::from_millis(ms);
+        for button in buttons {
+            report.set_button(*button, true);
+        }
+        report
+    }
+
+    /// The presses and releases of `events`, axes and everything else left out
+    fn digital(events: Vec<OutputEvent>) -> Vec<OutputEvent> {
+        events
+            .into_iter()
+            .filter(|event| matches!(event, OutputEvent::Press(_) | OutputEvent::Release(_)))
+            .collect()
+    }
+
+    fn axis(events: &[OutputEvent], axis: GamepadAxis) -> Option<f32> {
+        events.iter().rev().find_map(|event| match event {
+            OutputEvent::Axis(event_axis, value) if *event_axis == axis => Some(*value),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn centers_axes_on_first_report() {
+        let mut engine: MappingEngine = MappingEngine::new(Profile::default());
+        let events: Vec<OutputEvent> = engine.process(&report(0, &[]));
+        let expected: Vec<OutputEvent> = GamepadAxis::ALL.map(|axis| OutputEvent::Axis(axis, 0.0)).to_vec();
+        assert_eq!(events, expected);
+        assert_eq!(engine.process(&report(4, &[])), []);
+    }
+
+    #[test]
+    fn presses_and_releases_bound_button() {
+        let mut engine: MappingEngine = MappingEngine::new(Profile::default());
+        engine.process(&report(0, &[]));
+        assert_eq!(engine.process(&report(4, &[DeckButton::A])), [OutputEvent::Press(A)]);
+        assert_eq!(engine.process(&report(8, &[DeckButton::A])), []);
+        assert_eq!(engine.process(&report(12, &[])), [OutputEvent::Release(A)]);
+    }
+
+    #[test]
+    fn ignores_unbound_button() {
+        let mut engine: MappingEngine = MappingEngine::new(Profile::empty("test"));
+        assert_eq!(digital(engine.process(&report(0, &[DeckButton::L4]))), []);
+    }
+
+    #[test]
+    fn hold_layer_overrides_base_bindings() {
+        let profile: Profile = crate::profile::parse(
+            r#"
+            [bindings]
+            a = "gamepad:a"
+            l4 = { layer = "aim", mode = "hold" }
+
+            [layers.aim.bindings]
+            a = "key:r"
+            "#,
+        )
+        .unwrap();
+        let mut engine: MappingEngine = MappingEngine::new(profile);
+
+        assert_eq!(digital(engine.process(&report(0, &[DeckButton::L4]))), []);
+        assert_eq!(
+            engine.process(&report(4, &[DeckButton::L4, DeckButton::A])),
+            [OutputEvent::Press(KEY_R)]
+        );
+        // The held button keeps the binding it was pressed with after the layer is gone
+        assert_eq!(engine.process(&report(8, &[DeckButton::A])), []);
+        assert_eq!(engine.process(&report(12, &[])), [OutputEvent::Release(KEY_R)]);
+        assert_eq!(engine.process(&report(16, &[DeckButton::A])), [OutputEvent::Press(A)]);
+    }
+
+    #[test]
+    fn trigger_pulls_hold_layers_apart_from_trigger_button() {
+        let profile: Profile = crate::profile::parse(
+            r#"
+            [bindings]
+            l2 = { layer = "click", mode = "hold" }
+
+            [triggers.left]
+            soft_pull = { layer = "soft", mode = "hold" }
+            full_pull = { layer = "full", mode = "hold" }
+
+            [layers.click]
+            [layers.soft]
+            [layers.full]
+            "#,
+        )
+        .unwrap();
+        let mut engine: MappingEngine = MappingEngine::new(profile);
+        let names = |engine: &MappingEngine| {
+            let mut names: Vec<String> = engine.layers().names().map(str::to_string).collect();
+            names.sort();
+            names
+        };
+
+        let mut input: InputReport = report(0, &[DeckButton::L2]);
+        input.left_trigger = i16::MAX as u16;
+        engine.process(&input);
+        assert_eq!(names(&engine), ["click", "full", "soft"]);
+
+        // Easing off past the click point and the full pull keeps the soft pull's layer
+        input = report(4, &[]);
+        input.left_trigger = (i16::MAX / 4 * 3) as u16;
+        engine.process(&input);
+        assert_eq!(names(&engine), ["soft"]);
+
+        engine.process(&report(8, &[]));
+        assert_eq!(names(&engine), [] as [&str; 0]);
+    }
+
+    #[test]
+    fn maps_sticks_and_triggers_to_axes() {
+        let mut engine: MappingEngine = MappingEngine::new(Profile::default());
+        let mut input: InputReport = report(0, &[]);
+        input.left_stick.x = i16::MAX;
+        input.right_stick.y = i16::MIN;
+        input.right_trigger = i16::MAX as u16;
+        let events: Vec<OutputEvent> = engine.process(&input);
+
+        assert_eq!(axis(&events, GamepadAxis::LeftStickX), Some(1.0));
+        assert_eq!(axis(&events, GamepadAxis::LeftStickY), Some(0.0));
+        assert_eq!(axis(&events, GamepadAxis::RightStickY), Some(-1.0));
+        assert_eq!(axis(&events, GamepadAxis::RightTrigger), Some(1.0));
+        assert_eq!(axis(&events, GamepadAxis::LeftTrigger), Some(0.0));
+    }
+
+    #[test]
+    fn set_profile_closes_menus_hovering_slices_the_new_menu_lacks() {
+        let menu = |slices: &str| -> Profile {
+            crate::profile::parse(&format!(
+                r#"
+                [trackpads.left]
+                mode = "radial_menu"
+                menu = "weapons"
+
+                [menus.weapons]
+                slices = [{slices}]
+                "#
+            ))
+            .unwrap()
+        };
+        let mut engine: MappingEngine = MappingEngine::new(menu(r#""key:num1", "key:num2", "key:num3", "key:num4""#));
+        let mut input: InputReport = report(0, &[]);
+        input.left_pad.touched = true;
+        input.left_pad.x = i16::MIN;
+        engine.process(&input);
+
+        // The fourth slice was hovered, the new menu only has two
+        assert_eq!(engine.set_profile(menu(r#""key:num1", "key:num2""#)), []);
+        assert_eq!(digital(engine.process(&report(4, &[]))), []);
+    }
+
+    #[test]
+    fn set_profile_releases_trackpad_mode_outputs() {
+        let profile: Profile = crate::profile::parse(
+            r#"
+            [trackpads.left]
+            mode = "dpad"
+            dpad = { activation = "touch" }
+            "#,
+        )
+        .unwrap();
+        let mut engine: MappingEngine = MappingEngine::new(profile);
+        let mut input: InputReport = report(0, &[]);
+        input.left_pad.touched = true;
+        input.left_pad.x = i16::MIN;
+        assert_eq!(digital(engine.process(&input)), [OutputEvent::Press(DPAD_LEFT)]);
+
+        assert_eq!(engine.set_profile(Profile::default()), [OutputEvent::Release(DPAD_LEFT)]);
+        input.timestamp = Duration::from_millis(4);
+        assert_eq!(digital(engine.process(&input)), []);
+    }
+
+    #[test]
+    fn set_profile_cancels_macros_and_releases_trigger_pulls() {
+        let profile: Profile = crate::profile::parse(
+            r#"
+            [bindings]
+            a = { macro = "hold_r" }
+
+            [triggers.left]
+            soft_pull = "gamepad:a"
+
+            [macros.hold_r]
+            steps = [{ press = "key:r" }, { delay_ms = 1000 }, { release = "key:r" }]
+            "#,
+        )
+        .unwrap();
+        let mut engine: MappingEngine = MappingEngine::new(profile);
+        let mut input: InputReport = report(0, &[DeckButton::A]);
+        input.left_trigger = i16::MAX as u16;
+        assert_eq!(
+            digital(engine.process(&input)),
+            [OutputEvent::Press(KEY_R), OutputEvent::Press(A)]
+        );
+
+        let events: Vec<OutputEvent> = engine.set_profile(Profile::empty("test"));
+        assert_eq!(events, [OutputEvent::Release(KEY_R), OutputEvent::Release(A)]);
+        // The macro doesn't come back to release its output a second time
+        assert_eq!(digital(engine.process(&report(2000, &[]))), []);
+    }
+
+    #[test]
+    fn release_all_releases_held_outputs() {
+        let mut engine: MappingEngine = MappingEngine::new(Profile::default());
+        let mut input: InputReport = report(0, &[DeckButton::A]);
+        input.left_stick.x = i16::MAX;
+        engine.process(&input);
+
+        let events: Vec<OutputEvent> = engine.release_all();
+        assert_eq!(digital(events.clone()), [OutputEvent::Release(A)]);
+        assert_eq!(axis(&events, GamepadAxis::LeftStickX), Some(0.0));
+        assert_eq!(digital(engine.release_all()), []);
+    }
+}
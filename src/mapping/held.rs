@@ -0,0 +1,46 @@
+use crate::output::{DigitalOutput, OutputEvent};
+use std::collections::HashMap;
+
+/// Reference counts every held output so two inputs bound to the same output don't release it
+/// while the other one is still held
+#[derive(Debug, Default)]
+pub struct HeldOutputs {
+    counts: HashMap<DigitalOutput, u32>,
+}
+
+impl HeldOutputs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_held(&self, output: DigitalOutput) -> bool {
+        self.counts.contains_key(&output)
+    }
+
+    /// Emits a press event if the output wasn't held yet
+    pub fn press(&mut self, output: DigitalOutput, events: &mut Vec<OutputEvent>) {
+        let count: &mut u32 = self.counts.entry(output).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            events.push(OutputEvent::Press(output));
+        }
+    }
+
+    /// Emits a release event once the last holder of the output lets go
+    pub fn release(&mut self, output: DigitalOutput, events: &mut Vec<OutputEvent>) {
+        if let Some(count) = self.counts.get_mut(&output) {
+            *count -= 1;
+            if *count == 0 {
+                self.counts.remove(&output);
+                events.push(OutputEvent::Release(output));
+            }
+        }
+    }
+
+    /// Releases every held output regardless of how many inputs hold it
+    pub fn release_all(&mut self, events: &mut Vec<OutputEvent>) {
+        for (output, _) in self.counts.drain() {
+            events.push(OutputEvent::Release(output));
+        }
+    }
+}
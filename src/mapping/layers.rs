@@ -0,0 +1,98 @@
+use crate::mapping::ActionSource;
+use serde::Deserialize;
+
+/// How a layer binding activates its layer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayerMode {
+    /// Active while the button is held
+    #[default]
+    Hold,
+    /// Every press switches the layer on or off
+    Toggle,
+    /// Active until the next button press has been translated
+    OneShot,
+}
+
+#[derive(Debug)]
+struct ActiveLayer {
+    name: String,
+    mode: LayerMode,
+    priority: i32,
+    /// Activation order, breaks priority ties in favor of the newest layer
+    order: u64,
+    /// Input holding a `Hold` layer
+    source: Option<ActionSource>,
+}
+
+/// The layers currently stacked on top of the base bindings of a profile
+#[derive(Debug, Default)]
+pub struct LayerStack {
+    /// Sorted from lowest to highest priority
+    layers: Vec<ActiveLayer>,
+    next_order: u64,
+}
+
+impl LayerStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names of the active layers, highest priority first
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.layers.iter().rev().map(|layer| layer.name.as_str())
+    }
+
+    pub fn is_active(&self, name: &str) -> bool {
+        self.layers.iter().any(|layer| layer.name == name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Activates a layer pressed with `mode` by `source`
+    pub fn activate(&mut self, name: &str, mode: LayerMode, priority: i32, source: ActionSource) {
+        match mode {
+            LayerMode::Hold => self.push(name, mode, priority, Some(source)),
+            LayerMode::Toggle => {
+                let toggled: Option<usize> = self
+                    .layers
+                    .iter()
+                    .position(|layer| layer.name == name && layer.mode == LayerMode::Toggle);
+                match toggled {
+                    Some(index) => drop(self.layers.remove(index)),
+                    None => self.push(name, mode, priority, None),
+                }
+            }
+            LayerMode::OneShot => self.push(name, mode, priority, None),
+        }
+    }
+
+    /// Drops the `Hold` layers held by `source`
+    pub fn release(&mut self, source: ActionSource) {
+        self.layers
+            .retain(|layer| !(layer.mode == LayerMode::Hold && layer.source == Some(source)));
+    }
+
+    /// Drops the one-shot layers once a press went through them
+    pub fn consume_one_shots(&mut self) {
+        self.layers.retain(|layer| layer.mode != LayerMode::OneShot);
+    }
+
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    fn push(&mut self, name: &str, mode: LayerMode, priority: i32, source: Option<ActionSource>) {
+        self.layers.push(ActiveLayer {
+            name: name.into(),
+            mode,
+            priority,
+            order: self.next_order,
+            source,
+        });
+        self.next_order += 1;
+        self.layers.sort_by_key(|layer| (layer.priority, layer.order));
+    }
+}
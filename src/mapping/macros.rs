@@ -0,0 +1,420 @@
+use crate::mapping::{ActionSource, HeldOutputs};
+use crate::output::{DigitalOutput, OutputEvent};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// How long a `tap` step holds its output when it doesn't set `hold_ms`
+pub const DEFAULT_TAP_HOLD: Duration = Duration::from_millis(50);
+
+/// A named sequence of timed output events, e.g.
+///
+/// ```toml
+/// [macros.reload]
+/// steps = [{ tap = "key:r" }, { delay_ms = 100 }, { tap = "mouse:left", hold_ms = 30 }]
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Macro {
+    #[serde(deserialize_with = "deserialize_steps")]
+    pub steps: Vec<MacroStep>,
+    /// Stops playback as soon as the bound input is released instead of playing to the end
+    #[serde(default)]
+    pub cancel_on_release: bool,
+}
+
+/// A single step of a [`Macro`], exactly one of `press`, `release`, `tap` or `delay_ms`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "RawStep")]
+pub enum MacroStep {
+    Press(DigitalOutput),
+    Release(DigitalOutput),
+    /// Presses the output, waits `hold` and releases it again
+    Tap { output: DigitalOutput, hold: Duration },
+    Delay(Duration),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawStep {
+    press: Option<DigitalOutput>,
+    release: Option<DigitalOutput>,
+    tap: Option<DigitalOutput>,
+    hold_ms: Option<u64>,
+    delay_ms: Option<u64>,
+}
+
+impl TryFrom<RawStep> for MacroStep {
+    type Error = String;
+
+    fn try_from(raw: RawStep) -> Result<Self, Self::Error> {
+        if raw.hold_ms.is_some() && raw.tap.is_none() {
+            return Err("`hold_ms` only applies to `tap` steps".into());
+        }
+        match (raw.press, raw.release, raw.tap, raw.delay_ms) {
+            (Some(output), None, None, None) => Ok(MacroStep::Press(output)),
+            (None, Some(output), None, None) => Ok(MacroStep::Release(output)),
+            (None, None, Some(output), None) => Ok(MacroStep::Tap {
+                output,
+                hold: raw.hold_ms.map(Duration::from_millis).unwrap_or(DEFAULT_TAP_HOLD),
+            }),
+            (None, None, None, Some(delay_ms)) => Ok(MacroStep::Delay(Duration::from_millis(delay_ms))),
+            _ => Err("a step needs exactly one of `press`, `release`, `tap` or `delay_ms`".into()),
+        }
+    }
+}
+
+fn deserialize_steps<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<MacroStep>, D::Error> {
+    let steps: Vec<MacroStep> = Vec::deserialize(deserializer)?;
+    if steps.is_empty() {
+        return Err(serde::de::Error::custom("a macro needs at least one step"));
+    }
+    Ok(steps)
+}
+
+/// A macro being played back, started by `source`
+struct Playback {
+    source: ActionSource,
+    /// Remaining steps with taps already split into press, delay and release
+    steps: VecDeque<MacroStep>,
+    /// When the next step is due. Delays add to the scheduled time rather than the time the
+    /// report arrived, so a macro doesn't drift with the report rate.
+    next_at: Duration,
+    cancel_on_release: bool,
+    /// Outputs the macro pressed and didn't release yet, in press order
+    pressed: Vec<DigitalOutput>,
+}
+
+impl Playback {
+    fn release_pressed(&mut self, held: &mut HeldOutputs, events: &mut Vec<OutputEvent>) {
+        for output in self.pressed.drain(..).rev() {
+            held.release(output, events);
+        }
+    }
+}
+
+/// Plays macros back a step at a time as reports come in, so playback never blocks the read
+/// pipeline. Outputs still pressed when a macro ends or is cancelled are released.
+#[derive(Default)]
+pub struct MacroPlayer {
+    playing: Vec<Playback>,
+}
+
+impl MacroPlayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_playing(&self, source: ActionSource) -> bool {
+        self.playing.iter().any(|playback| playback.source == source)
+    }
+
+    /// Starts playing `macro_` on behalf of `source`, its first steps play on the next [`MacroPlayer::advance`]
+    pub fn start(&mut self, source: ActionSource, macro_: &Macro, now: Duration) {
+        let mut steps: VecDeque<MacroStep> = VecDeque::new();
+        for step in &macro_.steps {
+            match *step {
+                MacroStep::Tap { output, hold } => {
+                    steps.extend([MacroStep::Press(output), MacroStep::Delay(hold), MacroStep::Release(output)]);
+                }
+                step => steps.push_back(step),
+            }
+        }
+        self.playing.push(Playback {
+            source,
+            steps,
+            next_at: now,
+            cancel_on_release: macro_.cancel_on_release,
+            pressed: Vec::new(),
+        });
+    }
+
+    /// Plays every step that is due by `now` and drops finished macros
+    pub fn advance(&mut self, now: Duration, held: &mut HeldOutputs, events: &mut Vec<OutputEvent>) {
+        for playback in &mut self.playing {
+            while playback.next_at <= now {
+                let Some(step) = playback.steps.pop_front() else {
+                    break;
+                };
+                match step {
+                    MacroStep::Press(output) => {
+                        held.press(output, events);
+                        playback.pressed.push(output);
+                    }
+                    // Outputs the macro didn't press belong to someone else
+                    MacroStep::Release(output) => {
+                        if let Some(index) = playback.pressed.iter().rposition(|pressed| *pressed == output) {
+                            playback.pressed.remove(index);
+                            held.release(output, events);
+                        }
+                    }
+                    MacroStep::Delay(delay) => playback.next_at += delay,
+                    MacroStep::Tap { .. } => unreachable!("taps are split up by `start`"),
+                }
+            }
+            if playback.steps.is_empty() {
+                playback.release_pressed(held, events);
+            }
+        }
+        self.playing.retain(|playback| !playback.steps.is_empty());
+    }
+
+    /// Stops the macros started by `source`
+    pub fn cancel(&mut self, source: ActionSource, held: &mut HeldOutputs, events: &mut Vec<OutputEvent>) {
+        for playback in self.playing.iter_mut().filter(|playback| playback.source == source) {
+            playback.release_pressed(held, events);
+        }
+        self.playing.retain(|playback| playback.source != source);
+    }
+
+    /// Stops the macros started by `source` that are set to `cancel_on_release`
+    pub fn release(&mut self, source: ActionSource, held: &mut HeldOutputs, events: &mut Vec<OutputEvent>) {
+        if self
+            .playing
+            .iter()
+            .any(|playback| playback.source == source && playback.cancel_on_release)
+        {
+            self.cancel(source, held, events);
+        }
+    }
+
+    /// Stops every macro
+    pub fn cancel_all(&mut self, held: &mut HeldOutputs, events: &mut Vec<OutputEvent>) {
+        for playback in &mut self.playing {
+            playback.release_pressed(held, events);
+        }
+        self.playing.clear();
+    }
+}
+
+/// Turns the output events of a live session into a [`Macro`], keeping the delays between them
+#[derive(Default)]
+pub struct MacroRecorder {
+    steps: Vec<MacroStep>,
+    /// When the last recorded event happened
+    last: Option<Duration>,
+    pressed: Vec<DigitalOutput>,
+}
+
+impl MacroRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Records the press and release events that happened at `now`, axis events are skipped
+    pub fn record(&mut self, events: &[OutputEvent], now: Duration) {
+        for event in events {
+            let step: MacroStep = match *event {
+                OutputEvent::Press(output) => {
+                    self.pressed.push(output);
+                    MacroStep::Press(output)
+                }
+                OutputEvent::Release(output) => {
+                    self.pressed.retain(|pressed| *pressed != output);
+                    MacroStep::Release(output)
+                }
+                _ => continue,
+            };
+
+            if let Some(last) = self.last {
+                // Millisecond precision is all a profile can express
+                let delay: Duration = Duration::from_millis((now - last).as_millis() as u64);
+                if !delay.is_zero() {
+                    self.steps.push(MacroStep::Delay(delay));
+                }
+            }
+            self.last = Some(now);
+            self.steps.push(step);
+        }
+    }
+
+    /// Ends the recording, releasing whatever is still pressed and folding press, delay,
+    /// release runs into taps
+    pub fn finish(mut self) -> Macro {
+        for output in self.pressed.drain(..).rev() {
+            self.steps.push(MacroStep::Release(output));
+        }
+
+        let mut steps: Vec<MacroStep> = Vec::new();
+        let mut index: usize = 0;
+        while index < self.steps.len() {
+            let tap: Option<(MacroStep, usize)> = match self.steps[index..] {
+                [MacroStep::Press(output), MacroStep::Delay(hold), MacroStep::Release(released), ..]
+                    if output == released =>
+                {
+                    Some((MacroStep::Tap { output, hold }, 3))
+                }
+                // Pressed and released within one report, a zero hold would be too short to notice
+                [MacroStep::Press(output), MacroStep::Release(released), ..] if output == released => Some((
+                    MacroStep::Tap {
+                        output,
+                        hold: DEFAULT_TAP_HOLD,
+                    },
+                    2,
+                )),
+                _ => None,
+            };
+            let (step, length): (MacroStep, usize) = tap.unwrap_or((self.steps[index], 1));
+            steps.push(step);
+            index += length;
+        }
+
+        Macro {
+            steps,
+            cancel_on_release: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deck::DeckButton;
+    use crate::output::{GamepadAxis, GamepadButton, Key};
+
+    const A: DigitalOutput = DigitalOutput::Gamepad(GamepadButton::A);
+    const KEY_E: DigitalOutput = DigitalOutput::Key(Key::E);
+    const KEY_R: DigitalOutput = DigitalOutput::Key(Key::R);
+    const SOURCE: ActionSource = ActionSource::Button(DeckButton::A);
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    /// Advances `player` to `now` and returns the events it emitted
+    fn advance(player: &mut MacroPlayer, held: &mut HeldOutputs, now: u64) -> Vec<OutputEvent> {
+        let mut events: Vec<OutputEvent> = Vec::new();
+        player.advance(ms(now), held, &mut events);
+        events
+    }
+
+    /// Holds `key:r` for 100 ms
+    fn hold_r(cancel_on_release: bool) -> Macro {
+        Macro {
+            steps: vec![MacroStep::Press(KEY_R), MacroStep::Delay(ms(100)), MacroStep::Release(KEY_R)],
+            cancel_on_release,
+        }
+    }
+
+    #[test]
+    fn plays_steps_on_time() {
+        let macro_: Macro = Macro {
+            steps: vec![
+                MacroStep::Tap {
+                    output: KEY_R,
+                    hold: ms(30),
+                },
+                MacroStep::Delay(ms(100)),
+                MacroStep::Press(KEY_E),
+            ],
+            cancel_on_release: false,
+        };
+        let mut player: MacroPlayer = MacroPlayer::new();
+        let mut held: HeldOutputs = HeldOutputs::new();
+        player.start(SOURCE, &macro_, ms(10));
+
+        assert_eq!(advance(&mut player, &mut held, 10), [OutputEvent::Press(KEY_R)]);
+        assert_eq!(advance(&mut player, &mut held, 39), []);
+        assert_eq!(advance(&mut player, &mut held, 40), [OutputEvent::Release(KEY_R)]);
+        assert_eq!(advance(&mut player, &mut held, 139), []);
+        // Outputs still pressed at the end are released
+        assert_eq!(
+            advance(&mut player, &mut held, 145),
+            [OutputEvent::Press(KEY_E), OutputEvent::Release(KEY_E)]
+        );
+        assert!(!player.is_playing(SOURCE));
+    }
+
+    #[test]
+    fn release_cancels_only_cancel_on_release_macros() {
+        let mut player: MacroPlayer = MacroPlayer::new();
+        let mut held: HeldOutputs = HeldOutputs::new();
+        let mut events: Vec<OutputEvent> = Vec::new();
+
+        player.start(SOURCE, &hold_r(false), ms(0));
+        advance(&mut player, &mut held, 0);
+        player.release(SOURCE, &mut held, &mut events);
+        assert_eq!(events, []);
+        assert!(player.is_playing(SOURCE));
+        assert_eq!(advance(&mut player, &mut held, 100), [OutputEvent::Release(KEY_R)]);
+
+        player.start(SOURCE, &hold_r(true), ms(200));
+        advance(&mut player, &mut held, 200);
+        player.release(SOURCE, &mut held, &mut events);
+        assert_eq!(events, [OutputEvent::Release(KEY_R)]);
+        assert!(!player.is_playing(SOURCE));
+        assert_eq!(advance(&mut player, &mut held, 300), []);
+    }
+
+    #[test]
+    fn cancel_releases_pressed_outputs() {
+        let mut player: MacroPlayer = MacroPlayer::new();
+        let mut held: HeldOutputs = HeldOutputs::new();
+        let mut events: Vec<OutputEvent> = Vec::new();
+        player.start(SOURCE, &hold_r(false), ms(0));
+        advance(&mut player, &mut held, 0);
+
+        player.cancel(SOURCE, &mut held, &mut events);
+        assert_eq!(events, [OutputEvent::Release(KEY_R)]);
+        assert!(!held.is_held(KEY_R));
+        assert_eq!(advance(&mut player, &mut held, 100), []);
+    }
+
+    #[test]
+    fn recorder_keeps_delays_and_folds_taps() {
+        let mut recorder: MacroRecorder = MacroRecorder::new();
+        recorder.record(&[OutputEvent::Press(KEY_R)], ms(1000));
+        recorder.record(&[OutputEvent::Axis(GamepadAxis::LeftStickX, 0.5)], ms(1010));
+        recorder.record(&[OutputEvent::Release(KEY_R)], ms(1040));
+        recorder.record(&[OutputEvent::Press(A), OutputEvent::Release(A)], ms(1100));
+        recorder.record(&[OutputEvent::Press(KEY_E)], ms(1250));
+
+        assert_eq!(
+            recorder.finish().steps,
+            [
+                MacroStep::Tap {
+                    output: KEY_R,
+                    hold: ms(40),
+                },
+                MacroStep::Delay(ms(60)),
+                // Pressed and released in one report
+                MacroStep::Tap {
+                    output: A,
+                    hold: DEFAULT_TAP_HOLD,
+                },
+                MacroStep::Delay(ms(150)),
+                // Still held when the recording ended
+                MacroStep::Tap {
+                    output: KEY_E,
+                    hold: DEFAULT_TAP_HOLD,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn recorder_keeps_overlapping_presses_apart() {
+        let mut recorder: MacroRecorder = MacroRecorder::new();
+        recorder.record(&[OutputEvent::Press(KEY_R)], ms(0));
+        recorder.record(&[OutputEvent::Press(KEY_E)], ms(10));
+        recorder.record(&[OutputEvent::Release(KEY_R)], ms(20));
+        recorder.record(&[OutputEvent::Release(KEY_E)], ms(30));
+
+        assert_eq!(
+            recorder.finish().steps,
+            [
+                MacroStep::Press(KEY_R),
+                MacroStep::Delay(ms(10)),
+                MacroStep::Press(KEY_E),
+                MacroStep::Delay(ms(10)),
+                MacroStep::Release(KEY_R),
+                MacroStep::Delay(ms(10)),
+                MacroStep::Release(KEY_E),
+            ]
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use crate::profile::{Menu, MenuSelect};
+
+/// Input a radial menu is bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MenuInput {
+    LeftPad,
+    RightPad,
+    LeftStick,
+    RightStick,
+}
+
+impl MenuInput {
+    pub const ALL: [MenuInput; 4] = [
+        MenuInput::LeftPad,
+        MenuInput::RightPad,
+        MenuInput::LeftStick,
+        MenuInput::RightStick,
+    ];
+}
+
+/// State changes of a radial menu, emitted as [`OutputEvent::Menu`](crate::output::OutputEvent::Menu)
+/// for overlays when the menu sets `overlay`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuEvent {
+    /// The finger touched the pad or the stick left its center
+    Opened(MenuInput),
+    /// The finger or stick moved onto another slice
+    Hovered(MenuInput, usize),
+    Selected(MenuInput, usize),
+    Closed(MenuInput),
+}
+
+/// Tracks which slice of a radial menu is hovered and when one gets selected
+#[derive(Debug)]
+pub(crate) struct MenuTracker {
+    input: MenuInput,
+    open: bool,
+    hovered: Option<usize>,
+    clicked: bool,
+}
+
+impl MenuTracker {
+    pub fn new(input: MenuInput) -> Self {
+        Self {
+            input,
+            open: false,
+            hovered: None,
+            clicked: false,
+        }
+    }
+
+    /// `touching` is whether the finger is on the pad or the stick is out of its center,
+    /// `position` is normalized with positive Y up
+    pub fn update(&mut self, menu: &Menu, touching: bool, (x, y): (f32, f32), clicked: bool) -> Vec<MenuEvent> {
+        let mut events: Vec<MenuEvent> = Vec::new();
+        if !touching {
+            // Letting go picks whatever was last hovered
+            if menu.select == MenuSelect::Release
+                && let Some(slice) = self.hovered
+            {
+                events.push(MenuEvent::Selected(self.input, slice));
+            }
+            events.extend(self.close());
+            self.clicked = clicked;
+            return events;
+        }
+
+        if !self.open {
+            self.open = true;
+            events.push(MenuEvent::Opened(self.input));
+        }
+        if x.hypot(y) >= menu.deadzone {
+            let slice: usize = slice_at(menu, x, y);
+            if self.hovered != Some(slice) {
+                self.hovered = Some(slice);
+                events.push(MenuEvent::Hovered(self.input, slice));
+            }
+        }
+        if menu.select == MenuSelect::Click
+            && clicked
+            && !self.clicked
+            && let Some(slice) = self.hovered
+        {
+            events.push(MenuEvent::Selected(self.input, slice));
+        }
+        self.clicked = clicked;
+        events
+    }
+
+    /// Closes the menu without selecting anything, e.g. when it is unbound
+    pub fn close(&mut self) -> Option<MenuEvent> {
+        self.hovered = None;
+        std::mem::replace(&mut self.open, false).then_some(MenuEvent::Closed(self.input))
+    }
+}
+
+/// Slices go clockwise, the first one centered on `start_angle` degrees clockwise from up
+fn slice_at(menu: &Menu, x: f32, y: f32) -> usize {
+    let count: usize = menu.slices.len().max(1);
+    let width: f32 = 360.0 / count as f32;
+    let angle: f32 = (x.atan2(y).to_degrees() - menu.start_angle + width / 2.0).rem_euclid(360.0);
+    (angle / width) as usize % count
+}
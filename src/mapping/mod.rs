@@ -0,0 +1,20 @@
+mod action;
+mod binding;
+mod engine;
+mod held;
+mod layers;
+mod macros;
+mod menu;
+mod pad;
+mod source;
+mod tracker;
+
+pub use self::action::Action;
+pub use self::binding::{Activator, Binding};
+pub(crate) use self::binding::deserialize_map as deserialize_bindings;
+pub use self::engine::MappingEngine;
+pub use self::held::HeldOutputs;
+pub use self::layers::{LayerMode, LayerStack};
+pub use self::macros::{DEFAULT_TAP_HOLD, Macro, MacroPlayer, MacroRecorder, MacroStep};
+pub use self::menu::{MenuEvent, MenuInput};
+pub use self::source::ActionSource;
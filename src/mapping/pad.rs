@@ -0,0 +1,332 @@
+use crate::deck::Trackpad;
+use crate::mapping::Action;
+use crate::processing::{TrackpadMouse, rotate_clockwise};
+use crate::profile::{
+    DpadSettings, GridSettings, JoystickSettings, PadActivation, ScrollWheelSettings, Side, TrackpadMode,
+    TrackpadSettings,
+};
+use std::time::Duration;
+
+/// What a trackpad asks of the engine for one report
+#[derive(Debug, Default)]
+pub(crate) struct PadOutput {
+    /// Actions that should be held right now, the engine presses and releases the difference
+    pub actions: Vec<Action>,
+    /// Pointer motion in pixels
+    pub motion: (i32, i32),
+    /// Scroll wheel notches
+    pub scroll: (i32, i32),
+    /// Position of the emulated stick the pad drives, overriding the physical stick
+    pub stick: Option<(Side, (f32, f32))>,
+    /// Haptic ticks to play on this pad
+    pub ticks: u32,
+}
+
+/// Per-mode state of one trackpad. Switching modes starts over from a clean state.
+#[derive(Debug, Default)]
+pub(crate) struct PadMapper {
+    mode: TrackpadMode,
+    mouse: TrackpadMouse,
+    /// Finger angle on the wheel ring of the previous report, in degrees
+    wheel_angle: Option<f32>,
+    /// Clockwise travel since the last notch, in degrees
+    wheel_travel: f32,
+    /// Held d-pad directions as `[up, down, left, right]`
+    dpad: [bool; 4],
+    /// Grid cell under the finger
+    hovered_cell: Option<usize>,
+    /// Grid cell chosen when the grid was activated, kept until it is let go
+    active_cell: Option<usize>,
+    on_edge: bool,
+}
+
+impl PadMapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, settings: &TrackpadSettings, pad: &Trackpad, clicked: bool, now: Duration) -> PadOutput {
+        if settings.mode != self.mode {
+            *self = Self {
+                mode: settings.mode,
+                ..Self::default()
+            };
+        }
+
+        let position: Option<(f32, f32)> = pad
+            .touched
+            .then(|| rotate_clockwise(pad.normalized(), settings.rotation));
+        let mut output: PadOutput = PadOutput::default();
+        match settings.mode {
+            // Menus are run by the engine, which knows the profile's menus
+            TrackpadMode::None | TrackpadMode::RadialMenu => {}
+            TrackpadMode::Mouse => {
+                output.motion = self.mouse.update(settings, pad, now);
+                if let Some(click) = settings.click.filter(|_| clicked) {
+                    output.actions.push(Action::Output(click));
+                }
+            }
+            TrackpadMode::ScrollWheel => self.scroll_wheel(&settings.scroll_wheel, position, &mut output),
+            TrackpadMode::Dpad => self.dpad(&settings.dpad, position, clicked, &mut output),
+            TrackpadMode::Joystick => self.joystick(&settings.joystick, position, &mut output),
+            TrackpadMode::Grid => self.grid(&settings.grid, position, clicked, &mut output),
+        }
+        output
+    }
+
+    fn scroll_wheel(&mut self, settings: &ScrollWheelSettings, position: Option<(f32, f32)>, output: &mut PadOutput) {
+        let Some((x, y)) = position.filter(|(x, y)| x.hypot(*y) >= settings.inner_radius) else {
+            self.wheel_angle = None;
+            self.wheel_travel = 0.0;
+            return;
+        };
+
+        let angle: f32 = y.atan2(x).to_degrees();
+        if let Some(last) = self.wheel_angle {
+            // Shortest way around, angles grow counter-clockwise
+            let delta: f32 = (angle - last + 540.0).rem_euclid(360.0) - 180.0;
+            self.wheel_travel -= delta;
+        }
+        self.wheel_angle = Some(angle);
+
+        let notches: f32 = (self.wheel_travel / settings.degrees_per_notch).trunc();
+        if notches == 0.0 {
+            return;
+        }
+        self.wheel_travel -= notches * settings.degrees_per_notch;
+        // Clockwise scrolls down or right
+        let notches: i32 = if settings.invert { -notches as i32 } else { notches as i32 };
+        output.scroll = if settings.horizontal { (notches, 0) } else { (0, -notches) };
+        output.ticks = notches.unsigned_abs();
+    }
+
+    fn dpad(&mut self, settings: &DpadSettings, position: Option<(f32, f32)>, clicked: bool, output: &mut PadOutput) {
+        let mut directions: [bool; 4] = [false; 4];
+        if let Some((x, y)) = position
+            && is_active(settings.activation, clicked)
+            && x.hypot(y) >= settings.deadzone
+        {
+            let angle: f32 = y.atan2(x).to_degrees();
+            let half_width: f32 = match settings.ways {
+                8 => 67.5,
+                _ => 45.0,
+            };
+            let half_width: f32 = (half_width * (1.0 + settings.overlap)).min(89.0);
+            for (direction, center) in directions.iter_mut().zip([90.0, -90.0, 180.0, 0.0]) {
+                let distance: f32 = ((angle - center + 540.0).rem_euclid(360.0) - 180.0).abs();
+                *direction = distance < half_width;
+            }
+        }
+
+        let entered: usize = directions
+            .iter()
+            .zip(self.dpad)
+            .filter(|(now, before)| **now && !*before)
+            .count();
+        output.ticks = entered as u32;
+        self.dpad = directions;
+
+        for (held, direction) in directions
+            .into_iter()
+            .zip([settings.up, settings.down, settings.left, settings.right])
+        {
+            if held {
+                output.actions.push(Action::Output(direction));
+            }
+        }
+    }
+
+    fn joystick(&mut self, settings: &JoystickSettings, position: Option<(f32, f32)>, output: &mut PadOutput) {
+        let Some((x, y)) = position else {
+            self.on_edge = false;
+            return;
+        };
+
+        let distance: f32 = x.hypot(y).min(1.0);
+        let deflection: f32 = if distance <= settings.deadzone || settings.deadzone >= 1.0 {
+            0.0
+        } else {
+            ((distance - settings.deadzone) / (1.0 - settings.deadzone)).min(1.0)
+        };
+        let factor: f32 = if distance > 0.0 { deflection / distance } else { 0.0 };
+        output.stick = Some((settings.stick, ((x * factor).clamp(-1.0, 1.0), (y * factor).clamp(-1.0, 1.0))));
+
+        let on_edge: bool = distance >= settings.edge_radius;
+        if on_edge && !self.on_edge {
+            output.ticks = 1;
+        }
+        self.on_edge = on_edge;
+        if let Some(edge) = settings.edge.as_ref().filter(|_| on_edge) {
+            output.actions.push(edge.clone());
+        }
+    }
+
+    fn grid(&mut self, settings: &GridSettings, position: Option<(f32, f32)>, clicked: bool, output: &mut PadOutput) {
+        let hovered: Option<usize> = position.and_then(|(x, y)| {
+            if settings.rows == 0 || settings.columns == 0 {
+                return None;
+            }
+            let column: usize = (((x + 1.0) / 2.0 * settings.columns as f32) as usize).min(settings.columns as usize - 1);
+            // Rows count from the top, positive Y is up
+            let row: usize = (((1.0 - y) / 2.0 * settings.rows as f32) as usize).min(settings.rows as usize - 1);
+            Some(row * settings.columns as usize + column)
+        });
+        if hovered.is_some() && hovered != self.hovered_cell {
+            output.ticks = 1;
+        }
+        self.hovered_cell = hovered;
+
+        if position.is_some() && is_active(settings.activation, clicked) {
+            self.active_cell = self.active_cell.or(hovered);
+        } else {
+            self.active_cell = None;
+        }
+        if let Some(action) = self.active_cell.and_then(|cell| settings.actions.get(cell)) {
+            output.actions.push(action.clone());
+        }
+    }
+}
+
+fn is_active(activation: PadActivation, clicked: bool) -> bool {
+    match activation {
+        PadActivation::Click => clicked,
+        PadActivation::Touch => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{DigitalOutput, GamepadButton, Key};
+
+    const KEYS: [Key; 4] = [Key::Num1, Key::Num2, Key::Num3, Key::Num4];
+
+    fn touch(x: f32, y: f32) -> Trackpad {
+        Trackpad {
+            x: (x * i16::MAX as f32) as i16,
+            y: (y * i16::MAX as f32) as i16,
+            pressure: 0,
+            touched: true,
+        }
+    }
+
+    /// A touch on the ring at `degrees` counter-clockwise from the right
+    fn ring(degrees: f32) -> Trackpad {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        touch(0.8 * cos, 0.8 * sin)
+    }
+
+    fn gamepad(button: GamepadButton) -> Action {
+        Action::Output(DigitalOutput::Gamepad(button))
+    }
+
+    fn key(index: usize) -> Action {
+        Action::Output(DigitalOutput::Key(KEYS[index]))
+    }
+
+    #[test]
+    fn scroll_wheel_scrolls_a_notch_per_step() {
+        let settings: TrackpadSettings = TrackpadSettings {
+            mode: TrackpadMode::ScrollWheel,
+            ..TrackpadSettings::default()
+        };
+        let mut pad: PadMapper = PadMapper::new();
+        let mut update = |trackpad: Trackpad| pad.update(&settings, &trackpad, false, Duration::ZERO);
+        assert_eq!(update(ring(90.0)).scroll, (0, 0));
+
+        // Clockwise scrolls down, the travel left over counts toward the next notch
+        let output: PadOutput = update(ring(55.0));
+        assert_eq!((output.scroll, output.ticks), ((0, -1), 1));
+        assert_eq!(update(ring(30.0)).scroll, (0, -1));
+        assert_eq!(update(ring(80.0)).scroll, (0, 1));
+
+        // Lifting off or touching the middle forgets the travel
+        assert_eq!(update(touch(0.1, 0.0)).scroll, (0, 0));
+        assert_eq!(update(ring(80.0)).scroll, (0, 0));
+        assert_eq!(update(ring(60.0)).scroll, (0, 0));
+    }
+
+    #[test]
+    fn dpad_holds_the_touched_directions() {
+        let mut settings: TrackpadSettings = TrackpadSettings {
+            mode: TrackpadMode::Dpad,
+            dpad: DpadSettings {
+                activation: PadActivation::Touch,
+                ..DpadSettings::default()
+            },
+            ..TrackpadSettings::default()
+        };
+        let mut pad: PadMapper = PadMapper::new();
+        let output: PadOutput = pad.update(&settings, &touch(0.8, 0.1), false, Duration::ZERO);
+        assert_eq!((output.actions, output.ticks), (vec![gamepad(GamepadButton::DpadRight)], 1));
+        let output: PadOutput = pad.update(&settings, &touch(0.8, -0.1), false, Duration::ZERO);
+        assert_eq!((output.actions, output.ticks), (vec![gamepad(GamepadButton::DpadRight)], 0));
+        assert_eq!(pad.update(&settings, &touch(0.1, 0.1), false, Duration::ZERO).actions, []);
+
+        // In 8-way mode the diagonals hold both neighbours
+        settings.dpad.ways = 8;
+        let output: PadOutput = pad.update(&settings, &touch(-0.6, 0.6), false, Duration::ZERO);
+        assert_eq!(
+            output.actions,
+            [gamepad(GamepadButton::DpadUp), gamepad(GamepadButton::DpadLeft)]
+        );
+        assert_eq!(output.ticks, 2);
+
+        settings.dpad.activation = PadActivation::Click;
+        assert_eq!(pad.update(&settings, &touch(0.8, 0.0), false, Duration::ZERO).actions, []);
+        let output: PadOutput = pad.update(&settings, &touch(0.8, 0.0), true, Duration::ZERO);
+        assert_eq!(output.actions, [gamepad(GamepadButton::DpadRight)]);
+    }
+
+    #[test]
+    fn joystick_drives_a_stick_with_an_edge() {
+        let settings: TrackpadSettings = TrackpadSettings {
+            mode: TrackpadMode::Joystick,
+            joystick: JoystickSettings {
+                edge: Some(key(0)),
+                ..JoystickSettings::default()
+            },
+            ..TrackpadSettings::default()
+        };
+        let mut pad: PadMapper = PadMapper::new();
+        let output: PadOutput = pad.update(&settings, &touch(0.0, -0.55), false, Duration::ZERO);
+        let (side, (x, y)) = output.stick.unwrap();
+        assert_eq!(side, Side::Right);
+        assert!(x.abs() < 1e-6 && (y + 0.5).abs() < 1e-3, "({x}, {y})");
+        assert_eq!((output.actions, output.ticks), (vec![], 0));
+
+        let output: PadOutput = pad.update(&settings, &touch(0.0, 1.0), false, Duration::ZERO);
+        assert_eq!(output.stick, Some((Side::Right, (0.0, 1.0))));
+        assert_eq!((output.actions, output.ticks), (vec![key(0)], 1));
+        assert_eq!(pad.update(&settings, &touch(0.0, 1.0), false, Duration::ZERO).ticks, 0);
+
+        // Letting go hands the stick back
+        assert_eq!(pad.update(&settings, &Trackpad::default(), false, Duration::ZERO).stick, None);
+    }
+
+    #[test]
+    fn grid_keeps_the_activated_cell_until_let_go() {
+        let mut settings: TrackpadSettings = TrackpadSettings {
+            mode: TrackpadMode::Grid,
+            grid: GridSettings {
+                activation: PadActivation::Touch,
+                actions: (0..4).map(key).collect(),
+                ..GridSettings::default()
+            },
+            ..TrackpadSettings::default()
+        };
+        let mut pad: PadMapper = PadMapper::new();
+        let output: PadOutput = pad.update(&settings, &touch(0.5, 0.5), false, Duration::ZERO);
+        assert_eq!((output.actions, output.ticks), (vec![key(1)], 1));
+        // Sliding onto the bottom left cell ticks but keeps the first cell's action
+        let output: PadOutput = pad.update(&settings, &touch(-0.5, -0.5), false, Duration::ZERO);
+        assert_eq!((output.actions, output.ticks), (vec![key(1)], 1));
+        assert_eq!(pad.update(&settings, &Trackpad::default(), false, Duration::ZERO).actions, []);
+        assert_eq!(pad.update(&settings, &touch(-0.5, -0.5), false, Duration::ZERO).actions, [key(2)]);
+
+        // A grid without cells does nothing rather than panicking
+        pad.update(&settings, &Trackpad::default(), false, Duration::ZERO);
+        settings.grid.rows = 0;
+        assert_eq!(pad.update(&settings, &touch(0.5, 0.5), false, Duration::ZERO).actions, []);
+    }
+}
@@ -0,0 +1,25 @@
+use crate::deck::DeckButton;
+use crate::mapping::MenuInput;
+use crate::profile::Side;
+
+/// Input that pressed an action. Layers held and macros started by an action are released
+/// through the source that pressed it, so every input that presses actions on its own has one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ActionSource {
+    /// A binding of a Deck button
+    Button(DeckButton),
+    /// The mode of a trackpad, e.g. a region in `dpad` mode
+    Pad(Side),
+    /// `soft_pull` of a trigger, independent of the L2 and R2 bindings
+    SoftPull(Side),
+    /// `full_pull` of a trigger
+    FullPull(Side),
+    /// A slice selected in a radial menu
+    Menu(MenuInput),
+}
+
+impl From<DeckButton> for ActionSource {
+    fn from(button: DeckButton) -> Self {
+        ActionSource::Button(button)
+    }
+}
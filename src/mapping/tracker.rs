@@ -0,0 +1,425 @@
+use crate::deck::DeckButton;
+use crate::mapping::binding::{MAX_TURBO_RATE, MIN_TURBO_RATE};
+use crate::mapping::{Activator, Binding};
+use std::time::Duration;
+
+/// What the engine has to do with the action of the binding at an index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Press(usize),
+    Release(usize),
+    /// Press now and release shortly after
+    Tap(usize),
+}
+
+#[derive(Debug)]
+struct Turbo {
+    index: usize,
+    /// Time between two toggles, half of the tap period
+    interval: Duration,
+    next_toggle: Duration,
+    on: bool,
+}
+
+/// Runs the activators of a single Deck button from its first press until it settles.
+///
+/// The bindings are captured on the first press, so layer or profile changes never affect a
+/// press sequence that is already underway.
+///
+/// A chord takes over both of its buttons: it presses once both are held, in either order, and
+/// releases as soon as either one is let go. Whatever either button held on its own is released
+/// when the chord presses, and neither button triggers anything else until it is released.
+#[derive(Debug)]
+pub struct ButtonTracker {
+    bindings: Vec<Binding>,
+    /// Buttons with a chord binding that uses this button as `with`
+    partners: Vec<DeckButton>,
+    pressed: bool,
+    pressed_at: Duration,
+    /// Start of the press sequence the double press window is measured from
+    first_press: Duration,
+    /// Presses in the current sequence, `0` once the sequence is over
+    presses: u8,
+    /// A chord, long press or double press took over the current sequence
+    resolved: bool,
+    holding: Vec<usize>,
+    turbos: Vec<Turbo>,
+    /// Pressed chord bindings with their `with` button
+    chords: Vec<(usize, DeckButton)>,
+}
+
+impl ButtonTracker {
+    pub fn new(bindings: Vec<Binding>, partners: Vec<DeckButton>) -> Self {
+        Self {
+            bindings,
+            partners,
+            pressed: false,
+            pressed_at: Duration::ZERO,
+            first_press: Duration::ZERO,
+            presses: 0,
+            resolved: false,
+            holding: Vec::new(),
+            turbos: Vec::new(),
+            chords: Vec::new(),
+        }
+    }
+
+    pub fn binding(&self, index: usize) -> &Binding {
+        &self.bindings[index]
+    }
+
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    /// Nothing is held and no activator is waiting for a timeout
+    pub fn is_idle(&self) -> bool {
+        !self.pressed
+            && self.presses == 0
+            && self.holding.is_empty()
+            && self.turbos.is_empty()
+            && self.chords.is_empty()
+    }
+
+    /// Advances the activators to `now`. `is_held` reports the state of other buttons for chords.
+    pub fn update(&mut self, pressed: bool, now: Duration, is_held: impl Fn(DeckButton) -> bool) -> Vec<Command> {
+        let mut commands: Vec<Command> = Vec::new();
+        match (pressed, self.pressed) {
+            (true, false) => self.on_press(now, is_held, &mut commands),
+            (true, true) => self.on_hold(now, is_held, &mut commands),
+            (false, true) => self.on_release(&mut commands),
+            (false, false) => self.on_idle(now, &mut commands),
+        }
+        commands
+    }
+
+    fn on_press(&mut self, now: Duration, is_held: impl Fn(DeckButton) -> bool, commands: &mut Vec<Command>) {
+        self.pressed = true;
+        self.pressed_at = now;
+        if self.presses == 0 {
+            self.presses = 1;
+            self.first_press = now;
+            self.resolved = false;
+        } else {
+            self.presses = self.presses.saturating_add(1);
+        }
+
+        self.update_chords(&is_held, commands);
+        if self.resolved {
+            return;
+        }
+
+        let deferred: bool = self.defers_regular();
+        for (index, binding) in self.bindings.iter().enumerate() {
+            match binding.activator {
+                Activator::Regular if !deferred => {
+                    commands.push(Command::Press(index));
+                    self.holding.push(index);
+                }
+                Activator::DoublePress { window } if self.presses >= 2 && now - self.first_press <= window => {
+                    commands.push(Command::Press(index));
+                    self.holding.push(index);
+                    self.resolved = true;
+                }
+                Activator::Turbo { rate } => {
+                    let interval: Duration = turbo_interval(rate);
+                    commands.push(Command::Press(index));
+                    self.turbos.push(Turbo {
+                        index,
+                        interval,
+                        next_toggle: now + interval,
+                        on: true,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn on_hold(&mut self, now: Duration, is_held: impl Fn(DeckButton) -> bool, commands: &mut Vec<Command>) {
+        self.update_chords(&is_held, commands);
+        if !self.resolved {
+            for (index, binding) in self.bindings.iter().enumerate() {
+                if let Activator::LongPress { threshold } = binding.activator
+                    && now - self.pressed_at >= threshold
+                {
+                    commands.push(Command::Press(index));
+                    self.holding.push(index);
+                    self.resolved = true;
+                }
+            }
+        }
+
+        for turbo in &mut self.turbos {
+            if now >= turbo.next_toggle {
+                commands.push(if turbo.on { Command::Release(turbo.index) } else { Command::Press(turbo.index) });
+                turbo.on = !turbo.on;
+                // A late report toggles once rather than catching up on every toggle it missed
+                let missed: u32 = ((now - turbo.next_toggle).as_nanos() / turbo.interval.as_nanos()) as u32;
+                turbo.next_toggle += turbo.interval * (missed + 1);
+            }
+        }
+    }
+
+    fn on_release(&mut self, commands: &mut Vec<Command>) {
+        self.pressed = false;
+        commands.extend(self.chords.drain(..).map(|(index, _)| Command::Release(index)));
+        commands.extend(self.holding.drain(..).map(Command::Release));
+        commands.extend(self.turbos.drain(..).filter(|turbo| turbo.on).map(|turbo| Command::Release(turbo.index)));
+
+        if self.resolved {
+            self.presses = 0;
+            return;
+        }
+        for (index, binding) in self.bindings.iter().enumerate() {
+            if binding.activator == Activator::Release {
+                commands.push(Command::Tap(index));
+            }
+        }
+        // A single press with a double press binding has to wait for the window to run out
+        if self.presses == 1 && self.double_press_window().is_some() {
+            return;
+        }
+        if self.defers_regular() {
+            self.tap_regular(commands);
+        }
+        self.presses = 0;
+    }
+
+    fn on_idle(&mut self, now: Duration, commands: &mut Vec<Command>) {
+        if let Some(window) = self.double_press_window()
+            && self.presses > 0
+            && now - self.first_press > window
+        {
+            self.tap_regular(commands);
+            self.presses = 0;
+        }
+    }
+
+    /// Releases chords whose `with` button was let go and presses the ones whose `with` button is
+    /// held. A pressed chord of this button or of a partner takes over the press sequence.
+    fn update_chords(&mut self, is_held: &impl Fn(DeckButton) -> bool, commands: &mut Vec<Command>) {
+        self.chords.retain(|(index, with)| {
+            let held: bool = is_held(*with);
+            if !held {
+                commands.push(Command::Release(*index));
+            }
+            held
+        });
+
+        let pressed: Vec<(usize, DeckButton)> = self
+            .bindings
+            .iter()
+            .enumerate()
+            .filter_map(|(index, binding)| match binding.activator {
+                Activator::Chord { with } if is_held(with) => Some((index, with)),
+                _ => None,
+            })
+            .collect();
+        if pressed.is_empty() && !self.partners.iter().any(|partner| is_held(*partner)) {
+            return;
+        }
+        // The chord takes over, outputs held by the button alone are released before it presses
+        commands.extend(self.holding.drain(..).map(Command::Release));
+        commands.extend(self.turbos.drain(..).filter(|turbo| turbo.on).map(|turbo| Command::Release(turbo.index)));
+        self.resolved = true;
+        for (index, with) in pressed {
+            if !self.chords.iter().any(|(chord, _)| *chord == index) {
+                commands.push(Command::Press(index));
+                self.chords.push((index, with));
+            }
+        }
+    }
+
+    /// Regular bindings wait to see whether a long or double press happens
+    fn defers_regular(&self) -> bool {
+        self.bindings.iter().any(|binding| {
+            matches!(
+                binding.activator,
+                Activator::LongPress { .. } | Activator::DoublePress { .. }
+            )
+        })
+    }
+
+    fn double_press_window(&self) -> Option<Duration> {
+        self.bindings.iter().find_map(|binding| match binding.activator {
+            Activator::DoublePress { window } => Some(window),
+            _ => None,
+        })
+    }
+
+    fn tap_regular(&self, commands: &mut Vec<Command>) {
+        for (index, binding) in self.bindings.iter().enumerate() {
+            if binding.activator == Activator::Regular {
+                commands.push(Command::Tap(index));
+            }
+        }
+    }
+}
+
+/// Time between two toggles of a turbo tapping `rate` times per second. Profiles only allow
+/// sane rates, activators built in code are clamped to them so the toggle loop always advances.
+fn turbo_interval(rate: f32) -> Duration {
+    let rate: f32 = if rate.is_nan() { MIN_TURBO_RATE } else { rate.clamp(MIN_TURBO_RATE, MAX_TURBO_RATE) };
+    Duration::from_secs_f64(0.5 / rate as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::DigitalOutput;
+
+    fn binding(activator: Activator) -> Binding {
+        Binding {
+            activator,
+            action: "gamepad:a".parse::<DigitalOutput>().unwrap().into(),
+        }
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn tracker(activators: &[Activator]) -> ButtonTracker {
+        ButtonTracker::new(activators.iter().copied().map(binding).collect(), Vec::new())
+    }
+
+    const LONG: Activator = Activator::LongPress { threshold: Duration::from_millis(500) };
+    const DOUBLE: Activator = Activator::DoublePress { window: Duration::from_millis(300) };
+    const CHORD: Activator = Activator::Chord { with: DeckButton::L4 };
+
+    #[test]
+    fn regular_holds() {
+        let mut tracker: ButtonTracker = tracker(&[Activator::Regular]);
+        assert_eq!(tracker.update(true, ms(0), |_| false), [Command::Press(0)]);
+        assert_eq!(tracker.update(true, ms(1000), |_| false), []);
+        assert_eq!(tracker.update(false, ms(1004), |_| false), [Command::Release(0)]);
+        assert!(tracker.is_idle());
+    }
+
+    #[test]
+    fn long_press_fires_after_threshold() {
+        let mut tracker: ButtonTracker = tracker(&[Activator::Regular, LONG]);
+        assert_eq!(tracker.update(true, ms(0), |_| false), []);
+        assert_eq!(tracker.update(true, ms(496), |_| false), []);
+        assert_eq!(tracker.update(true, ms(500), |_| false), [Command::Press(1)]);
+        assert_eq!(tracker.update(false, ms(700), |_| false), [Command::Release(1)]);
+        assert!(tracker.is_idle());
+    }
+
+    #[test]
+    fn short_press_taps_deferred_regular() {
+        let mut tracker: ButtonTracker = tracker(&[Activator::Regular, LONG]);
+        tracker.update(true, ms(0), |_| false);
+        assert_eq!(tracker.update(false, ms(100), |_| false), [Command::Tap(0)]);
+        assert!(tracker.is_idle());
+    }
+
+    #[test]
+    fn double_press_within_window() {
+        let mut tracker: ButtonTracker = tracker(&[Activator::Regular, DOUBLE]);
+        assert_eq!(tracker.update(true, ms(0), |_| false), []);
+        assert_eq!(tracker.update(false, ms(80), |_| false), []);
+        assert_eq!(tracker.update(false, ms(120), |_| false), []);
+        assert_eq!(tracker.update(true, ms(200), |_| false), [Command::Press(1)]);
+        assert_eq!(tracker.update(false, ms(260), |_| false), [Command::Release(1)]);
+        assert!(tracker.is_idle());
+    }
+
+    #[test]
+    fn single_press_taps_once_window_ran_out() {
+        let mut tracker: ButtonTracker = tracker(&[Activator::Regular, DOUBLE]);
+        tracker.update(true, ms(0), |_| false);
+        assert_eq!(tracker.update(false, ms(80), |_| false), []);
+        assert_eq!(tracker.update(false, ms(300), |_| false), []);
+        assert_eq!(tracker.update(false, ms(304), |_| false), [Command::Tap(0)]);
+        assert!(tracker.is_idle());
+    }
+
+    #[test]
+    fn release_taps_on_release() {
+        let mut tracker: ButtonTracker = tracker(&[Activator::Release]);
+        assert_eq!(tracker.update(true, ms(0), |_| false), []);
+        assert_eq!(tracker.update(true, ms(200), |_| false), []);
+        assert_eq!(tracker.update(false, ms(204), |_| false), [Command::Tap(0)]);
+        assert!(tracker.is_idle());
+    }
+
+    #[test]
+    fn chord_with_held_first() {
+        let mut tracker: ButtonTracker = tracker(&[Activator::Regular, CHORD]);
+        assert_eq!(tracker.update(true, ms(0), |button| button == DeckButton::L4), [Command::Press(1)]);
+        assert_eq!(tracker.update(true, ms(100), |button| button == DeckButton::L4), []);
+        assert_eq!(tracker.update(false, ms(200), |button| button == DeckButton::L4), [Command::Release(1)]);
+        assert!(tracker.is_idle());
+    }
+
+    #[test]
+    fn chord_with_pressed_second() {
+        let mut tracker: ButtonTracker = tracker(&[Activator::Regular, CHORD]);
+        assert_eq!(tracker.update(true, ms(0), |_| false), [Command::Press(0)]);
+        // The regular binding gives way to the chord
+        assert_eq!(
+            tracker.update(true, ms(100), |button| button == DeckButton::L4),
+            [Command::Release(0), Command::Press(1)]
+        );
+        assert_eq!(tracker.update(false, ms(200), |button| button == DeckButton::L4), [Command::Release(1)]);
+        assert!(tracker.is_idle());
+    }
+
+    #[test]
+    fn chord_releases_with_its_other_button() {
+        let mut tracker: ButtonTracker = tracker(&[Activator::Regular, CHORD]);
+        tracker.update(true, ms(0), |button| button == DeckButton::L4);
+        assert_eq!(tracker.update(true, ms(100), |_| false), [Command::Release(1)]);
+        // Still suppressed until this button is released too
+        assert_eq!(tracker.update(true, ms(200), |_| false), []);
+        assert_eq!(tracker.update(false, ms(300), |_| false), []);
+        assert!(tracker.is_idle());
+    }
+
+    #[test]
+    fn chord_partner_suppresses_bindings() {
+        let partners: Vec<DeckButton> = vec![DeckButton::A];
+        let mut tracker: ButtonTracker = ButtonTracker::new(vec![binding(Activator::Regular)], partners.clone());
+        assert_eq!(tracker.update(true, ms(0), |button| button == DeckButton::A), []);
+        assert_eq!(tracker.update(false, ms(100), |button| button == DeckButton::A), []);
+        assert!(tracker.is_idle());
+
+        let mut tracker: ButtonTracker = ButtonTracker::new(vec![binding(Activator::Regular)], partners);
+        assert_eq!(tracker.update(true, ms(0), |_| false), [Command::Press(0)]);
+        assert_eq!(tracker.update(true, ms(100), |button| button == DeckButton::A), [Command::Release(0)]);
+        assert_eq!(tracker.update(false, ms(200), |_| false), []);
+        assert!(tracker.is_idle());
+    }
+
+    #[test]
+    fn turbo_taps_at_rate() {
+        let mut tracker: ButtonTracker = tracker(&[Activator::Turbo { rate: 10.0 }]);
+        assert_eq!(tracker.update(true, ms(0), |_| false), [Command::Press(0)]);
+        assert_eq!(tracker.update(true, ms(40), |_| false), []);
+        assert_eq!(tracker.update(true, ms(50), |_| false), [Command::Release(0)]);
+        assert_eq!(tracker.update(true, ms(100), |_| false), [Command::Press(0)]);
+        assert_eq!(tracker.update(false, ms(120), |_| false), [Command::Release(0)]);
+        assert!(tracker.is_idle());
+    }
+
+    #[test]
+    fn turbo_huge_rate_toggles_once_per_report() {
+        let mut tracker: ButtonTracker = tracker(&[Activator::Turbo { rate: 1e10 }]);
+        tracker.update(true, ms(0), |_| false);
+        // Clamped to the maximum rate, a toggle every 5 ms. A report a second late toggles once
+        // and picks the schedule back up.
+        assert_eq!(tracker.update(true, ms(1000), |_| false), [Command::Release(0)]);
+        assert_eq!(tracker.update(true, ms(1004), |_| false), []);
+        assert_eq!(tracker.update(true, ms(1005), |_| false), [Command::Press(0)]);
+    }
+
+    #[test]
+    fn turbo_tiny_rate_does_not_panic() {
+        let mut tracker: ButtonTracker = tracker(&[Activator::Turbo { rate: 1e-30 }]);
+        assert_eq!(tracker.update(true, ms(0), |_| false), [Command::Press(0)]);
+        assert_eq!(tracker.update(true, ms(1000), |_| false), []);
+        assert_eq!(tracker.update(false, ms(1100), |_| false), [Command::Release(0)]);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::deck::HapticPulse;
+use crate::mapping::MenuEvent;
+use crate::output::{GamepadAxis, GamepadButton};
+use serde::de::{self, DeserializeOwned, IntoDeserializer};
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Keyboard keys that can be emitted, named after their US layout legend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Key {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Num0,
+    Num1,
+    Num2,
+    Num3,
+    Num4,
+    Num5,
+    Num6,
+    Num7,
+    Num8,
+    Num9,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    Escape,
+    Enter,
+    Tab,
+    Space,
+    Backspace,
+    Delete,
+    Insert,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Up,
+    Down,
+    Left,
+    Right,
+    Minus,
+    Equal,
+    LeftBracket,
+    RightBracket,
+    Semicolon,
+    Apostrophe,
+    Grave,
+    Backslash,
+    Comma,
+    Period,
+    Slash,
+    CapsLock,
+    LeftShift,
+    RightShift,
+    LeftCtrl,
+    RightCtrl,
+    LeftAlt,
+    RightAlt,
+    LeftMeta,
+    RightMeta,
+    VolumeUp,
+    VolumeDown,
+    Mute,
+    PlayPause,
+    PrintScreen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+}
+
+/// Anything that can be pressed and released on the output side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DigitalOutput {
+    Gamepad(GamepadButton),
+    Key(Key),
+    Mouse(MouseButton),
+}
+
+impl FromStr for DigitalOutput {
+    type Err = String;
+
+    /// Parses the `<gamepad|key|mouse>:<name>` notation used by profiles, e.g. `key:left_ctrl`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn parse_name<T: DeserializeOwned>(name: &str) -> Result<T, String> {
+            T::deserialize(name.into_deserializer()).map_err(|err: de::value::Error| err.to_string())
+        }
+
+        match s.split_once(':') {
+            Some(("gamepad", name)) => parse_name(name).map(DigitalOutput::Gamepad),
+            Some(("key", name)) => parse_name(name).map(DigitalOutput::Key),
+            Some(("mouse", name)) => parse_name(name).map(DigitalOutput::Mouse),
+            Some((kind, _)) => Err(format!("unknown output kind `{kind}`, expected `gamepad`, `key` or `mouse`")),
+            None => Err(format!("expected `<gamepad|key|mouse>:<name>`, found `{s}`")),
+        }
+    }
+}
+
+impl fmt::Display for DigitalOutput {
+    /// Writes the notation [`DigitalOutput::from_str`] parses
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DigitalOutput::Gamepad(button) => write!(f, "gamepad:{}", snake_case(button)),
+            DigitalOutput::Key(key) => write!(f, "key:{}", snake_case(key)),
+            DigitalOutput::Mouse(button) => write!(f, "mouse:{}", snake_case(button)),
+        }
+    }
+}
+
+/// The name serde's `rename_all = "snake_case"` gives a unit variant, e.g. `LeftCtrl` -> `left_ctrl`
+pub(crate) fn snake_case(variant: &impl fmt::Debug) -> String {
+    let mut name: String = String::new();
+    for (i, c) in format!("{variant:?}").chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+impl<'de> Deserialize<'de> for DigitalOutput {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let notation: String = String::deserialize(deserializer)?;
+        notation.parse().map_err(de::Error::custom)
+    }
+}
+
+/// A single change to the emulated devices, produced by the mapping engine
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputEvent {
+    Press(DigitalOutput),
+    Release(DigitalOutput),
+    /// Normalized value, see [`GamepadAxis::is_trigger`]
+    Axis(GamepadAxis, f32),
+    /// Relative pointer motion in pixels, positive Y is down
+    MouseMove { x: i32, y: i32 },
+    /// Scroll wheel notches, positive Y scrolls up and positive X scrolls right
+    Scroll { x: i32, y: i32 },
+    /// Played by the Deck's own trackpad actuators rather than an emulated device
+    Haptic(HapticPulse),
+    /// Radial menu state for overlays, nothing is emulated for it
+    Menu(MenuEvent),
+}
@@ -0,0 +1,162 @@
+use serde::Deserialize;
+use std::error::Error;
+
+/// Buttons of the emulated gamepad, laid out like an Xbox 360 controller since that is what
+/// every output backend (uinput, uhid, ViGEmBus) understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    LeftBumper,
+    RightBumper,
+    LeftStick,
+    RightStick,
+    Back,
+    Start,
+    Guide,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+}
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 15] = [
+        GamepadButton::A,
+        GamepadButton::B,
+        GamepadButton::X,
+        GamepadButton::Y,
+        GamepadButton::LeftBumper,
+        GamepadButton::RightBumper,
+        GamepadButton::LeftStick,
+        GamepadButton::RightStick,
+        GamepadButton::Back,
+        GamepadButton::Start,
+        GamepadButton::Guide,
+        GamepadButton::DpadUp,
+        GamepadButton::DpadDown,
+        GamepadButton::DpadLeft,
+        GamepadButton::DpadRight,
+    ];
+
+    fn mask(self) -> u16 {
+        1 << (self as u16)
+    }
+}
+
+/// Analog outputs of the emulated gamepad
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl GamepadAxis {
+    pub const ALL: [GamepadAxis; 6] = [
+        GamepadAxis::LeftStickX,
+        GamepadAxis::LeftStickY,
+        GamepadAxis::RightStickX,
+        GamepadAxis::RightStickY,
+        GamepadAxis::LeftTrigger,
+        GamepadAxis::RightTrigger,
+    ];
+
+    /// Triggers range from `0.0` to `1.0`, stick axes from `-1.0` to `1.0`
+    pub fn is_trigger(self) -> bool {
+        matches!(self, GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger)
+    }
+}
+
+/// A full snapshot of what the emulated gamepad should currently report.
+///
+/// Axis values are normalized (see [`GamepadAxis::is_trigger`]) so the translation pipeline
+/// doesn't need to know the native resolution of whichever backend ends up consuming them.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GamepadState {
+    buttons: u16,
+    axes: [f32; 6],
+}
+
+impl GamepadState {
+    pub fn is_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons & button.mask() != 0
+    }
+
+    pub fn set_button(&mut self, button: GamepadButton, pressed: bool) {
+        if pressed {
+            self.buttons |= button.mask();
+        } else {
+            self.buttons &= !button.mask();
+        }
+    }
+
+    /// Iterates over every button that is currently held
+    pub fn pressed_buttons(&self) -> impl Iterator<Item = GamepadButton> + '_ {
+        GamepadButton::ALL.into_iter().filter(|button| self.is_pressed(*button))
+    }
+
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes[axis as usize]
+    }
+
+    /// Values outside of the axis range are clamped
+    pub fn set_axis(&mut self, axis: GamepadAxis, value: f32) {
+        let min: f32 = if axis.is_trigger() { 0.0 } else { -1.0 };
+        self.axes[axis as usize] = value.clamp(min, 1.0);
+    }
+
+    /// Releases every button and centers every axis
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Force feedback requested by the host for the emulated gamepad.
+/// Motor strengths use the full `u16` range, matching XInput.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rumble {
+    pub low_frequency: u16,
+    pub high_frequency: u16,
+}
+
+/// Describes what an output backend is able to emulate
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    pub name: String,
+    pub buttons: Vec<GamepadButton>,
+    pub axes: Vec<GamepadAxis>,
+    pub rumble: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            name: "Virtual Gamepad".into(),
+            buttons: GamepadButton::ALL.to_vec(),
+            axes: GamepadAxis::ALL.to_vec(),
+            rumble: true,
+        }
+    }
+}
+
+/// An emulated gamepad the translation pipeline writes to.
+///
+/// Implemented once per output backend (uinput, uhid, ViGEmBus, ...), so nothing upstream of
+/// this trait needs to care which one is in use.
+pub trait VirtualGamepad: Send {
+    fn capabilities(&self) -> Capabilities;
+
+    /// Pushes a new state to the host. Called every time the translated state changes.
+    fn apply(&mut self, state: &GamepadState) -> Result<(), Box<dyn Error>>;
+
+    /// Returns the next pending force feedback request from the host, if any
+    fn poll_feedback(&mut self) -> Option<Rumble>;
+}
@@ -0,0 +1,10 @@
+use crate::output::OutputEvent;
+use std::error::Error;
+
+/// An emulated keyboard and mouse the translation pipeline writes to.
+///
+/// Only ever receives the keyboard and mouse events of a batch, gamepad events are folded into
+/// a [`GamepadState`](crate::output::GamepadState) and sent to the [`VirtualGamepad`](crate::output::VirtualGamepad) instead.
+pub trait VirtualKeyboardMouse: Send {
+    fn emit(&mut self, event: &OutputEvent) -> Result<(), Box<dyn Error>>;
+}
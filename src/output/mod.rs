@@ -0,0 +1,16 @@
+mod event;
+mod gamepad;
+mod keyboard_mouse;
+mod recording;
+mod send_input;
+mod sink;
+mod vigem;
+
+pub(crate) use self::event::snake_case;
+pub use self::event::{DigitalOutput, Key, MouseButton, OutputEvent};
+pub use self::gamepad::{Capabilities, GamepadAxis, GamepadButton, GamepadState, Rumble, VirtualGamepad};
+pub use self::keyboard_mouse::VirtualKeyboardMouse;
+pub use self::recording::{RecordingGamepad, RecordingKeyboardMouse};
+pub use self::send_input::SendInputKeyboardMouse;
+pub use self::sink::OutputSink;
+pub use self::vigem::ViGEmGamepad;
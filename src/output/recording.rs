@@ -0,0 +1,158 @@
+use crate::output::{Capabilities, GamepadState, OutputEvent, Rumble, VirtualGamepad, VirtualKeyboardMouse};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct Recording {
+    states: Vec<GamepadState>,
+    feedback: VecDeque<Rumble>,
+}
+
+/// In-memory [`VirtualGamepad`] that records every applied state instead of emitting it.
+///
+/// Clones share the same recording, so one clone can be boxed into the pipeline while another
+/// is kept around to make assertions on what was emitted.
+#[derive(Clone, Default)]
+pub struct RecordingGamepad {
+    capabilities: Capabilities,
+    recording: Arc<Mutex<Recording>>,
+}
+
+impl RecordingGamepad {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capabilities(capabilities: Capabilities) -> Self {
+        Self {
+            capabilities,
+            recording: Arc::default(),
+        }
+    }
+
+    /// Every state applied so far, oldest first
+    pub fn states(&self) -> Vec<GamepadState> {
+        self.recording.lock().unwrap().states.clone()
+    }
+
+    pub fn last_state(&self) -> Option<GamepadState> {
+        self.recording.lock().unwrap().states.last().copied()
+    }
+
+    pub fn clear(&self) {
+        self.recording.lock().unwrap().states.clear();
+    }
+
+    /// Queues a rumble request, as if the host had sent it
+    pub fn push_feedback(&self, rumble: Rumble) {
+        self.recording.lock().unwrap().feedback.push_back(rumble);
+    }
+}
+
+impl VirtualGamepad for RecordingGamepad {
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities.clone()
+    }
+
+    fn apply(&mut self, state: &GamepadState) -> Result<(), Box<dyn Error>> {
+        self.recording.lock().unwrap().states.push(*state);
+        Ok(())
+    }
+
+    fn poll_feedback(&mut self) -> Option<Rumble> {
+        self.recording.lock().unwrap().feedback.pop_front()
+    }
+}
+
+/// In-memory [`VirtualKeyboardMouse`] that records every event instead of emitting it.
+/// Clones share the same recording, like [`RecordingGamepad`].
+#[derive(Clone, Default)]
+pub struct RecordingKeyboardMouse {
+    events: Arc<Mutex<Vec<OutputEvent>>>,
+}
+
+impl RecordingKeyboardMouse {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every event emitted so far, oldest first
+    pub fn events(&self) -> Vec<OutputEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        self.events.lock().unwrap().clear();
+    }
+}
+
+impl VirtualKeyboardMouse for RecordingKeyboardMouse {
+    fn emit(&mut self, event: &OutputEvent) -> Result<(), Box<dyn Error>> {
+        self.events.lock().unwrap().push(*event);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deck::{DeckButton, InputReport};
+    use crate::mapping::MappingEngine;
+    use crate::output::{GamepadAxis, GamepadButton, OutputSink};
+    use crate::profile::Profile;
+    use std::time::Duration;
+
+    #[test]
+    fn round_trips_states_and_feedback() {
+        let recording: RecordingGamepad = RecordingGamepad::new();
+        let mut gamepad: Box<dyn VirtualGamepad> = Box::new(recording.clone());
+        let mut state: GamepadState = GamepadState::default();
+        state.set_button(GamepadButton::A, true);
+        gamepad.apply(&GamepadState::default()).unwrap();
+        gamepad.apply(&state).unwrap();
+        assert_eq!(recording.states(), [GamepadState::default(), state]);
+        assert_eq!(recording.last_state(), Some(state));
+
+        let rumble: Rumble = Rumble {
+            low_frequency: 0x8000,
+            high_frequency: 0xFFFF,
+        };
+        assert_eq!(gamepad.poll_feedback(), None);
+        recording.push_feedback(rumble);
+        recording.push_feedback(Rumble::default());
+        assert_eq!(gamepad.poll_feedback(), Some(rumble));
+        assert_eq!(gamepad.poll_feedback(), Some(Rumble::default()));
+        assert_eq!(gamepad.poll_feedback(), None);
+
+        recording.clear();
+        assert_eq!(recording.last_state(), None);
+    }
+
+    #[test]
+    fn records_engine_output_through_sink() {
+        let recording: RecordingGamepad = RecordingGamepad::new();
+        let mut sink: OutputSink = OutputSink::new(Box::new(recording.clone()));
+        let mut engine: MappingEngine = MappingEngine::new(Profile::default());
+
+        let mut report: InputReport = InputReport::default();
+        sink.send(&engine.process(&report)).unwrap();
+        // Centering the already centered axes doesn't change the state
+        assert_eq!(recording.states(), []);
+
+        report.timestamp = Duration::from_millis(4);
+        report.set_button(DeckButton::A, true);
+        report.right_trigger = i16::MAX as u16;
+        sink.send(&engine.process(&report)).unwrap();
+        let state: GamepadState = recording.last_state().unwrap();
+        assert!(state.is_pressed(GamepadButton::A));
+        assert_eq!(state.axis(GamepadAxis::RightTrigger), 1.0);
+
+        report.timestamp = Duration::from_millis(8);
+        report.set_button(DeckButton::A, false);
+        report.right_trigger = 0;
+        sink.send(&engine.process(&report)).unwrap();
+        assert_eq!(recording.states().len(), 2);
+        assert_eq!(recording.last_state(), Some(GamepadState::default()));
+    }
+}
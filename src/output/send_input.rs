@@ -0,0 +1,195 @@
+use crate::output::{DigitalOutput, Key, MouseButton, OutputEvent, VirtualKeyboardMouse};
+use std::error::Error;
+use std::mem;
+use windows::Win32::UI::Input::KeyboardAndMouse::*;
+
+/// One scroll wheel notch, `WHEEL_DELTA`
+const WHEEL_DELTA: i32 = 120;
+
+/// [`VirtualKeyboardMouse`] injecting events into the Windows input stream with `SendInput`
+#[derive(Default)]
+pub struct SendInputKeyboardMouse;
+
+impl SendInputKeyboardMouse {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl VirtualKeyboardMouse for SendInputKeyboardMouse {
+    fn emit(&mut self, event: &OutputEvent) -> Result<(), Box<dyn Error>> {
+        let inputs: Vec<INPUT> = match *event {
+            OutputEvent::Press(DigitalOutput::Key(key)) => vec![keyboard_input(key, true)],
+            OutputEvent::Release(DigitalOutput::Key(key)) => vec![keyboard_input(key, false)],
+            OutputEvent::Press(DigitalOutput::Mouse(button)) => vec![mouse_button_input(button, true)],
+            OutputEvent::Release(DigitalOutput::Mouse(button)) => vec![mouse_button_input(button, false)],
+            OutputEvent::MouseMove { x, y } => vec![mouse_input(x, y, 0, MOUSEEVENTF_MOVE)],
+            OutputEvent::Scroll { x, y } => [(y, MOUSEEVENTF_WHEEL), (x, MOUSEEVENTF_HWHEEL)]
+                .into_iter()
+                .filter(|(notches, _)| *notches != 0)
+                .map(|(notches, flags)| mouse_input(0, 0, notches * WHEEL_DELTA, flags))
+                .collect(),
+            _ => return Ok(()),
+        };
+
+        let sent: u32 = unsafe { SendInput(&inputs, mem::size_of::<INPUT>() as i32) };
+        if sent as usize != inputs.len() {
+            return Err(format!("SendInput sent {sent} of {} inputs for {:?}", inputs.len(), event).into());
+        }
+        Ok(())
+    }
+}
+
+fn keyboard_input(key: Key, pressed: bool) -> INPUT {
+    let (vk, extended): (VIRTUAL_KEY, bool) = virtual_key(key);
+    let mut flags: KEYBD_EVENT_FLAGS = KEYBD_EVENT_FLAGS(0);
+    if extended {
+        flags |= KEYEVENTF_EXTENDEDKEY;
+    }
+    if !pressed {
+        flags |= KEYEVENTF_KEYUP;
+    }
+    // Games reading raw input or DirectInput go by the scan code rather than the virtual key
+    let scan: u16 = unsafe { MapVirtualKeyW(vk.0 as u32, MAPVK_VK_TO_VSC) } as u16;
+    INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: vk,
+                wScan: scan,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    }
+}
+
+fn mouse_button_input(button: MouseButton, pressed: bool) -> INPUT {
+    // The data of the X buttons is `XBUTTON1` or `XBUTTON2`
+    let (data, flags): (i32, MOUSE_EVENT_FLAGS) = match (button, pressed) {
+        (MouseButton::Left, true) => (0, MOUSEEVENTF_LEFTDOWN),
+        (MouseButton::Left, false) => (0, MOUSEEVENTF_LEFTUP),
+        (MouseButton::Right, true) => (0, MOUSEEVENTF_RIGHTDOWN),
+        (MouseButton::Right, false) => (0, MOUSEEVENTF_RIGHTUP),
+        (MouseButton::Middle, true) => (0, MOUSEEVENTF_MIDDLEDOWN),
+        (MouseButton::Middle, false) => (0, MOUSEEVENTF_MIDDLEUP),
+        (MouseButton::Back, true) => (1, MOUSEEVENTF_XDOWN),
+        (MouseButton::Back, false) => (1, MOUSEEVENTF_XUP),
+        (MouseButton::Forward, true) => (2, MOUSEEVENTF_XDOWN),
+        (MouseButton::Forward, false) => (2, MOUSEEVENTF_XUP),
+    };
+    mouse_input(0, 0, data, flags)
+}
+
+fn mouse_input(dx: i32, dy: i32, data: i32, flags: MOUSE_EVENT_FLAGS) -> INPUT {
+    INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                dx,
+                dy,
+                // Wheel deltas are signed even though the field isn't
+                mouseData: data as u32,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    }
+}
+
+/// Virtual key of `key`, and whether it is one of the extended keys that need
+/// `KEYEVENTF_EXTENDEDKEY` to not be taken for their numpad twins
+fn virtual_key(key: Key) -> (VIRTUAL_KEY, bool) {
+    match key {
+        Key::A => (VK_A, false),
+        Key::B => (VK_B, false),
+        Key::C => (VK_C, false),
+        Key::D => (VK_D, false),
+        Key::E => (VK_E, false),
+        Key::F => (VK_F, false),
+        Key::G => (VK_G, false),
+        Key::H => (VK_H, false),
+        Key::I => (VK_I, false),
+        Key::J => (VK_J, false),
+        Key::K => (VK_K, false),
+        Key::L => (VK_L, false),
+        Key::M => (VK_M, false),
+        Key::N => (VK_N, false),
+        Key::O => (VK_O, false),
+        Key::P => (VK_P, false),
+        Key::Q => (VK_Q, false),
+        Key::R => (VK_R, false),
+        Key::S => (VK_S, false),
+        Key::T => (VK_T, false),
+        Key::U => (VK_U, false),
+        Key::V => (VK_V, false),
+        Key::W => (VK_W, false),
+        Key::X => (VK_X, false),
+        Key::Y => (VK_Y, false),
+        Key::Z => (VK_Z, false),
+        Key::Num0 => (VK_0, false),
+        Key::Num1 => (VK_1, false),
+        Key::Num2 => (VK_2, false),
+        Key::Num3 => (VK_3, false),
+        Key::Num4 => (VK_4, false),
+        Key::Num5 => (VK_5, false),
+        Key::Num6 => (VK_6, false),
+        Key::Num7 => (VK_7, false),
+        Key::Num8 => (VK_8, false),
+        Key::Num9 => (VK_9, false),
+        Key::F1 => (VK_F1, false),
+        Key::F2 => (VK_F2, false),
+        Key::F3 => (VK_F3, false),
+        Key::F4 => (VK_F4, false),
+        Key::F5 => (VK_F5, false),
+        Key::F6 => (VK_F6, false),
+        Key::F7 => (VK_F7, false),
+        Key::F8 => (VK_F8, false),
+        Key::F9 => (VK_F9, false),
+        Key::F10 => (VK_F10, false),
+        Key::F11 => (VK_F11, false),
+        Key::F12 => (VK_F12, false),
+        Key::Escape => (VK_ESCAPE, false),
+        Key::Enter => (VK_RETURN, false),
+        Key::Tab => (VK_TAB, false),
+        Key::Space => (VK_SPACE, false),
+        Key::Backspace => (VK_BACK, false),
+        Key::Delete => (VK_DELETE, true),
+        Key::Insert => (VK_INSERT, true),
+        Key::Home => (VK_HOME, true),
+        Key::End => (VK_END, true),
+        Key::PageUp => (VK_PRIOR, true),
+        Key::PageDown => (VK_NEXT, true),
+        Key::Up => (VK_UP, true),
+        Key::Down => (VK_DOWN, true),
+        Key::Left => (VK_LEFT, true),
+        Key::Right => (VK_RIGHT, true),
+        Key::Minus => (VK_OEM_MINUS, false),
+        Key::Equal => (VK_OEM_PLUS, false),
+        Key::LeftBracket => (VK_OEM_4, false),
+        Key::RightBracket => (VK_OEM_6, false),
+        Key::Semicolon => (VK_OEM_1, false),
+        Key::Apostrophe => (VK_OEM_7, false),
+        Key::Grave => (VK_OEM_3, false),
+        Key::Backslash => (VK_OEM_5, false),
+        Key::Comma => (VK_OEM_COMMA, false),
+        Key::Period => (VK_OEM_PERIOD, false),
+        Key::Slash => (VK_OEM_2, false),
+        Key::CapsLock => (VK_CAPITAL, false),
+        Key::LeftShift => (VK_LSHIFT, false),
+        Key::RightShift => (VK_RSHIFT, false),
+        Key::LeftCtrl => (VK_LCONTROL, false),
+        Key::RightCtrl => (VK_RCONTROL, true),
+        Key::LeftAlt => (VK_LMENU, false),
+        Key::RightAlt => (VK_RMENU, true),
+        Key::LeftMeta => (VK_LWIN, true),
+        Key::RightMeta => (VK_RWIN, true),
+        Key::VolumeUp => (VK_VOLUME_UP, true),
+        Key::VolumeDown => (VK_VOLUME_DOWN, true),
+        Key::Mute => (VK_VOLUME_MUTE, true),
+        Key::PlayPause => (VK_MEDIA_PLAY_PAUSE, true),
+        Key::PrintScreen => (VK_SNAPSHOT, true),
+    }
+}
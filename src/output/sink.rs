@@ -0,0 +1,58 @@
+use crate::output::{DigitalOutput, GamepadState, OutputEvent, VirtualGamepad, VirtualKeyboardMouse};
+use crate::prelude::*;
+use std::error::Error;
+
+/// Routes output events to the backends that can emit them
+pub struct OutputSink {
+    gamepad: Box<dyn VirtualGamepad>,
+    keyboard_mouse: Option<Box<dyn VirtualKeyboardMouse>>,
+    state: GamepadState,
+}
+
+impl OutputSink {
+    pub fn new(gamepad: Box<dyn VirtualGamepad>) -> Self {
+        Self {
+            gamepad,
+            keyboard_mouse: None,
+            state: GamepadState::default(),
+        }
+    }
+
+    pub fn set_keyboard_mouse(&mut self, keyboard_mouse: Box<dyn VirtualKeyboardMouse>) {
+        self.keyboard_mouse = Some(keyboard_mouse);
+    }
+
+    pub fn gamepad(&mut self) -> &mut dyn VirtualGamepad {
+        self.gamepad.as_mut()
+    }
+
+    /// The gamepad state as of the last batch
+    pub fn state(&self) -> &GamepadState {
+        &self.state
+    }
+
+    /// Applies a batch of events. The gamepad only receives a new state if the batch changed it.
+    pub fn send(&mut self, events: &[OutputEvent]) -> Result<(), Box<dyn Error>> {
+        let mut state: GamepadState = self.state;
+        for event in events {
+            match event {
+                OutputEvent::Press(DigitalOutput::Gamepad(button)) => state.set_button(*button, true),
+                OutputEvent::Release(DigitalOutput::Gamepad(button)) => state.set_button(*button, false),
+                OutputEvent::Axis(axis, value) => state.set_axis(*axis, *value),
+                // Played on the controller by whoever reads the events, see `HapticPlayer`
+                OutputEvent::Haptic(_) => {}
+                OutputEvent::Menu(_) => {}
+                _ => match self.keyboard_mouse.as_mut() {
+                    Some(keyboard_mouse) => keyboard_mouse.emit(event)?,
+                    None => trace!("No keyboard/mouse backend, dropping {:?}", event),
+                },
+            }
+        }
+
+        if state != self.state {
+            self.gamepad.apply(&state)?;
+            self.state = state;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use crate::output::{Capabilities, GamepadAxis, GamepadButton, GamepadState, Rumble, VirtualGamepad};
+use std::error::Error;
+use vigem_client::{Client, TargetId, XButtons, XGamepad, Xbox360Wired};
+
+/// [`VirtualGamepad`] backed by an Xbox 360 controller plugged into the ViGEmBus driver
+pub struct ViGEmGamepad {
+    target: Xbox360Wired<Client>,
+}
+
+impl ViGEmGamepad {
+    /// Plugs a new controller into ViGEmBus and waits until the host picked it up
+    pub fn connect() -> Result<Self, Box<dyn Error>> {
+        let client: Client =
+            Client::connect().map_err(|err| format!("Can't connect to ViGEmBus, is the driver installed? {err}"))?;
+        let mut target: Xbox360Wired<Client> = Xbox360Wired::new(client, TargetId::XBOX360_WIRED);
+        target.plugin()?;
+        target.wait_ready()?;
+        Ok(Self { target })
+    }
+}
+
+impl VirtualGamepad for ViGEmGamepad {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            name: "ViGEmBus Xbox 360 Controller".into(),
+            // Rumble notifications aren't read back from the driver
+            rumble: false,
+            ..Capabilities::default()
+        }
+    }
+
+    fn apply(&mut self, state: &GamepadState) -> Result<(), Box<dyn Error>> {
+        let raw: u16 = state.pressed_buttons().fold(0, |raw, button| raw | xinput_mask(button));
+        let stick = |axis: GamepadAxis| (state.axis(axis) * i16::MAX as f32).round() as i16;
+        let trigger = |axis: GamepadAxis| (state.axis(axis) * u8::MAX as f32).round() as u8;
+        let gamepad: XGamepad = XGamepad {
+            buttons: XButtons { raw },
+            left_trigger: trigger(GamepadAxis::LeftTrigger),
+            right_trigger: trigger(GamepadAxis::RightTrigger),
+            thumb_lx: stick(GamepadAxis::LeftStickX),
+            thumb_ly: stick(GamepadAxis::LeftStickY),
+            thumb_rx: stick(GamepadAxis::RightStickX),
+            thumb_ry: stick(GamepadAxis::RightStickY),
+        };
+        self.target.update(&gamepad)?;
+        Ok(())
+    }
+
+    fn poll_feedback(&mut self) -> Option<Rumble> {
+        None
+    }
+}
+
+/// `XINPUT_GAMEPAD_*` bit of a button
+fn xinput_mask(button: GamepadButton) -> u16 {
+    match button {
+        GamepadButton::DpadUp => 0x0001,
+        GamepadButton::DpadDown => 0x0002,
+        GamepadButton::DpadLeft => 0x0004,
+        GamepadButton::DpadRight => 0x0008,
+        GamepadButton::Start => 0x0010,
+        GamepadButton::Back => 0x0020,
+        GamepadButton::LeftStick => 0x0040,
+        GamepadButton::RightStick => 0x0080,
+        GamepadButton::LeftBumper => 0x0100,
+        GamepadButton::RightBumper => 0x0200,
+        GamepadButton::Guide => 0x0400,
+        GamepadButton::A => 0x1000,
+        GamepadButton::B => 0x2000,
+        GamepadButton::X => 0x4000,
+        GamepadButton::Y => 0x8000,
+    }
+}
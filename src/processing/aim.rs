@@ -0,0 +1,266 @@
+use crate::profile::{GyroSettings, GyroSpace};
+use std::time::Duration;
+
+/// Pointer motion per degree the controller turns, at sensitivity 1.0
+const PIXELS_PER_DEGREE: f32 = 10.0;
+/// Turning speed that pushes the stick all the way, at sensitivity 1.0
+const STICK_FULL_RATE: f32 = 90.0;
+/// How much player space lets the world yaw exceed the local yaw and roll, lets turning work
+/// well with the controller held anywhere between flat and upright
+const YAW_RELAX: f32 = 1.41;
+
+/// Turns the corrected angular velocity from [`crate::processing::GyroProcessor`] into pointer
+/// motion or stick deflection.
+///
+/// Takes plain rates and a gravity vector rather than reports, so recorded traces can be replayed
+/// through it directly.
+#[derive(Debug, Default)]
+pub struct GyroAim {
+    last_timestamp: Option<Duration>,
+    /// Recent average of the aim velocity for smoothing, in °/s
+    smoothed: (f32, f32),
+    /// Sub-pixel motion carried over to the next report
+    remainder: (f32, f32),
+}
+
+impl GyroAim {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets the motion history, e.g. while the gyro is deactivated
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Pointer motion in whole pixels for one report, positive Y is down. `rate` is in °/s around
+    /// the controller's X (pitch), Y (roll) and Z (yaw) axes, `gravity` a unit vector pointing up.
+    pub fn mouse(&mut self, settings: &GyroSettings, rate: [f32; 3], gravity: [f32; 3], now: Duration) -> (i32, i32) {
+        let elapsed: f32 = self.elapsed(now);
+        let (x, y) = self.velocity(settings, rate, gravity, elapsed);
+        let x: f32 = self.remainder.0 + x * elapsed * PIXELS_PER_DEGREE;
+        let y: f32 = self.remainder.1 - y * elapsed * PIXELS_PER_DEGREE;
+        self.remainder = (x.fract(), y.fract());
+        (x.trunc() as i32, y.trunc() as i32)
+    }
+
+    /// Stick deflection for one report, positive Y is up. Takes the same inputs as
+    /// [`GyroAim::mouse`].
+    pub fn stick(&mut self, settings: &GyroSettings, rate: [f32; 3], gravity: [f32; 3], now: Duration) -> (f32, f32) {
+        let elapsed: f32 = self.elapsed(now);
+        let (x, y) = self.velocity(settings, rate, gravity, elapsed);
+        let (x, y) = (x / STICK_FULL_RATE, y / STICK_FULL_RATE);
+        let magnitude: f32 = x.hypot(y);
+        if magnitude <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let scaled: f32 = settings.anti_deadzone + (1.0 - settings.anti_deadzone) * magnitude.min(1.0);
+        (x / magnitude * scaled, y / magnitude * scaled)
+    }
+
+    fn elapsed(&mut self, now: Duration) -> f32 {
+        let elapsed: f32 = self
+            .last_timestamp
+            .map(|last| now.saturating_sub(last).as_secs_f32())
+            .unwrap_or(0.0);
+        self.last_timestamp = Some(now);
+        elapsed
+    }
+
+    /// Aim velocity in °/s after smoothing, tightening and sensitivity, positive is right and up
+    fn velocity(&mut self, settings: &GyroSettings, rate: [f32; 3], gravity: [f32; 3], elapsed: f32) -> (f32, f32) {
+        let pitch: f32 = rate[0];
+        // Turning left is a positive rotation around the up axis, but has to move the aim left
+        let yaw: f32 = match settings.space {
+            GyroSpace::Yaw => -rate[2],
+            GyroSpace::Roll => rate[1],
+            GyroSpace::PlayerSpace => {
+                let world_yaw: f32 = rate[1] * gravity[1] + rate[2] * gravity[2];
+                -world_yaw.signum() * (world_yaw.abs() * YAW_RELAX).min(rate[1].hypot(rate[2]))
+            }
+            GyroSpace::WorldSpace => -(0..3).map(|axis| rate[axis] * gravity[axis]).sum::<f32>(),
+        };
+        let speed: f32 = yaw.hypot(pitch);
+
+        // Below half the threshold the motion is fully smoothed, above it fully direct
+        let smoothing_time: f32 = settings.smoothing_ms as f32 / 1000.0;
+        let blend: f32 = if smoothing_time > 0.0 {
+            (elapsed / smoothing_time).min(1.0)
+        } else {
+            1.0
+        };
+        self.smoothed.0 += blend * (yaw - self.smoothed.0);
+        self.smoothed.1 += blend * (pitch - self.smoothed.1);
+        let direct: f32 = if settings.smoothing_threshold > 0.0 {
+            let half: f32 = settings.smoothing_threshold / 2.0;
+            ((speed - half) / half).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let mut x: f32 = direct * yaw + (1.0 - direct) * self.smoothed.0;
+        let mut y: f32 = direct * pitch + (1.0 - direct) * self.smoothed.1;
+
+        if speed < settings.tightening {
+            let scale: f32 = speed / settings.tightening;
+            x *= scale;
+            y *= scale;
+        }
+
+        let mut gain: f32 = settings.sensitivity;
+        if settings.acceleration > 0.0 {
+            let ramp: f32 =
+                (speed - settings.acceleration_start) / (settings.acceleration_end - settings.acceleration_start);
+            gain *= 1.0 + settings.acceleration * settings.acceleration_curve.apply(ramp);
+        }
+        x *= if settings.invert_x { -gain } else { gain };
+        y *= if settings.invert_y { -gain } else { gain };
+        (x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::Curve;
+
+    const STEP: Duration = Duration::from_millis(4);
+    const FLAT: [f32; 3] = [0.0, 0.0, 1.0];
+    const UPRIGHT: [f32; 3] = [0.0, 1.0, 0.0];
+
+    fn settings(space: GyroSpace) -> GyroSettings {
+        GyroSettings {
+            space,
+            ..GyroSettings::default()
+        }
+    }
+
+    /// Aim velocity in °/s of the last of `reports` reports turning at `rate`, read off the stick
+    /// output which is linear below full deflection
+    fn velocity(settings: &GyroSettings, rate: [f32; 3], gravity: [f32; 3], reports: u32) -> (f32, f32) {
+        let mut aim: GyroAim = GyroAim::new();
+        let mut deflection: (f32, f32) = (0.0, 0.0);
+        for report in 0..reports {
+            deflection = aim.stick(settings, rate, gravity, STEP * report);
+        }
+        (deflection.0 * STICK_FULL_RATE, deflection.1 * STICK_FULL_RATE)
+    }
+
+    fn assert_close(actual: (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-3 && (actual.1 - expected.1).abs() < 1e-3,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn yaw_space_follows_yaw_axis() {
+        let settings: GyroSettings = settings(GyroSpace::Yaw);
+        // Turning left is positive around Z and moves the aim left
+        assert_close(velocity(&settings, [0.0, 0.0, 30.0], FLAT, 1), (-30.0, 0.0));
+        assert_close(velocity(&settings, [0.0, 30.0, 0.0], FLAT, 1), (0.0, 0.0));
+        assert_close(velocity(&settings, [20.0, 0.0, 0.0], FLAT, 1), (0.0, 20.0));
+    }
+
+    #[test]
+    fn roll_space_follows_roll_axis() {
+        let settings: GyroSettings = settings(GyroSpace::Roll);
+        assert_close(velocity(&settings, [0.0, 30.0, 0.0], FLAT, 1), (30.0, 0.0));
+        assert_close(velocity(&settings, [0.0, 0.0, 30.0], FLAT, 1), (0.0, 0.0));
+        assert_close(velocity(&settings, [-20.0, 0.0, 0.0], FLAT, 1), (0.0, -20.0));
+    }
+
+    #[test]
+    fn world_space_turns_around_gravity() {
+        let settings: GyroSettings = settings(GyroSpace::WorldSpace);
+        assert_close(velocity(&settings, [0.0, 0.0, 30.0], FLAT, 1), (-30.0, 0.0));
+        assert_close(velocity(&settings, [0.0, 30.0, 0.0], UPRIGHT, 1), (-30.0, 0.0));
+        assert_close(velocity(&settings, [0.0, 0.0, 30.0], UPRIGHT, 1), (0.0, 0.0));
+        let tilted: [f32; 3] = [0.0, 0.6, 0.8];
+        assert_close(velocity(&settings, [0.0, 0.0, 30.0], tilted, 1), (-24.0, 0.0));
+    }
+
+    #[test]
+    fn player_space_relaxes_world_yaw() {
+        let settings: GyroSettings = settings(GyroSpace::PlayerSpace);
+        // Capped at the local turning speed
+        assert_close(velocity(&settings, [0.0, 0.0, 30.0], FLAT, 1), (-30.0, 0.0));
+        assert_close(velocity(&settings, [0.0, 30.0, 0.0], UPRIGHT, 1), (-30.0, 0.0));
+        // World space would give 18°/s
+        let tilted: [f32; 3] = [0.0, 0.8, 0.6];
+        assert_close(velocity(&settings, [0.0, 0.0, 30.0], tilted, 1), (-18.0 * YAW_RELAX, 0.0));
+        assert_close(velocity(&settings, [0.0, 0.0, -30.0], tilted, 1), (18.0 * YAW_RELAX, 0.0));
+        assert_close(velocity(&settings, [0.0, 0.0, 30.0], [0.0, 0.6, 0.8], 1), (-30.0, 0.0));
+    }
+
+    #[test]
+    fn tightening_scales_slow_motion() {
+        let settings: GyroSettings = GyroSettings {
+            tightening: 20.0,
+            ..settings(GyroSpace::Yaw)
+        };
+        assert_close(velocity(&settings, [0.0, 0.0, -10.0], FLAT, 1), (5.0, 0.0));
+        assert_close(velocity(&settings, [6.0, 0.0, -8.0], FLAT, 1), (4.0, 3.0));
+        assert_close(velocity(&settings, [0.0, 0.0, -20.0], FLAT, 1), (20.0, 0.0));
+        assert_close(velocity(&settings, [0.0, 0.0, -40.0], FLAT, 1), (40.0, 0.0));
+    }
+
+    #[test]
+    fn smoothing_below_threshold() {
+        let settings: GyroSettings = GyroSettings {
+            smoothing_threshold: 20.0,
+            smoothing_ms: 100,
+            ..settings(GyroSpace::Yaw)
+        };
+        // Below half the threshold each 4ms report moves 4% of the way to the actual speed
+        assert_close(velocity(&settings, [0.0, 0.0, -5.0], FLAT, 1), (0.0, 0.0));
+        assert_close(velocity(&settings, [0.0, 0.0, -5.0], FLAT, 2), (0.2, 0.0));
+        assert_close(velocity(&settings, [0.0, 0.0, -5.0], FLAT, 3), (0.2 + 0.04 * 4.8, 0.0));
+        let (settled, _) = velocity(&settings, [0.0, 0.0, -5.0], FLAT, 500);
+        assert!((settled - 5.0).abs() < 0.01, "{settled}");
+        // From the threshold on the motion is direct, halfway between it blends
+        assert_close(velocity(&settings, [0.0, 0.0, -20.0], FLAT, 1), (20.0, 0.0));
+        assert_close(velocity(&settings, [0.0, 0.0, -15.0], FLAT, 1), (7.5, 0.0));
+    }
+
+    #[test]
+    fn acceleration_ramps_gain() {
+        let settings: GyroSettings = GyroSettings {
+            sensitivity: 0.5,
+            acceleration: 1.0,
+            acceleration_start: 10.0,
+            acceleration_end: 50.0,
+            ..settings(GyroSpace::Yaw)
+        };
+        assert_close(velocity(&settings, [0.0, 0.0, -10.0], FLAT, 1), (5.0, 0.0));
+        assert_close(velocity(&settings, [0.0, 0.0, -30.0], FLAT, 1), (30.0 * 0.5 * 1.5, 0.0));
+        assert_close(velocity(&settings, [0.0, 0.0, -50.0], FLAT, 1), (50.0, 0.0));
+        assert_close(velocity(&settings, [0.0, 0.0, -60.0], FLAT, 1), (60.0, 0.0));
+
+        let settings: GyroSettings = GyroSettings {
+            acceleration_curve: Curve::Exponential { exponent: 2.0 },
+            ..settings
+        };
+        assert_close(velocity(&settings, [0.0, 0.0, -30.0], FLAT, 1), (30.0 * 0.5 * 1.25, 0.0));
+    }
+
+    #[test]
+    fn carries_sub_pixel_motion() {
+        let settings: GyroSettings = settings(GyroSpace::Yaw);
+        let mut aim: GyroAim = GyroAim::new();
+        // A quarter pixel right and a fifth of a pixel up per report
+        let rate: [f32; 3] = [5.0, 0.0, -6.25];
+        let mut total: (i32, i32) = (0, 0);
+        for report in 0..=100 {
+            let (x, y) = aim.mouse(&settings, rate, FLAT, STEP * report);
+            assert!(x.abs() <= 1 && y.abs() <= 1, "{x}, {y}");
+            total = (total.0 + x, total.1 + y);
+        }
+        assert!((24..=25).contains(&total.0), "{total:?}");
+        assert!((-20..=-19).contains(&total.1), "{total:?}");
+
+        // Without the carry none of it would ever move the pointer
+        aim.reset();
+        assert_eq!(aim.mouse(&settings, rate, FLAT, Duration::ZERO), (0, 0));
+        assert_eq!(aim.mouse(&settings, rate, FLAT, STEP), (0, 0));
+    }
+}
@@ -0,0 +1,90 @@
+use crate::profile::Curve;
+
+impl Curve {
+    /// Maps a magnitude in `0.0..=1.0` to the output magnitude, inputs outside the range are
+    /// clamped first
+    pub fn apply(&self, value: f32) -> f32 {
+        let value: f32 = value.clamp(0.0, 1.0);
+        match self {
+            Curve::Linear => value,
+            Curve::Exponential { exponent } => value.powf(*exponent),
+            Curve::Points { points } => {
+                let mut previous: [f32; 2] = [0.0, 0.0];
+                for point in points.iter().copied().chain([[1.0, 1.0]]) {
+                    if value <= point[0] {
+                        let width: f32 = point[0] - previous[0];
+                        if width <= 0.0 {
+                            return point[1];
+                        }
+                        let t: f32 = (value - previous[0]) / width;
+                        return previous[1] + t * (point[1] - previous[1]);
+                    }
+                    previous = point;
+                }
+                previous[1]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-5, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn linear_passes_through() {
+        for value in [0.0, 0.25, 0.5, 1.0] {
+            assert_close(Curve::Linear.apply(value), value);
+        }
+        assert_close(Curve::Linear.apply(-0.5), 0.0);
+        assert_close(Curve::Linear.apply(1.5), 1.0);
+    }
+
+    #[test]
+    fn exponential_raises_to_exponent() {
+        let curve: Curve = Curve::Exponential { exponent: 2.0 };
+        assert_close(curve.apply(0.5), 0.25);
+        assert_close(curve.apply(1.0), 1.0);
+        let curve: Curve = Curve::Exponential { exponent: 0.5 };
+        assert_close(curve.apply(0.25), 0.5);
+        assert_close(curve.apply(0.0), 0.0);
+    }
+
+    #[test]
+    fn points_interpolate_between_implied_ends() {
+        let curve: Curve = Curve::Points {
+            points: vec![[0.5, 0.2], [0.8, 0.5]],
+        };
+        assert_close(curve.apply(0.0), 0.0);
+        assert_close(curve.apply(0.25), 0.1);
+        assert_close(curve.apply(0.5), 0.2);
+        assert_close(curve.apply(0.65), 0.35);
+        assert_close(curve.apply(0.9), 0.75);
+        assert_close(curve.apply(1.0), 1.0);
+        assert_close(curve.apply(2.0), 1.0);
+    }
+
+    #[test]
+    fn points_with_duplicate_x_step() {
+        // Profiles reject these, but a step must not divide by zero
+        let curve: Curve = Curve::Points {
+            points: vec![[0.5, 0.2], [0.5, 0.8]],
+        };
+        assert_close(curve.apply(0.5), 0.2);
+        assert_close(curve.apply(0.75), 0.9);
+        let curve: Curve = Curve::Points {
+            points: vec![[0.0, 0.3], [1.0, 1.0]],
+        };
+        assert_close(curve.apply(0.0), 0.3);
+        assert_close(curve.apply(0.5), 0.65);
+        assert_close(curve.apply(1.0), 1.0);
+
+        let err: toml::de::Error = toml::from_str::<Curve>("type = \"points\"\npoints = [[0.5, 0.2], [0.5, 0.8]]")
+            .unwrap_err();
+        assert!(err.message().contains("strictly increasing"), "{err}");
+    }
+}
@@ -0,0 +1,180 @@
+use crate::profile::FlickSettings;
+use std::time::Duration;
+
+/// How far below `threshold` a pushed stick may sag before it counts as released, keeps a stick
+/// resting right at the threshold from flicking over and over
+const RELEASE_HYSTERESIS: f32 = 0.1;
+
+/// A flick being turned, angles in degrees clockwise
+#[derive(Debug)]
+struct Flick {
+    angle: f32,
+    started: Duration,
+    /// Part of `angle` already turned
+    turned: f32,
+}
+
+/// Turns the stick angle into horizontal mouse motion, see [`FlickSettings`]
+#[derive(Debug, Default)]
+pub struct FlickStick {
+    last_timestamp: Option<Duration>,
+    /// Stick angle of the previous report, `None` while the stick isn't pushed
+    last_angle: Option<f32>,
+    flick: Option<Flick>,
+    /// Recent average of the rotation speed for smoothing, in °/s
+    smoothed: f32,
+    /// Sub-count motion carried over to the next report
+    remainder: f32,
+}
+
+impl FlickStick {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets the stick history and drops a flick in progress
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Processes the stick position of one report, `-1.0..=1.0` with positive Y up, and returns
+    /// the horizontal mouse motion in whole counts
+    pub fn update(&mut self, settings: &FlickSettings, (x, y): (f32, f32), now: Duration) -> i32 {
+        let elapsed: f32 = self
+            .last_timestamp
+            .map(|last| now.saturating_sub(last).as_secs_f32())
+            .unwrap_or(0.0);
+        self.last_timestamp = Some(now);
+
+        let magnitude: f32 = x.hypot(y);
+        // Clockwise from straight up, so pushing right turns right
+        let angle: f32 = x.atan2(y).to_degrees();
+        let pushed: bool = magnitude >= settings.threshold
+            || (self.last_angle.is_some() && magnitude >= release_threshold(settings.threshold));
+
+        let mut turn: f32 = 0.0;
+        if pushed {
+            match self.last_angle {
+                Some(last) => turn += self.smooth(settings, wrap_degrees(angle - last), elapsed),
+                None => {
+                    self.flick = Some(Flick {
+                        angle,
+                        started: now,
+                        turned: 0.0,
+                    });
+                    self.smoothed = 0.0;
+                }
+            }
+            self.last_angle = Some(angle);
+        } else {
+            self.last_angle = None;
+        }
+
+        if let Some(flick) = &mut self.flick {
+            let flick_time: f32 = settings.flick_time_ms as f32 / 1000.0;
+            let progress: f32 = if flick_time > 0.0 {
+                (now.saturating_sub(flick.started).as_secs_f32() / flick_time).min(1.0)
+            } else {
+                1.0
+            };
+            // Eases out so the turn settles instead of stopping dead
+            let target: f32 = flick.angle * (1.0 - (1.0 - progress).powi(2));
+            turn += target - flick.turned;
+            flick.turned = target;
+            if progress >= 1.0 {
+                self.flick = None;
+            }
+        }
+
+        let counts: f32 = self.remainder + turn * settings.counts_per_360 / 360.0;
+        self.remainder = counts.fract();
+        counts.trunc() as i32
+    }
+
+    /// Rotation of the pushed stick, slow rotation is averaged and fast rotation passes through
+    fn smooth(&mut self, settings: &FlickSettings, delta: f32, elapsed: f32) -> f32 {
+        if elapsed <= 0.0 || settings.smoothing_threshold <= 0.0 {
+            return delta;
+        }
+        let speed: f32 = delta / elapsed;
+        let smoothing_time: f32 = settings.smoothing_ms as f32 / 1000.0;
+        let blend: f32 = if smoothing_time > 0.0 {
+            (elapsed / smoothing_time).min(1.0)
+        } else {
+            1.0
+        };
+        self.smoothed += blend * (speed - self.smoothed);
+        let half: f32 = settings.smoothing_threshold / 2.0;
+        let direct: f32 = ((speed.abs() - half) / half).clamp(0.0, 1.0);
+        (direct * speed + (1.0 - direct) * self.smoothed) * elapsed
+    }
+}
+
+/// Deflection below which a pushed stick counts as released. Low thresholds keep half of their
+/// deflection rather than the full hysteresis, or the stick could never be released.
+fn release_threshold(threshold: f32) -> f32 {
+    (threshold - RELEASE_HYSTERESIS).max(threshold / 2.0)
+}
+
+/// Wraps an angle difference to `-180.0..=180.0` degrees
+fn wrap_degrees(angle: f32) -> f32 {
+    (angle + 180.0).rem_euclid(360.0) - 180.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(flick_time_ms: u64, threshold: f32) -> FlickSettings {
+        FlickSettings {
+            counts_per_360: 3600.0,
+            flick_time_ms,
+            threshold,
+            ..FlickSettings::default()
+        }
+    }
+
+    fn at(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn flicks_toward_the_stick_over_flick_time() {
+        let settings: FlickSettings = settings(100, 0.9);
+        let mut flick: FlickStick = FlickStick::new();
+        assert_eq!(flick.update(&settings, (0.0, 0.5), at(0)), 0);
+
+        // Pushing right turns a quarter, easing out over the flick time
+        let turns: Vec<i32> = [0, 50, 100, 150].map(|ms| flick.update(&settings, (1.0, 0.0), at(10 + ms))).to_vec();
+        assert_eq!(turns[0], 0);
+        assert!(turns[1] > 450, "turned {} of 900 counts after half the flick time", turns[1]);
+        assert_eq!(turns.iter().sum::<i32>(), 900);
+        assert_eq!(turns[3], 0);
+    }
+
+    #[test]
+    fn rotating_across_straight_back_turns_the_short_way() {
+        let settings: FlickSettings = settings(0, 0.9);
+        let mut flick: FlickStick = FlickStick::new();
+        let back_right: (f32, f32) = (0.2_f32.sin(), -0.2_f32.cos());
+        let back_left: (f32, f32) = (-0.2_f32.sin(), -0.2_f32.cos());
+        flick.update(&settings, back_right, at(0));
+
+        let turn: i32 = flick.update(&settings, back_left, at(4));
+        let expected: i32 = (0.4_f32.to_degrees() * 10.0) as i32;
+        assert!(turn.abs_diff(expected) <= 1, "turned {turn} counts, expected {expected}");
+    }
+
+    #[test]
+    fn releases_below_a_low_threshold() {
+        let settings: FlickSettings = settings(0, 0.1);
+        let mut flick: FlickStick = FlickStick::new();
+        let first: i32 = flick.update(&settings, (0.5, 0.0), at(0));
+        assert!(first.abs_diff(900) <= 1);
+
+        // Half the threshold releases the stick even though the hysteresis is as big as the threshold
+        assert_eq!(flick.update(&settings, (0.04, 0.0), at(4)), 0);
+        let second: i32 = flick.update(&settings, (0.5, 0.0), at(8));
+        assert!(second.abs_diff(900) <= 1, "pushing again flicked {second} counts");
+    }
+}
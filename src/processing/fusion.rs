@@ -0,0 +1,393 @@
+use crate::deck::InputReport;
+use crate::profile::FusionFilter;
+use std::ops::Mul;
+use std::time::Duration;
+
+/// The controller sends a state report every 4ms
+const REPORT_INTERVAL: f32 = 0.004;
+/// Larger sequence gaps mean the reports stopped for a while, e.g. while the device was closed,
+/// and the receive timestamps are the better guess
+const MAX_SEQUENCE_GAP: u32 = 50;
+/// Rate in rad/s per unit of tilt error the complementary filter corrects with
+const COMPLEMENTARY_GAIN: f32 = 1.0;
+/// Step size of Madgwick's gradient descent
+const MADGWICK_BETA: f32 = 0.1;
+/// The accelerometer only shows where down is when it doesn't measure much else
+const MIN_GRAVITY: f32 = 0.8;
+const MAX_GRAVITY: f32 = 1.2;
+
+/// Rotation quaternion, `w` is the real part
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Quaternion {
+    pub const IDENTITY: Quaternion = Quaternion {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    /// Rotation by `angle` radians around the unit vector `axis`
+    pub fn from_axis_angle(axis: [f32; 3], angle: f32) -> Self {
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Self {
+            w: cos,
+            x: axis[0] * sin,
+            y: axis[1] * sin,
+            z: axis[2] * sin,
+        }
+    }
+
+    pub fn conjugate(self) -> Self {
+        Self {
+            w: self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+
+    pub fn normalized(self) -> Self {
+        let length: f32 = (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        if length < f32::EPSILON {
+            return Self::IDENTITY;
+        }
+        Self {
+            w: self.w / length,
+            x: self.x / length,
+            y: self.y / length,
+            z: self.z / length,
+        }
+    }
+
+    /// Applies the rotation to `vector`
+    pub fn rotate(self, vector: [f32; 3]) -> [f32; 3] {
+        let rotated: Quaternion = self
+            * Quaternion {
+                w: 0.0,
+                x: vector[0],
+                y: vector[1],
+                z: vector[2],
+            }
+            * self.conjugate();
+        [rotated.x, rotated.y, rotated.z]
+    }
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mul for Quaternion {
+    type Output = Quaternion;
+
+    /// Hamilton product, `a * b` rotates by `b` first
+    fn mul(self, other: Quaternion) -> Quaternion {
+        Quaternion {
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+        }
+    }
+}
+
+/// Tracks the orientation of the controller from its gyro and accelerometer.
+///
+/// The orientation rotates controller coordinates into world coordinates with Z pointing up. Its
+/// yaw is relative to where the controller pointed when tracking started and drifts slowly, there
+/// is no compass to correct it.
+#[derive(Debug, Default)]
+pub struct SensorFusion {
+    orientation: Quaternion,
+    /// Sequence number and timestamp of the previous report
+    last_report: Option<(u32, Duration)>,
+    started: bool,
+}
+
+impl SensorFusion {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn orientation(&self) -> Quaternion {
+        self.orientation
+    }
+
+    /// Unit vector pointing up in controller coordinates
+    pub fn gravity(&self) -> [f32; 3] {
+        self.orientation.conjugate().rotate([0.0, 0.0, 1.0])
+    }
+
+    /// Processes one report with its bias-corrected gyro `rate` in °/s. The time step comes from
+    /// the sequence numbers, which unlike receive times don't jitter with USB and thread
+    /// scheduling.
+    pub fn update_report(&mut self, filter: FusionFilter, report: &InputReport, rate: [f32; 3]) {
+        let elapsed: f32 = match self.last_report {
+            Some((sequence, timestamp)) => {
+                let reports: u32 = report.sequence.wrapping_sub(sequence);
+                if (1..=MAX_SEQUENCE_GAP).contains(&reports) {
+                    reports as f32 * REPORT_INTERVAL
+                } else {
+                    report.timestamp.saturating_sub(timestamp).as_secs_f32()
+                }
+            }
+            None => 0.0,
+        };
+        self.last_report = Some((report.sequence, report.timestamp));
+        self.update(filter, rate, report.accel_g(), elapsed);
+    }
+
+    /// Advances the orientation by `elapsed` seconds of angular velocity `rate` in °/s, with
+    /// `accel` in g
+    pub fn update(&mut self, filter: FusionFilter, rate: [f32; 3], accel: [f32; 3], elapsed: f32) {
+        let accel_length: f32 = length(accel);
+        let up: Option<[f32; 3]> = (MIN_GRAVITY..=MAX_GRAVITY)
+            .contains(&accel_length)
+            .then(|| accel.map(|value| value / accel_length));
+        if !self.started {
+            // Starting level would take seconds to settle, the accelerometer knows better
+            if let Some(up) = up {
+                self.orientation = tilt_from_up(up);
+                self.started = true;
+            }
+            return;
+        }
+
+        let rate: [f32; 3] = rate.map(f32::to_radians);
+        self.orientation = match filter {
+            FusionFilter::Complementary => self.complementary(rate, up, elapsed),
+            FusionFilter::Madgwick => self.madgwick(rate, up, elapsed),
+        };
+    }
+
+    fn complementary(&self, mut rate: [f32; 3], up: Option<[f32; 3]>, elapsed: f32) -> Quaternion {
+        if let Some(up) = up {
+            // Turning around `measured x estimated` moves the estimated up towards the measured one
+            let correction: [f32; 3] = cross(up, self.gravity());
+            for axis in 0..3 {
+                rate[axis] += COMPLEMENTARY_GAIN * correction[axis];
+            }
+        }
+        let angle: f32 = length(rate) * elapsed;
+        if angle <= 0.0 {
+            return self.orientation;
+        }
+        let axis: [f32; 3] = rate.map(|value| value / length(rate));
+        (self.orientation * Quaternion::from_axis_angle(axis, angle)).normalized()
+    }
+
+    fn madgwick(&self, [gx, gy, gz]: [f32; 3], up: Option<[f32; 3]>, elapsed: f32) -> Quaternion {
+        let Quaternion { w, x, y, z } = self.orientation;
+        let mut derivative: [f32; 4] = [
+            0.5 * (-x * gx - y * gy - z * gz),
+            0.5 * (w * gx + y * gz - z * gy),
+            0.5 * (w * gy - x * gz + z * gx),
+            0.5 * (w * gz + x * gy - y * gx),
+        ];
+
+        if let Some([ax, ay, az]) = up {
+            // Gradient of the distance between the estimated and the measured up
+            let step: [f32; 4] = [
+                4.0 * w * y * y + 2.0 * y * ax + 4.0 * w * x * x - 2.0 * x * ay,
+                4.0 * x * z * z - 2.0 * z * ax + 4.0 * w * w * x - 2.0 * w * ay - 4.0 * x
+                    + 8.0 * x * x * x
+                    + 8.0 * x * y * y
+                    + 4.0 * x * az,
+                4.0 * w * w * y + 2.0 * w * ax + 4.0 * y * z * z - 2.0 * z * ay - 4.0 * y
+                    + 8.0 * y * x * x
+                    + 8.0 * y * y * y
+                    + 4.0 * y * az,
+                4.0 * x * x * z - 2.0 * x * ax + 4.0 * y * y * z - 2.0 * y * ay,
+            ];
+            let step_length: f32 = step.iter().map(|value| value * value).sum::<f32>().sqrt();
+            if step_length > f32::EPSILON {
+                for (value, step) in derivative.iter_mut().zip(step) {
+                    *value -= MADGWICK_BETA * step / step_length;
+                }
+            }
+        }
+
+        Quaternion {
+            w: w + derivative[0] * elapsed,
+            x: x + derivative[1] * elapsed,
+            y: y + derivative[2] * elapsed,
+            z: z + derivative[3] * elapsed,
+        }
+        .normalized()
+    }
+}
+
+/// Orientation with no yaw whose up points along `up` in controller coordinates
+fn tilt_from_up(up: [f32; 3]) -> Quaternion {
+    // Rotates `up` onto world Z
+    let axis: [f32; 3] = cross(up, [0.0, 0.0, 1.0]);
+    let sin: f32 = length(axis);
+    let cos: f32 = up[2];
+    if sin < f32::EPSILON {
+        return if cos > 0.0 {
+            Quaternion::IDENTITY
+        } else {
+            Quaternion::from_axis_angle([1.0, 0.0, 0.0], std::f32::consts::PI)
+        };
+    }
+    Quaternion::from_axis_angle(axis.map(|value| value / sin), sin.atan2(cos))
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn length(vector: [f32; 3]) -> f32 {
+    vector.iter().map(|value| value * value).sum::<f32>().sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    const FILTERS: [FusionFilter; 2] = [FusionFilter::Complementary, FusionFilter::Madgwick];
+    /// Accelerometer reading of 1g
+    const ACCEL_COUNTS_PER_G: i16 = 16384;
+
+    /// Angle between two unit vectors, in degrees
+    fn angle_between(a: [f32; 3], b: [f32; 3]) -> f32 {
+        let dot: f32 = a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+        dot.clamp(-1.0, 1.0).acos().to_degrees()
+    }
+
+    /// Angle of the rotation `orientation` describes, in degrees
+    fn rotation_angle(orientation: Quaternion) -> f32 {
+        2.0 * orientation.w.abs().min(1.0).acos().to_degrees()
+    }
+
+    fn started(filter: FusionFilter, up: [f32; 3]) -> SensorFusion {
+        let mut fusion: SensorFusion = SensorFusion::new();
+        fusion.update(filter, [0.0; 3], up, 0.0);
+        fusion
+    }
+
+    #[test]
+    fn starts_from_accelerometer() {
+        let up: [f32; 3] = [0.0, 0.6, 0.8];
+        for filter in FILTERS {
+            let fusion: SensorFusion = started(filter, up);
+            assert!(angle_between(fusion.gravity(), up) < 0.1, "{filter:?}");
+        }
+    }
+
+    #[test]
+    fn still_gravity_converges_to_accelerometer() {
+        let up: [f32; 3] = [0.5, 0.0, 0.75f32.sqrt()];
+        for filter in FILTERS {
+            let mut fusion: SensorFusion = started(filter, [0.0, 0.0, 1.0]);
+            assert!(angle_between(fusion.gravity(), up) > 29.0);
+            for _ in 0..10_000 {
+                fusion.update(filter, [0.0; 3], up, REPORT_INTERVAL);
+            }
+            assert!(angle_between(fusion.gravity(), up) < 1.0, "{filter:?}: {:?}", fusion.gravity());
+        }
+    }
+
+    #[test]
+    fn constant_rate_integrates_to_angle() {
+        for filter in FILTERS {
+            // Without a usable accelerometer reading there is nothing to correct with
+            let mut fusion: SensorFusion = started(filter, [0.0, 0.0, 1.0]);
+            for _ in 0..250 {
+                fusion.update(filter, [90.0, 0.0, 0.0], [0.0; 3], REPORT_INTERVAL);
+            }
+            let expected: Quaternion = Quaternion::from_axis_angle([1.0, 0.0, 0.0], FRAC_PI_2);
+            assert!(rotation_angle(expected.conjugate() * fusion.orientation()) < 0.5, "{filter:?}");
+            assert!(angle_between(fusion.gravity(), [0.0, 1.0, 0.0]) < 0.5, "{filter:?}");
+
+            // Yaw doesn't move gravity, so the accelerometer agrees and leaves the angle alone
+            let mut fusion: SensorFusion = started(filter, [0.0, 0.0, 1.0]);
+            for _ in 0..250 {
+                fusion.update(filter, [0.0, 0.0, 180.0], [0.0, 0.0, 1.0], REPORT_INTERVAL);
+            }
+            assert!((rotation_angle(fusion.orientation()) - 180.0).abs() < 0.5, "{filter:?}");
+        }
+    }
+
+    #[test]
+    fn filters_agree_on_trace() {
+        // (rate in °/s, seconds) segments of a controller being tilted, turned and put back
+        let trace: [([f32; 3], f32); 6] = [
+            ([0.0, 0.0, 0.0], 0.5),
+            ([60.0, 0.0, 0.0], 0.5),
+            ([0.0, 0.0, 120.0], 1.0),
+            ([0.0, -45.0, 30.0], 1.0),
+            ([-60.0, 45.0, 0.0], 0.5),
+            ([0.0, 0.0, 0.0], 0.5),
+        ];
+        let mut truth: Quaternion = Quaternion::IDENTITY;
+        let mut fusions: [SensorFusion; 2] = FILTERS.map(|filter| started(filter, [0.0, 0.0, 1.0]));
+        for (rate, seconds) in trace {
+            let radians: [f32; 3] = rate.map(f32::to_radians);
+            for _ in 0..(seconds / REPORT_INTERVAL).round() as u32 {
+                if length(radians) > 0.0 {
+                    let axis: [f32; 3] = radians.map(|value| value / length(radians));
+                    truth = (truth * Quaternion::from_axis_angle(axis, length(radians) * REPORT_INTERVAL)).normalized();
+                }
+                let accel: [f32; 3] = truth.conjugate().rotate([0.0, 0.0, 1.0]);
+                for (fusion, filter) in fusions.iter_mut().zip(FILTERS) {
+                    fusion.update(filter, rate, accel, REPORT_INTERVAL);
+                }
+            }
+        }
+
+        let [complementary, madgwick] = fusions.map(|fusion| fusion.gravity());
+        let actual: [f32; 3] = truth.conjugate().rotate([0.0, 0.0, 1.0]);
+        assert!(angle_between(complementary, madgwick) < 2.0, "{complementary:?} vs {madgwick:?}");
+        assert!(angle_between(complementary, actual) < 2.0);
+        assert!(angle_between(madgwick, actual) < 2.0);
+    }
+
+    /// Yaw in degrees after a level report with `first` and a yawing report with `second` as
+    /// sequence number and receive time in ms
+    fn yaw_between(first: (u32, u64), second: (u32, u64)) -> f32 {
+        let mut fusion: SensorFusion = SensorFusion::new();
+        let mut report: InputReport = InputReport::default();
+        report.sequence = first.0;
+        report.timestamp = Duration::from_millis(first.1);
+        report.accel = [0, 0, ACCEL_COUNTS_PER_G];
+        fusion.update_report(FusionFilter::Complementary, &report, [0.0; 3]);
+        report.sequence = second.0;
+        report.timestamp = Duration::from_millis(second.1);
+        report.accel = [0; 3];
+        fusion.update_report(FusionFilter::Complementary, &report, [0.0, 0.0, 1000.0]);
+        rotation_angle(fusion.orientation())
+    }
+
+    #[test]
+    fn steps_by_sequence_numbers() {
+        // Receive times jitter, the sequence says one report passed
+        assert!((yaw_between((7, 0), (8, 10)) - 4.0).abs() < 0.01);
+        assert!((yaw_between((7, 0), (10, 10)) - 12.0).abs() < 0.01);
+        assert!((yaw_between((u32::MAX, 0), (0, 10)) - 4.0).abs() < 0.01);
+        assert!((yaw_between((u32::MAX - 1, 0), (1, 10)) - 12.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn falls_back_to_timestamps_on_gaps() {
+        assert!((yaw_between((0, 0), (MAX_SEQUENCE_GAP + 1, 20)) - 20.0).abs() < 0.01);
+        assert!((yaw_between((u32::MAX - 10, 0), (MAX_SEQUENCE_GAP, 30)) - 30.0).abs() < 0.01);
+        // A repeated or older sequence number isn't a step forward either
+        assert!((yaw_between((5, 0), (5, 8)) - 8.0).abs() < 0.01);
+        assert!((yaw_between((5, 0), (4, 8)) - 8.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::deck::InputReport;
+use std::time::Duration;
+
+/// Largest angular velocity, in °/s, that can still be the controller lying still with some bias
+const MAX_STILL_RATE: f32 = 5.0;
+/// Largest deviation from the recent average, in °/s, that is still just sensor noise
+const MAX_STILL_NOISE: f32 = 0.75;
+/// Largest deviation of the acceleration from its recent average, in g
+const MAX_STILL_ACCEL: f32 = 0.02;
+/// How long the controller has to be still before the bias follows the gyro
+const STILL_TIME: Duration = Duration::from_millis(1000);
+/// Time constant of the recent averages
+const AVERAGE_TIME: f32 = 0.25;
+/// Time constant of the bias while still, slow enough that a slow deliberate turn can't pull it
+const BIAS_TIME: f32 = 2.0;
+
+/// Converts the gyro of every report to °/s and keeps estimating the remaining drift.
+///
+/// The calibration saved by `calibrate gyro` is already applied to the reports, this only picks
+/// up what changed since, e.g. with temperature. Whenever the controller lies still for a moment
+/// the bias slowly follows the measured rate.
+#[derive(Debug, Default)]
+pub struct GyroProcessor {
+    bias: [f32; 3],
+    /// Recent average of the raw rate and the acceleration
+    average_rate: [f32; 3],
+    average_accel: [f32; 3],
+    still_since: Option<Duration>,
+    last_timestamp: Option<Duration>,
+    /// Bias-corrected rate of the last report
+    rate: [f32; 3],
+}
+
+impl GyroProcessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Estimated drift in °/s
+    pub fn bias(&self) -> [f32; 3] {
+        self.bias
+    }
+
+    /// Corrected angular velocity of the last report in °/s
+    pub fn rate(&self) -> [f32; 3] {
+        self.rate
+    }
+
+    pub fn is_still(&self) -> bool {
+        self.still_since.is_some()
+    }
+
+    /// Processes one report and returns its corrected angular velocity in °/s
+    pub fn update(&mut self, report: &InputReport, auto_calibrate: bool) -> [f32; 3] {
+        let rate: [f32; 3] = report.gyro_dps();
+        let accel: [f32; 3] = report.accel_g();
+        let elapsed: f32 = match self.last_timestamp {
+            Some(last) => report.timestamp.saturating_sub(last).as_secs_f32(),
+            None => {
+                self.average_rate = rate;
+                self.average_accel = accel;
+                0.0
+            }
+        };
+        self.last_timestamp = Some(report.timestamp);
+
+        let blend: f32 = (elapsed / AVERAGE_TIME).min(1.0);
+        for axis in 0..3 {
+            self.average_rate[axis] += blend * (rate[axis] - self.average_rate[axis]);
+            self.average_accel[axis] += blend * (accel[axis] - self.average_accel[axis]);
+        }
+
+        let still: bool = (0..3).all(|axis| {
+            (rate[axis] - self.bias[axis]).abs() < MAX_STILL_RATE
+                && (rate[axis] - self.average_rate[axis]).abs() < MAX_STILL_NOISE
+                && (accel[axis] - self.average_accel[axis]).abs() < MAX_STILL_ACCEL
+        });
+        self.still_since = match (still, self.still_since) {
+            (true, Some(since)) => Some(since),
+            (true, None) => Some(report.timestamp),
+            (false, _) => None,
+        };
+
+        if auto_calibrate
+            && let Some(since) = self.still_since
+            && report.timestamp.saturating_sub(since) >= STILL_TIME
+        {
+            let blend: f32 = (elapsed / BIAS_TIME).min(1.0);
+            for axis in 0..3 {
+                self.bias[axis] += blend * (self.average_rate[axis] - self.bias[axis]);
+            }
+        }
+
+        self.rate = [0, 1, 2].map(|axis| rate[axis] - self.bias[axis]);
+        self.rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Time between reports
+    const PERIOD: Duration = Duration::from_millis(4);
+
+    /// Feeds `seconds` worth of reports with the rate `dps` around X and gravity along Z scaled by
+    /// `accel(index)`
+    fn feed(gyro: &mut GyroProcessor, from: Duration, seconds: f32, dps: f32, accel: impl Fn(u32) -> f32) -> Duration {
+        let count: u32 = (seconds / PERIOD.as_secs_f32()) as u32;
+        for index in 0..count {
+            let mut report: InputReport = InputReport::default();
+            report.timestamp = from + PERIOD * index;
+            report.gyro = [(dps * 32768.0 / 2000.0).round() as i16, 0, 0];
+            report.accel = [0, 0, (accel(index) * 16384.0) as i16];
+            gyro.update(&report, true);
+        }
+        from + PERIOD * count
+    }
+
+    #[test]
+    fn bias_follows_the_rate_while_still() {
+        let mut gyro: GyroProcessor = GyroProcessor::new();
+        let now: Duration = feed(&mut gyro, Duration::ZERO, 0.5, 2.0, |_| 1.0);
+        assert!(gyro.is_still());
+        // Not still for long enough yet
+        assert_eq!(gyro.bias(), [0.0; 3]);
+
+        feed(&mut gyro, now, 12.0, 2.0, |_| 1.0);
+        let bias: f32 = gyro.bias()[0];
+        assert!((bias - 2.0).abs() < 0.05, "{bias}");
+        assert!(gyro.rate()[0].abs() < 0.05, "{:?}", gyro.rate());
+    }
+
+    #[test]
+    fn bias_stays_without_auto_calibration() {
+        let mut gyro: GyroProcessor = GyroProcessor::new();
+        let mut report: InputReport = InputReport::default();
+        report.gyro = [33, 0, 0];
+        report.accel = [0, 0, 16384];
+        for index in 0..1000 {
+            report.timestamp = PERIOD * index;
+            gyro.update(&report, false);
+        }
+        assert!(gyro.is_still());
+        assert_eq!(gyro.bias(), [0.0; 3]);
+    }
+
+    #[test]
+    fn turning_is_not_rest() {
+        let mut gyro: GyroProcessor = GyroProcessor::new();
+        feed(&mut gyro, Duration::ZERO, 3.0, 90.0, |_| 1.0);
+        assert!(!gyro.is_still());
+        assert_eq!(gyro.bias(), [0.0; 3]);
+        assert!((gyro.rate()[0] - 90.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn noise_or_shaking_is_not_rest() {
+        let mut gyro: GyroProcessor = GyroProcessor::new();
+        // Jittering by more than sensor noise
+        let mut now: Duration = Duration::ZERO;
+        for _ in 0..200 {
+            now = feed(&mut gyro, now, 0.008, 2.0, |_| 1.0);
+            now = feed(&mut gyro, now, 0.008, -2.0, |_| 1.0);
+            assert!(!gyro.is_still());
+        }
+        assert_eq!(gyro.bias(), [0.0; 3]);
+
+        // Bumped while the gyro reads a steady rate
+        let mut gyro: GyroProcessor = GyroProcessor::new();
+        feed(&mut gyro, Duration::ZERO, 3.0, 1.0, |index| if index % 4 < 2 { 1.0 } else { 1.1 });
+        assert!(!gyro.is_still());
+        assert_eq!(gyro.bias(), [0.0; 3]);
+    }
+}
@@ -0,0 +1,20 @@
+mod aim;
+mod curve;
+mod flick;
+mod fusion;
+mod gyro;
+mod stick;
+mod tilt;
+mod touch;
+mod trackpad;
+mod trigger;
+
+pub use self::aim::GyroAim;
+pub use self::flick::FlickStick;
+pub use self::fusion::{Quaternion, SensorFusion};
+pub use self::gyro::GyroProcessor;
+pub use self::stick::process_stick;
+pub use self::tilt::{tilt_angles, tilt_output};
+pub use self::touch::TouchDebouncer;
+pub use self::trackpad::{TrackpadMouse, rotate_clockwise};
+pub use self::trigger::{TriggerOutput, TriggerProcessor};
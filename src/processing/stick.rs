@@ -0,0 +1,154 @@
+use crate::profile::StickSettings;
+
+/// Shapes a normalized stick position (`-1.0..=1.0` on both axes) according to `settings`.
+///
+/// Axial deadzones apply to each axis on its own first, then the radial deadzones, the curve and
+/// the anti-deadzone apply to the distance from the center so the direction is kept. Inversion
+/// comes last.
+pub fn process_stick(settings: &StickSettings, x: f32, y: f32) -> (f32, f32) {
+    let x: f32 = rescale(x.clamp(-1.0, 1.0), settings.axial_deadzone, settings.outer_axial_deadzone);
+    let y: f32 = rescale(y.clamp(-1.0, 1.0), settings.axial_deadzone, settings.outer_axial_deadzone);
+
+    let magnitude: f32 = x.hypot(y);
+    let scaled: f32 = rescale(magnitude.min(1.0), settings.deadzone, settings.outer_deadzone);
+    if scaled == 0.0 {
+        return (0.0, 0.0);
+    }
+    let shaped: f32 = settings.curve.apply(scaled);
+    // Jumps past the game's own deadzone as soon as the stick leaves ours
+    let shaped: f32 = settings.anti_deadzone + (1.0 - settings.anti_deadzone) * shaped;
+
+    let factor: f32 = shaped / magnitude;
+    let x: f32 = (x * factor).clamp(-1.0, 1.0);
+    let y: f32 = (y * factor).clamp(-1.0, 1.0);
+    (
+        if settings.invert_x { -x } else { x },
+        if settings.invert_y { -y } else { y },
+    )
+}
+
+/// Maps `|value|` from `inner..=1.0 - outer` to `0.0..=1.0`, keeping the sign. Values inside the
+/// inner deadzone become `0.0`, values inside the outer one become `±1.0`.
+fn rescale(value: f32, inner: f32, outer: f32) -> f32 {
+    let live: f32 = 1.0 - inner - outer;
+    if value.abs() <= inner {
+        return 0.0;
+    }
+    if live <= 0.0 {
+        return value.signum();
+    }
+    value.signum() * ((value.abs() - inner) / live).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::Curve;
+
+    /// Settings without any deadzone, curve or inversion
+    fn unshaped() -> StickSettings {
+        StickSettings {
+            deadzone: 0.0,
+            outer_deadzone: 0.0,
+            ..StickSettings::default()
+        }
+    }
+
+    fn assert_close((x, y): (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (x - expected.0).abs() < 1e-5 && (y - expected.1).abs() < 1e-5,
+            "{:?} != {expected:?}",
+            (x, y)
+        );
+    }
+
+    #[test]
+    fn passes_through_without_shaping() {
+        assert_close(process_stick(&unshaped(), 0.3, -0.4), (0.3, -0.4));
+        assert_close(process_stick(&unshaped(), 0.0, 0.0), (0.0, 0.0));
+        assert_close(process_stick(&unshaped(), 2.0, 0.0), (1.0, 0.0));
+    }
+
+    #[test]
+    fn radial_deadzone_keeps_direction() {
+        let settings: StickSettings = StickSettings {
+            deadzone: 0.1,
+            ..unshaped()
+        };
+        assert_close(process_stick(&settings, 0.06, 0.08), (0.0, 0.0));
+        // 0.5 from the center is (0.5 - 0.1) / 0.9 of the live range
+        let scaled: f32 = 0.4 / 0.9;
+        assert_close(process_stick(&settings, 0.3, 0.4), (0.6 * scaled, 0.8 * scaled));
+    }
+
+    #[test]
+    fn axial_deadzone_applies_before_radial() {
+        let settings: StickSettings = StickSettings {
+            deadzone: 0.1,
+            axial_deadzone: 0.2,
+            ..unshaped()
+        };
+        // X drops out on its own axis first, then Y goes through both deadzones
+        let y: f32 = ((0.9 - 0.2) / 0.8 - 0.1) / 0.9;
+        assert_close(process_stick(&settings, 0.1, 0.9), (0.0, y));
+        assert_close(process_stick(&settings, -0.1, -0.9), (0.0, -y));
+    }
+
+    #[test]
+    fn outer_deadzone_saturates() {
+        let settings: StickSettings = StickSettings {
+            outer_deadzone: 0.2,
+            ..unshaped()
+        };
+        assert_close(process_stick(&settings, 0.8, 0.0), (1.0, 0.0));
+        assert_close(process_stick(&settings, 0.0, -0.95), (0.0, -1.0));
+        let diagonal: f32 = std::f32::consts::FRAC_1_SQRT_2;
+        assert_close(process_stick(&settings, 0.6, 0.6), (diagonal, diagonal));
+        assert_close(process_stick(&settings, 0.4, 0.0), (0.5, 0.0));
+
+        let settings: StickSettings = StickSettings {
+            outer_axial_deadzone: 0.2,
+            ..settings
+        };
+        assert_close(process_stick(&settings, 0.8, 0.0), (1.0, 0.0));
+    }
+
+    #[test]
+    fn anti_deadzone_starts_above_zero() {
+        let settings: StickSettings = StickSettings {
+            deadzone: 0.1,
+            anti_deadzone: 0.25,
+            ..unshaped()
+        };
+        assert_close(process_stick(&settings, 0.05, 0.0), (0.0, 0.0));
+        assert_close(process_stick(&settings, 0.100_001, 0.0), (0.25, 0.0));
+        assert_close(process_stick(&settings, 0.55, 0.0), (0.25 + 0.75 * 0.5, 0.0));
+        assert_close(process_stick(&settings, 0.0, -1.0), (0.0, -1.0));
+    }
+
+    #[test]
+    fn curve_shapes_magnitude() {
+        let settings: StickSettings = StickSettings {
+            curve: Curve::Exponential { exponent: 2.0 },
+            ..unshaped()
+        };
+        assert_close(process_stick(&settings, 0.5, 0.0), (0.25, 0.0));
+        // The curve applies to the distance, not to each axis
+        assert_close(process_stick(&settings, 0.3, 0.4), (0.15, 0.2));
+    }
+
+    #[test]
+    fn inverts_last() {
+        let settings: StickSettings = StickSettings {
+            anti_deadzone: 0.5,
+            invert_x: true,
+            ..unshaped()
+        };
+        assert_close(process_stick(&settings, 0.5, 0.0), (-0.75, 0.0));
+        let settings: StickSettings = StickSettings {
+            invert_y: true,
+            ..unshaped()
+        };
+        assert_close(process_stick(&settings, 0.3, 0.4), (0.3, -0.4));
+    }
+}
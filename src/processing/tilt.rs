@@ -0,0 +1,102 @@
+use crate::profile::TiltAxis;
+
+/// Roll and pitch in degrees between two up vectors in controller coordinates, e.g. from
+/// [`crate::processing::SensorFusion::gravity`]. Positive roll leans the right side down, positive
+/// pitch tilts the top of the controller towards the player.
+pub fn tilt_angles(gravity: [f32; 3], center: [f32; 3]) -> (f32, f32) {
+    let roll = |up: [f32; 3]| (-up[0]).atan2(up[1].hypot(up[2]));
+    let pitch = |up: [f32; 3]| up[1].atan2(up[2]);
+    let pitch: f32 = (pitch(gravity) - pitch(center)).to_degrees();
+    (
+        (roll(gravity) - roll(center)).to_degrees(),
+        (pitch + 180.0).rem_euclid(360.0) - 180.0,
+    )
+}
+
+/// Value `angle` pushes the output of `axis` to, signed for stick axes and `0.0..=1.0` for
+/// triggers
+pub fn tilt_output(axis: &TiltAxis, angle: f32) -> f32 {
+    let angle: f32 = if axis.invert { -angle } else { angle };
+    if axis.output.is_trigger() && angle <= 0.0 {
+        return 0.0;
+    }
+    let travel: f32 = (angle.abs() - axis.deadzone) / (axis.max_angle - axis.deadzone);
+    if travel <= 0.0 {
+        return 0.0;
+    }
+    axis.curve.apply(travel).copysign(angle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::GamepadAxis;
+    use crate::profile::Curve;
+
+    fn axis(output: GamepadAxis, deadzone: f32) -> TiltAxis {
+        TiltAxis {
+            output,
+            max_angle: 30.0,
+            deadzone,
+            curve: Curve::Linear,
+            invert: false,
+        }
+    }
+
+    /// Up vector of a controller lying flat, then rolled by `roll` and pitched by `pitch` degrees
+    fn up(roll: f32, pitch: f32) -> [f32; 3] {
+        let (roll_sin, roll_cos) = roll.to_radians().sin_cos();
+        let (pitch_sin, pitch_cos) = pitch.to_radians().sin_cos();
+        [-roll_sin, roll_cos * pitch_sin, roll_cos * pitch_cos]
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-3, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn angles_are_relative_to_the_center() {
+        assert_eq!(tilt_angles(up(0.0, 0.0), up(0.0, 0.0)), (0.0, 0.0));
+        let (roll, pitch) = tilt_angles(up(20.0, -35.0), up(20.0, -35.0));
+        assert_close(roll, 0.0);
+        assert_close(pitch, 0.0);
+
+        let (roll, pitch) = tilt_angles(up(15.0, 0.0), up(0.0, 0.0));
+        assert_close(roll, 15.0);
+        assert_close(pitch, 0.0);
+        let (roll, pitch) = tilt_angles(up(0.0, 10.0), up(0.0, -20.0));
+        assert_close(roll, 0.0);
+        assert_close(pitch, 30.0);
+        // Pitching across upside down stays the short way round
+        let (_, pitch) = tilt_angles(up(0.0, -170.0), up(0.0, 170.0));
+        assert_close(pitch, 20.0);
+    }
+
+    #[test]
+    fn output_saturates_at_the_max_angle() {
+        let stick: TiltAxis = axis(GamepadAxis::LeftStickX, 0.0);
+        assert_close(tilt_output(&stick, 15.0), 0.5);
+        assert_close(tilt_output(&stick, -15.0), -0.5);
+        assert_eq!(tilt_output(&stick, 60.0), 1.0);
+        assert_eq!(tilt_output(&stick, -60.0), -1.0);
+
+        let inverted: TiltAxis = TiltAxis { invert: true, ..stick };
+        assert_close(tilt_output(&inverted, 15.0), -0.5);
+
+        // Triggers only follow tilting one way
+        let trigger: TiltAxis = axis(GamepadAxis::RightTrigger, 0.0);
+        assert_eq!(tilt_output(&trigger, 45.0), 1.0);
+        assert_eq!(tilt_output(&trigger, -45.0), 0.0);
+    }
+
+    #[test]
+    fn deadzone_is_ignored_and_scaled_out() {
+        let stick: TiltAxis = axis(GamepadAxis::LeftStickY, 10.0);
+        assert_eq!(tilt_output(&stick, 0.0), 0.0);
+        assert_eq!(tilt_output(&stick, 10.0), 0.0);
+        assert_eq!(tilt_output(&stick, -9.0), 0.0);
+        assert_close(tilt_output(&stick, 20.0), 0.5);
+        assert_close(tilt_output(&stick, -20.0), -0.5);
+        assert_eq!(tilt_output(&stick, 30.0), 1.0);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::deck::{DeckButton, InputReport};
+use std::time::Duration;
+
+/// Debounced state of one touch sensor
+#[derive(Debug, Default, Clone, Copy)]
+struct TouchState {
+    touched: bool,
+    /// When the raw state started to differ from `touched`
+    changing_since: Option<Duration>,
+}
+
+/// Hides the flicker of the capacitive touch sensors, a change only goes through once the raw
+/// state held it for the debounce time.
+///
+/// Applies to the touch buttons and the `touched` flag of the sticks. Trackpad modes keep the raw
+/// `touched` flag of the pads since the finger position is only valid while it is set.
+#[derive(Debug, Default)]
+pub struct TouchDebouncer {
+    /// Indexed like [`DeckButton::TOUCH`]
+    states: [TouchState; 4],
+}
+
+impl TouchDebouncer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the touch state of `report` with the debounced one
+    pub fn apply(&mut self, report: &mut InputReport, debounce: Duration) {
+        let now: Duration = report.timestamp;
+        for (state, button) in self.states.iter_mut().zip(DeckButton::TOUCH) {
+            let raw: bool = report.is_pressed(button);
+            if raw == state.touched {
+                state.changing_since = None;
+            } else {
+                let since: Duration = *state.changing_since.get_or_insert(now);
+                if now.saturating_sub(since) >= debounce {
+                    state.touched = raw;
+                    state.changing_since = None;
+                }
+            }
+            report.set_button(button, state.touched);
+        }
+        report.left_stick.touched = report.is_pressed(DeckButton::LeftStickTouch);
+        report.right_stick.touched = report.is_pressed(DeckButton::RightStickTouch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEBOUNCE: Duration = Duration::from_millis(20);
+
+    /// Debounced state of the left stick touch for a report at `ms` with the raw state `touched`
+    fn update(debouncer: &mut TouchDebouncer, ms: u64, touched: bool) -> bool {
+        let mut report: InputReport = InputReport::default();
+        report.timestamp = Duration::from_millis(ms);
+        report.set_button(DeckButton::LeftStickTouch, touched);
+        debouncer.apply(&mut report, DEBOUNCE);
+        assert_eq!(report.left_stick.touched, report.is_pressed(DeckButton::LeftStickTouch));
+        report.left_stick.touched
+    }
+
+    #[test]
+    fn touches_go_through_once_held_for_the_debounce_time() {
+        let mut debouncer: TouchDebouncer = TouchDebouncer::new();
+        assert!(!update(&mut debouncer, 0, true));
+        assert!(!update(&mut debouncer, 19, true));
+        assert!(update(&mut debouncer, 20, true));
+
+        assert!(update(&mut debouncer, 30, false));
+        assert!(update(&mut debouncer, 49, false));
+        assert!(!update(&mut debouncer, 50, false));
+    }
+
+    #[test]
+    fn flicker_shorter_than_the_debounce_time_is_hidden() {
+        let mut debouncer: TouchDebouncer = TouchDebouncer::new();
+        assert!(!update(&mut debouncer, 0, true));
+        assert!(!update(&mut debouncer, 15, false));
+        // The flicker restarted the wait
+        assert!(!update(&mut debouncer, 20, true));
+        assert!(!update(&mut debouncer, 35, true));
+        assert!(update(&mut debouncer, 40, true));
+
+        assert!(update(&mut debouncer, 50, false));
+        assert!(update(&mut debouncer, 60, true));
+        assert!(update(&mut debouncer, 80, false));
+        assert!(update(&mut debouncer, 99, false));
+    }
+
+    #[test]
+    fn sensors_are_debounced_separately() {
+        let mut debouncer: TouchDebouncer = TouchDebouncer::new();
+        let mut report: InputReport = InputReport::default();
+        report.set_button(DeckButton::RightPadTouch, true);
+        debouncer.apply(&mut report, DEBOUNCE);
+
+        let mut report: InputReport = InputReport::default();
+        report.timestamp = DEBOUNCE;
+        report.set_button(DeckButton::RightPadTouch, true);
+        report.set_button(DeckButton::RightStickTouch, true);
+        debouncer.apply(&mut report, DEBOUNCE);
+        assert!(report.is_pressed(DeckButton::RightPadTouch));
+        assert!(!report.is_pressed(DeckButton::RightStickTouch));
+        assert!(!report.right_stick.touched);
+    }
+
+    #[test]
+    fn no_debounce_time_passes_changes_straight_through() {
+        let mut debouncer: TouchDebouncer = TouchDebouncer::new();
+        let mut report: InputReport = InputReport::default();
+        report.set_button(DeckButton::LeftPadTouch, true);
+        debouncer.apply(&mut report, Duration::ZERO);
+        assert!(report.is_pressed(DeckButton::LeftPadTouch));
+    }
+}
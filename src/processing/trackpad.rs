@@ -0,0 +1,162 @@
+use crate::deck::Trackpad;
+use crate::profile::TrackpadSettings;
+use std::time::Duration;
+
+/// Pixels moved per normalized unit at sensitivity 1.0, a pad is 2.0 units wide
+const PIXELS_PER_UNIT: f32 = 300.0;
+/// Gliding stops below this speed, in pixels per second
+const MIN_GLIDE_SPEED: f32 = 20.0;
+/// Weight of the newest sample in the smoothed finger velocity, keeps a single noisy report at
+/// lift-off from deciding the glide
+const VELOCITY_SMOOTHING: f32 = 0.5;
+
+/// Turns finger motion on one trackpad into relative pointer motion
+#[derive(Debug, Default)]
+pub struct TrackpadMouse {
+    /// Finger position of the previous report, `None` while the pad isn't touched
+    last_position: Option<(f32, f32)>,
+    last_timestamp: Option<Duration>,
+    /// Pointer velocity in pixels per second, positive Y is down
+    velocity: (f32, f32),
+    /// Sub-pixel motion carried over to the next report
+    remainder: (f32, f32),
+}
+
+impl TrackpadMouse {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Processes the pad state of one report and returns the pointer motion in whole pixels
+    pub fn update(&mut self, settings: &TrackpadSettings, pad: &Trackpad, now: Duration) -> (i32, i32) {
+        let elapsed: f32 = self
+            .last_timestamp
+            .map(|last| now.saturating_sub(last).as_secs_f32())
+            .unwrap_or(0.0);
+        self.last_timestamp = Some(now);
+
+        let motion: (f32, f32) = if pad.touched {
+            let position: (f32, f32) = rotate_clockwise(pad.normalized(), settings.rotation);
+            let motion: (f32, f32) = match self.last_position {
+                Some(last) => self.track(settings, (position.0 - last.0, position.1 - last.1), elapsed),
+                // Touching down stops a glide
+                None => {
+                    self.velocity = (0.0, 0.0);
+                    (0.0, 0.0)
+                }
+            };
+            self.last_position = Some(position);
+            motion
+        } else {
+            self.last_position = None;
+            self.glide(settings, elapsed)
+        };
+
+        let x: f32 = self.remainder.0 + motion.0;
+        let y: f32 = self.remainder.1 + motion.1;
+        self.remainder = (x.fract(), y.fract());
+        (x.trunc() as i32, y.trunc() as i32)
+    }
+
+    /// Motion of a finger on the pad, `delta` is in normalized units with positive Y up
+    fn track(&mut self, settings: &TrackpadSettings, delta: (f32, f32), elapsed: f32) -> (f32, f32) {
+        let distance: f32 = delta.0.hypot(delta.1);
+        let mut gain: f32 = settings.sensitivity * PIXELS_PER_UNIT;
+        if elapsed > 0.0 {
+            // Pad widths per second
+            let speed: f32 = distance / 2.0 / elapsed;
+            gain *= 1.0 + settings.acceleration * speed;
+        }
+        let motion: (f32, f32) = (delta.0 * gain, -delta.1 * gain);
+
+        if elapsed > 0.0 {
+            let velocity: (f32, f32) = (motion.0 / elapsed, motion.1 / elapsed);
+            self.velocity = (
+                self.velocity.0 + VELOCITY_SMOOTHING * (velocity.0 - self.velocity.0),
+                self.velocity.1 + VELOCITY_SMOOTHING * (velocity.1 - self.velocity.1),
+            );
+        }
+        motion
+    }
+
+    /// Motion after the finger lifted off, slowing down by `friction`
+    fn glide(&mut self, settings: &TrackpadSettings, elapsed: f32) -> (f32, f32) {
+        let speed: f32 = self.velocity.0.hypot(self.velocity.1);
+        if !settings.inertia || speed < MIN_GLIDE_SPEED {
+            self.velocity = (0.0, 0.0);
+            return (0.0, 0.0);
+        }
+        let motion: (f32, f32) = (self.velocity.0 * elapsed, self.velocity.1 * elapsed);
+        let decay: f32 = (1.0 - settings.friction).powf(elapsed);
+        self.velocity = (self.velocity.0 * decay, self.velocity.1 * decay);
+        motion
+    }
+}
+
+/// Rotates a position around the center of the pad
+pub fn rotate_clockwise((x, y): (f32, f32), degrees: f32) -> (f32, f32) {
+    if degrees == 0.0 {
+        return (x, y);
+    }
+    let (sin, cos) = (-degrees.to_radians()).sin_cos();
+    (x * cos - y * sin, x * sin + y * cos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(x: i16) -> Trackpad {
+        Trackpad {
+            x,
+            touched: true,
+            ..Trackpad::default()
+        }
+    }
+
+    /// Swipes right across the pad and lifts off, returns when the finger left
+    fn swipe(mouse: &mut TrackpadMouse, settings: &TrackpadSettings) -> u64 {
+        for (ms, x) in [(0, -8000), (4, -4000), (8, 0), (12, 4000)] {
+            mouse.update(settings, &touch(x), Duration::from_millis(ms));
+        }
+        12
+    }
+
+    #[test]
+    fn glide_slows_down_and_stops() {
+        let settings: TrackpadSettings = TrackpadSettings {
+            friction: 0.9,
+            ..TrackpadSettings::default()
+        };
+        let mut mouse: TrackpadMouse = TrackpadMouse::new();
+        let mut ms: u64 = swipe(&mut mouse, &settings);
+
+        let mut speeds: Vec<f32> = vec![mouse.velocity.0.hypot(mouse.velocity.1)];
+        let mut glided: i32 = 0;
+        while mouse.velocity != (0.0, 0.0) && ms < 10_000 {
+            ms += 4;
+            let (x, y) = mouse.update(&settings, &Trackpad::default(), Duration::from_millis(ms));
+            assert_eq!(y, 0);
+            glided += x;
+            speeds.push(mouse.velocity.0.hypot(mouse.velocity.1));
+        }
+        assert!(glided > 0, "the glide keeps moving right");
+        assert!(speeds.windows(2).all(|pair| pair[1] < pair[0]), "the glide didn't slow down");
+        // It stops on the first report slower than the minimum glide speed
+        assert_eq!(mouse.velocity, (0.0, 0.0), "the glide didn't stop");
+        assert!(speeds[speeds.len() - 2] < MIN_GLIDE_SPEED);
+        assert!(speeds[speeds.len() - 3] >= MIN_GLIDE_SPEED);
+        assert_eq!(mouse.update(&settings, &Trackpad::default(), Duration::from_millis(ms + 4)), (0, 0));
+    }
+
+    #[test]
+    fn no_glide_without_inertia() {
+        let settings: TrackpadSettings = TrackpadSettings {
+            inertia: false,
+            ..TrackpadSettings::default()
+        };
+        let mut mouse: TrackpadMouse = TrackpadMouse::new();
+        let ms: u64 = swipe(&mut mouse, &settings);
+        assert_eq!(mouse.update(&settings, &Trackpad::default(), Duration::from_millis(ms + 4)), (0, 0));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::profile::TriggerSettings;
+
+/// Travel a hair trigger has to move in or out before it changes state, keeps sensor noise from
+/// toggling it
+const HAIR_TRIGGER_STEP: f32 = 0.02;
+/// How far below its threshold a pull has to drop before it releases, keeps a trigger resting
+/// right at a threshold from chattering
+const PULL_HYSTERESIS: f32 = 0.02;
+
+/// What one trigger produces for a report
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TriggerOutput {
+    /// Analog output, `0.0..=1.0`
+    pub value: f32,
+    pub soft_pull: bool,
+    pub full_pull: bool,
+}
+
+/// Shapes the travel of one analog trigger and decides its soft and full pull
+#[derive(Debug, Default)]
+pub struct TriggerProcessor {
+    /// Hair trigger state, whether it is active and the furthest travel in the current direction:
+    /// the deepest point while active and the shallowest one while not
+    hair_active: bool,
+    hair_extreme: f32,
+    soft_pull: bool,
+    full_pull: bool,
+}
+
+impl TriggerProcessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Processes the trigger travel of one report, `0.0..=1.0`
+    pub fn update(&mut self, settings: &TriggerSettings, travel: f32) -> TriggerOutput {
+        let range: f32 = 1.0 - settings.deadzone - settings.outer_deadzone;
+        let shaped: f32 = ((travel - settings.deadzone) / range).clamp(0.0, 1.0);
+        let value: f32 = if shaped > 0.0 {
+            settings.output_min + (settings.output_max - settings.output_min) * settings.curve.apply(shaped)
+        } else {
+            0.0
+        };
+
+        let full_pull: bool = pulled(&mut self.full_pull, travel, settings.full_pull_threshold);
+        let soft_pull: bool = if settings.hair_trigger {
+            self.hair_trigger(settings, travel)
+        } else {
+            pulled(&mut self.soft_pull, travel, settings.threshold)
+        };
+        TriggerOutput {
+            value,
+            soft_pull: soft_pull && !(settings.exclusive && full_pull),
+            full_pull,
+        }
+    }
+
+    fn hair_trigger(&mut self, settings: &TriggerSettings, travel: f32) -> bool {
+        if travel <= settings.deadzone {
+            self.hair_active = false;
+            self.hair_extreme = travel;
+        } else if self.hair_active {
+            self.hair_extreme = self.hair_extreme.max(travel);
+            if travel < self.hair_extreme - HAIR_TRIGGER_STEP {
+                self.hair_active = false;
+                self.hair_extreme = travel;
+            }
+        } else {
+            self.hair_extreme = self.hair_extreme.min(travel);
+            if travel > self.hair_extreme + HAIR_TRIGGER_STEP {
+                self.hair_active = true;
+                self.hair_extreme = travel;
+            }
+        }
+        self.hair_active
+    }
+}
+
+/// Whether a pull is held, pressing at `threshold` and releasing [`PULL_HYSTERESIS`] below it
+fn pulled(active: &mut bool, travel: f32, threshold: f32) -> bool {
+    *active = travel >= threshold || (*active && travel > threshold - PULL_HYSTERESIS);
+    *active
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Soft and full pull of every travel in turn
+    fn pulls(settings: &TriggerSettings, travels: &[f32]) -> Vec<(bool, bool)> {
+        let mut trigger: TriggerProcessor = TriggerProcessor::new();
+        travels
+            .iter()
+            .map(|travel| {
+                let output: TriggerOutput = trigger.update(settings, *travel);
+                (output.soft_pull, output.full_pull)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dual_stage_pulls_at_their_thresholds() {
+        let settings: TriggerSettings = TriggerSettings::default();
+        assert_eq!(
+            pulls(&settings, &[0.3, 0.5, 0.95, 0.0]),
+            [(false, false), (true, false), (true, true), (false, false)]
+        );
+
+        let exclusive: TriggerSettings = TriggerSettings {
+            exclusive: true,
+            ..TriggerSettings::default()
+        };
+        assert_eq!(pulls(&exclusive, &[0.5, 0.95, 0.6]), [(true, false), (false, true), (true, false)]);
+    }
+
+    #[test]
+    fn pulls_release_below_their_threshold() {
+        let settings: TriggerSettings = TriggerSettings::default();
+        // Resting around a threshold keeps the pull, dropping clearly below it releases
+        assert_eq!(
+            pulls(&settings, &[0.5, 0.49, 0.5, 0.47, 0.49]),
+            [(true, false), (true, false), (true, false), (false, false), (false, false)]
+        );
+        assert_eq!(
+            pulls(&settings, &[0.95, 0.94, 0.92, 0.94]),
+            [(true, true), (true, true), (true, false), (true, false)]
+        );
+    }
+
+    #[test]
+    fn hair_trigger_rearms_on_direction_changes() {
+        let settings: TriggerSettings = TriggerSettings {
+            hair_trigger: true,
+            deadzone: 0.05,
+            ..TriggerSettings::default()
+        };
+        let soft: Vec<bool> = pulls(&settings, &[0.1, 0.3, 0.29, 0.25, 0.26, 0.28, 0.6, 0.04])
+            .into_iter()
+            .map(|(soft, _)| soft)
+            .collect();
+        // Pressing in activates it wherever the trigger is, easing out by more than the noise
+        // step releases it, and pressing back in activates it again without a full release
+        assert_eq!(soft, [true, true, true, false, false, true, true, false]);
+    }
+
+    #[test]
+    fn shapes_the_analog_value() {
+        let settings: TriggerSettings = TriggerSettings {
+            deadzone: 0.1,
+            outer_deadzone: 0.1,
+            output_min: 0.2,
+            ..TriggerSettings::default()
+        };
+        let mut trigger: TriggerProcessor = TriggerProcessor::new();
+        assert_eq!(trigger.update(&settings, 0.05).value, 0.0);
+        assert!((trigger.update(&settings, 0.5).value - 0.6).abs() < 1e-6);
+        assert_eq!(trigger.update(&settings, 0.95).value, 1.0);
+    }
+}
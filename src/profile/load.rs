@@ -0,0 +1,135 @@
+use crate::profile::Profile;
+use crate::profile::validate::{self, SchemaError};
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
+use std::{error::Error, fmt, fs, io};
+use toml_edit::{ImDocument, Item};
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io { path: PathBuf, source: io::Error },
+    /// The profile is not valid TOML or doesn't match the schema
+    Invalid {
+        path: Option<PathBuf>,
+        /// 1-based
+        line: usize,
+        /// 1-based, counted in characters
+        column: usize,
+        message: String,
+        /// The offending line, used to point at the error
+        source_line: String,
+    },
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            ProfileError::Invalid {
+                path: Some(path),
+                line,
+                column,
+                message,
+                ..
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            ProfileError::Invalid {
+                path: None,
+                line,
+                column,
+                message,
+                ..
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+        }
+    }
+}
+
+impl Error for ProfileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProfileError::Io { source, .. } => Some(source),
+            ProfileError::Invalid { .. } => None,
+        }
+    }
+}
+
+/// Reads and validates a profile, its name defaults to the file name
+pub fn load(path: &Path) -> Result<Profile, ProfileError> {
+    let mut profile: Profile = parse_profile(&read_text(path)?, Some(path))?;
+    if profile.name.is_empty() {
+        profile.name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+    }
+    Ok(profile)
+}
+
+/// Validates a profile held in memory
+pub fn parse(text: &str) -> Result<Profile, ProfileError> {
+    parse_profile(text, None)
+}
+
+pub(crate) fn parse_profile(text: &str, path: Option<&Path>) -> Result<Profile, ProfileError> {
+    let profile: Profile = from_toml(text, path)?;
+    validate::check(&profile)
+        .map_err(|err: SchemaError| invalid_at(text, key_offset(text, &err.key_path), path, &err.message))?;
+    Ok(profile)
+}
+
+pub(crate) fn read_text(path: &Path) -> Result<String, ProfileError> {
+    fs::read_to_string(path).map_err(|source: io::Error| ProfileError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Deserializes any TOML document, locating errors the same way as for profiles
+pub(crate) fn from_toml<T: DeserializeOwned>(text: &str, path: Option<&Path>) -> Result<T, ProfileError> {
+    toml::from_str(text).map_err(|err: toml::de::Error| {
+        let offset: usize = err.span().map(|span| span.start).unwrap_or(0);
+        invalid_at(text, offset, path, err.message().trim_end())
+    })
+}
+
+/// Builds an error pointing at a byte offset of `text`, for checks that run after deserializing
+pub(crate) fn invalid_at(text: &str, offset: usize, path: Option<&Path>, message: &str) -> ProfileError {
+    let (line, column, source_line) = locate(text, offset);
+    ProfileError::Invalid {
+        path: path.map(Path::to_path_buf),
+        line,
+        column,
+        message: message.to_string(),
+        source_line,
+    }
+}
+
+/// Byte offset of the value at the end of `key_path`, or of the deepest key along it that exists
+fn key_offset(text: &str, key_path: &[String]) -> usize {
+    let Ok(document) = ImDocument::parse(text) else {
+        return 0;
+    };
+
+    let mut item: &Item = document.as_item();
+    let mut offset: usize = 0;
+    for key in key_path {
+        match item.get(key.as_str()) {
+            Some(child) => {
+                item = child;
+                offset = item.span().map(|span| span.start).unwrap_or(offset);
+            }
+            None => break,
+        }
+    }
+    offset
+}
+
+/// Turns a byte offset into a 1-based line and column plus the text of that line
+fn locate(text: &str, offset: usize) -> (usize, usize, String) {
+    let offset: usize = offset.min(text.len());
+    let line_start: usize = text[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end: usize = text[offset..].find('\n').map(|i| offset + i).unwrap_or(text.len());
+
+    let line: usize = text[..offset].matches('\n').count() + 1;
+    let column: usize = text[line_start..offset].chars().count() + 1;
+    (line, column, text[line_start..line_end].trim_end_matches('\r').to_string())
+}
@@ -0,0 +1,16 @@
+mod load;
+mod save;
+mod schema;
+mod validate;
+mod watch;
+
+pub(crate) use self::load::{from_toml, invalid_at, read_text};
+pub use self::load::{ProfileError, load, parse};
+pub use self::save::save_macro;
+pub use self::schema::{
+    Curve, DpadSettings, FlickSettings, FusionFilter, GridSettings, GyroActivation, GyroMode, GyroSettings, GyroSpace,
+    HapticSettings, JoystickSettings, Layer, LizardMode, Menu, MenuSelect, PadActivation, Profile, ScrollWheelSettings,
+    Side, StickMode, StickSettings, Sticks, TiltAxis, TiltSettings, TrackpadMode, TrackpadSettings, Trackpads,
+    TriggerMode, TriggerSettings, Triggers,
+};
+pub use self::watch::ProfileWatcher;
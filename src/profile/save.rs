@@ -0,0 +1,65 @@
+use crate::mapping::{DEFAULT_TAP_HOLD, Macro, MacroStep};
+use crate::profile::ProfileError;
+use crate::profile::load::{invalid_at, parse_profile, read_text};
+use std::path::Path;
+use std::{fs, io};
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Table, TomlError, Value};
+
+/// Writes `macro_` to `[macros.<name>]` of the profile at `path`, replacing a macro with the same
+/// name. The rest of the file, comments and formatting included, is left as it is.
+pub fn save_macro(path: &Path, name: &str, macro_: &Macro) -> Result<(), ProfileError> {
+    let text: String = read_text(path)?;
+    // Refuse to touch a profile that is already broken
+    parse_profile(&text, Some(path))?;
+    let mut document: DocumentMut = text.parse().map_err(|err: TomlError| {
+        invalid_at(&text, err.span().map(|span| span.start).unwrap_or(0), Some(path), err.message())
+    })?;
+
+    let macros: &mut Item = document.entry("macros").or_insert_with(|| {
+        let mut table: Table = Table::new();
+        table.set_implicit(true);
+        Item::Table(table)
+    });
+    if let Some(inline) = macros.as_inline_table() {
+        *macros = Item::Table(inline.clone().into_table());
+    }
+    let Some(macros) = macros.as_table_mut() else {
+        let offset: usize = macros.span().map(|span| span.start).unwrap_or(0);
+        return Err(invalid_at(&text, offset, Some(path), "`macros` must be a table"));
+    };
+
+    let mut steps: Array = Array::new();
+    for step in &macro_.steps {
+        let mut table: InlineTable = InlineTable::new();
+        match step {
+            MacroStep::Press(output) => table.insert("press", output.to_string().into()),
+            MacroStep::Release(output) => table.insert("release", output.to_string().into()),
+            MacroStep::Tap { output, hold } => {
+                table.insert("tap", output.to_string().into());
+                if *hold != DEFAULT_TAP_HOLD {
+                    table.insert("hold_ms", Value::from(hold.as_millis() as i64))
+                } else {
+                    None
+                }
+            }
+            MacroStep::Delay(delay) => table.insert("delay_ms", Value::from(delay.as_millis() as i64)),
+        };
+        let mut value: Value = Value::InlineTable(table);
+        value.decor_mut().set_prefix("\n    ");
+        steps.push_formatted(value);
+    }
+    steps.set_trailing("\n");
+    steps.set_trailing_comma(true);
+
+    let mut table: Table = Table::new();
+    table.insert("steps", Item::Value(Value::Array(steps)));
+    if macro_.cancel_on_release {
+        table.insert("cancel_on_release", Item::Value(Value::from(true)));
+    }
+    macros.insert(name, Item::Table(table));
+
+    fs::write(path, document.to_string()).map_err(|source: io::Error| ProfileError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
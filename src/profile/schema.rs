@@ -0,0 +1,796 @@
+use crate::deck::DeckButton;
+use crate::mapping::{self, Action, Binding, Macro};
+use crate::output::{DigitalOutput, GamepadAxis, GamepadButton, MouseButton};
+use crate::profile::validate;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Declarative description of how Deck inputs translate to outputs.
+///
+/// Deserialized straight from a TOML profile, every table rejects unknown keys and every
+/// missing key falls back to [`Profile::default`].
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Defaults to the file name when loaded from a file
+    #[serde(default)]
+    pub name: String,
+    pub lizard_mode: LizardMode,
+    /// How long the touch sensors of the sticks and trackpads have to hold a new state before
+    /// bindings see it
+    pub touch_debounce_ms: u64,
+    /// Deck buttons without a binding do nothing. A `[bindings]` table replaces the default
+    /// bindings as a whole.
+    #[serde(deserialize_with = "mapping::deserialize_bindings")]
+    pub bindings: BTreeMap<DeckButton, Vec<Binding>>,
+    /// Extra sets of bindings stacked on top of `bindings` by [`Action::Layer`] bindings
+    pub layers: BTreeMap<String, Layer>,
+    /// Sequences of timed outputs played by [`Action::Macro`] bindings
+    pub macros: BTreeMap<String, Macro>,
+    /// Radial menus, opened by a trackpad or stick in `radial_menu` mode
+    pub menus: BTreeMap<String, Menu>,
+    pub sticks: Sticks,
+    pub trackpads: Trackpads,
+    pub triggers: Triggers,
+    pub gyro: GyroSettings,
+    pub tilt: TiltSettings,
+    pub haptics: HapticSettings,
+}
+
+impl Profile {
+    /// A profile without any bindings
+    pub fn empty(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            bindings: BTreeMap::new(),
+            ..Self::default()
+        }
+    }
+
+    /// Replaces the bindings of `button` with a single regular binding
+    pub fn bind(&mut self, button: DeckButton, action: impl Into<Action>) -> &mut Self {
+        self.bindings.insert(button, vec![Binding::regular(action)]);
+        self
+    }
+
+    pub fn bindings_for(&self, button: DeckButton) -> &[Binding] {
+        self.bindings.get(&button).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+impl Default for Profile {
+    /// Maps every Deck button to its Xbox controller counterpart, the back grips and the trackpad
+    /// clicks stay unbound
+    fn default() -> Self {
+        let mut bindings: BTreeMap<DeckButton, Vec<Binding>> = BTreeMap::new();
+        for (button, gamepad_button) in [
+            (DeckButton::A, GamepadButton::A),
+            (DeckButton::B, GamepadButton::B),
+            (DeckButton::X, GamepadButton::X),
+            (DeckButton::Y, GamepadButton::Y),
+            (DeckButton::L1, GamepadButton::LeftBumper),
+            (DeckButton::R1, GamepadButton::RightBumper),
+            (DeckButton::View, GamepadButton::Back),
+            (DeckButton::Menu, GamepadButton::Start),
+            (DeckButton::Steam, GamepadButton::Guide),
+            (DeckButton::DpadUp, GamepadButton::DpadUp),
+            (DeckButton::DpadDown, GamepadButton::DpadDown),
+            (DeckButton::DpadLeft, GamepadButton::DpadLeft),
+            (DeckButton::DpadRight, GamepadButton::DpadRight),
+            (DeckButton::LeftStickClick, GamepadButton::LeftStick),
+            (DeckButton::RightStickClick, GamepadButton::RightStick),
+        ] {
+            bindings.insert(button, vec![Binding::regular(DigitalOutput::Gamepad(gamepad_button))]);
+        }
+
+        Self {
+            name: "default".into(),
+            lizard_mode: LizardMode::default(),
+            touch_debounce_ms: 20,
+            bindings,
+            layers: BTreeMap::new(),
+            macros: BTreeMap::new(),
+            menus: BTreeMap::new(),
+            sticks: Sticks::default(),
+            trackpads: Trackpads::default(),
+            triggers: Triggers::default(),
+            gyro: GyroSettings::default(),
+            tilt: TiltSettings::default(),
+            haptics: HapticSettings::default(),
+        }
+    }
+}
+
+/// A set of bindings overriding the ones below it while active, e.g.
+///
+/// ```toml
+/// [bindings]
+/// l4 = { layer = "aim", mode = "hold" }
+///
+/// [layers.aim.bindings]
+/// a = "key:r"
+/// ```
+///
+/// Buttons the layer doesn't bind fall through to the next active layer, then to the base bindings.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Layer {
+    /// Layers with a higher priority win, ties go to the most recently activated layer
+    pub priority: i32,
+    #[serde(deserialize_with = "mapping::deserialize_bindings")]
+    pub bindings: BTreeMap<DeckButton, Vec<Binding>>,
+}
+
+/// A ring of actions picked by pointing a trackpad or stick at them, e.g.
+///
+/// ```toml
+/// [menus.weapons]
+/// slices = ["key:num1", "key:num2", "key:num3", "key:num4"]
+///
+/// [trackpads.right]
+/// mode = "radial_menu"
+/// menu = "weapons"
+/// ```
+///
+/// Crossing into another slice plays a haptic tick, so the menu works without anything on screen.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Menu {
+    /// Clockwise from `start_angle`, the selected slice's action is tapped
+    #[serde(deserialize_with = "validate::non_empty")]
+    pub slices: Vec<Action>,
+    #[serde(default)]
+    pub select: MenuSelect,
+    /// Distance from the center before a slice is hovered
+    #[serde(default = "Menu::default_deadzone", deserialize_with = "validate::unit_interval")]
+    pub deadzone: f32,
+    /// Where the middle of the first slice is, in degrees clockwise from up
+    #[serde(default, deserialize_with = "validate::finite")]
+    pub start_angle: f32,
+    /// Emits menu events for an overlay to draw
+    #[serde(default)]
+    pub overlay: bool,
+}
+
+impl Menu {
+    fn default_deadzone() -> f32 {
+        0.4
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MenuSelect {
+    /// Lifting the finger or letting the stick go back to center selects the hovered slice
+    #[default]
+    Release,
+    /// Clicking the pad or stick selects the hovered slice
+    Click,
+}
+
+/// What happens to the controller's built-in keyboard and mouse emulation ("lizard mode")
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LizardMode {
+    /// Turned off while `run` uses this profile and restored on exit, so the trackpads and buttons
+    /// only do what the profile binds
+    #[default]
+    Disabled,
+    /// Left untouched, the trackpads keep moving the desktop cursor
+    Enabled,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sticks {
+    pub left: StickSettings,
+    pub right: StickSettings,
+}
+
+/// How a stick is shaped before it reaches the output, see [`crate::processing::process_stick`]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StickSettings {
+    pub mode: StickMode,
+    /// Fraction of the stick travel around the center that is ignored
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub deadzone: f32,
+    /// Fraction of the stick travel at the edge that already counts as fully deflected
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub outer_deadzone: f32,
+    /// Like `deadzone` but for each axis on its own, keeps a mostly vertical push from drifting sideways
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub axial_deadzone: f32,
+    /// Like `outer_deadzone` but for each axis on its own
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub outer_axial_deadzone: f32,
+    /// Smallest deflection sent once the stick leaves the deadzone, set it to the game's own
+    /// deadzone so the first bit of travel isn't lost twice
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub anti_deadzone: f32,
+    pub curve: Curve,
+    pub invert_x: bool,
+    pub invert_y: bool,
+    /// Menu opened in `radial_menu` mode
+    pub menu: Option<String>,
+    pub flick: FlickSettings,
+}
+
+impl Default for StickSettings {
+    fn default() -> Self {
+        Self {
+            mode: StickMode::default(),
+            deadzone: 0.05,
+            outer_deadzone: 0.02,
+            axial_deadzone: 0.0,
+            outer_axial_deadzone: 0.0,
+            anti_deadzone: 0.0,
+            curve: Curve::default(),
+            invert_x: false,
+            invert_y: false,
+            menu: None,
+            flick: FlickSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StickMode {
+    /// Drives the stick of the same side on the emulated gamepad
+    #[default]
+    Gamepad,
+    None,
+    /// Points at the slices of `menu`
+    RadialMenu,
+    /// Turns the camera with the mouse, see [`FlickSettings`]
+    FlickStick,
+}
+
+/// Pushing the stick turns the camera to face where it points within `flick_time_ms`, rotating
+/// the pushed stick then keeps turning with it, e.g.
+///
+/// ```toml
+/// [sticks.right]
+/// mode = "flick_stick"
+/// flick = { counts_per_360 = 7200 }
+/// ```
+///
+/// Pairs well with gyro aiming since the stick only ever turns horizontally.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FlickSettings {
+    /// Mouse counts it takes the game to turn all the way around. Find it by raising it until a
+    /// flick straight back faces exactly backwards.
+    #[serde(deserialize_with = "validate::positive")]
+    pub counts_per_360: f32,
+    /// How long a flick takes to turn, 0 turns at once
+    pub flick_time_ms: u64,
+    /// Stick deflection that starts a flick
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub threshold: f32,
+    /// Rotation speed in °/s below which turning is averaged over `smoothing_ms` to hide the
+    /// jitter of the stick angle, 0.0 disables smoothing
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub smoothing_threshold: f32,
+    pub smoothing_ms: u64,
+}
+
+impl Default for FlickSettings {
+    fn default() -> Self {
+        Self {
+            counts_per_360: 3600.0,
+            flick_time_ms: 100,
+            threshold: 0.9,
+            smoothing_threshold: 0.0,
+            smoothing_ms: 125,
+        }
+    }
+}
+
+/// Response curve applied to an analog input after its deadzones, e.g.
+/// `curve = { type = "exponential", exponent = 2.0 }`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Curve {
+    #[default]
+    Linear,
+    Exponential {
+        #[serde(deserialize_with = "validate::positive")]
+        exponent: f32,
+    },
+    /// Piecewise linear curve through `[input, output]` points, `(0, 0)` and `(1, 1)` are implied
+    Points {
+        #[serde(deserialize_with = "validate::curve_points")]
+        points: Vec<[f32; 2]>,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Triggers {
+    pub left: TriggerSettings,
+    pub right: TriggerSettings,
+}
+
+/// How an analog trigger drives the output, see [`crate::processing::TriggerProcessor`], e.g.
+/// aiming down sights on a soft pull and firing on a full pull:
+///
+/// ```toml
+/// [triggers.left]
+/// mode = "none"
+/// soft_pull = "mouse:right"
+/// full_pull = "mouse:left"
+/// ```
+///
+/// The L2 and R2 bindings stay independent of these and fire on the controller's own click point.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TriggerSettings {
+    pub mode: TriggerMode,
+    /// Fraction of the travel at the start that is ignored
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub deadzone: f32,
+    /// Fraction of the travel at the end that already counts as fully pulled
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub outer_deadzone: f32,
+    pub curve: Curve,
+    /// Range the analog output is remapped to once the trigger leaves the deadzone, e.g. raise
+    /// `output_min` past the game's own deadzone
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub output_min: f32,
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub output_max: f32,
+    /// Held while the trigger is pulled past `threshold`
+    pub soft_pull: Option<Action>,
+    /// Held while the trigger is pulled past `full_pull_threshold`
+    pub full_pull: Option<Action>,
+    /// Travel where `soft_pull` activates
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub threshold: f32,
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub full_pull_threshold: f32,
+    /// Releases `soft_pull` while `full_pull` is held instead of holding both
+    pub exclusive: bool,
+    /// Activates `soft_pull` as soon as the trigger moves in and releases it as soon as it moves
+    /// back out, wherever it is. `threshold` is ignored.
+    pub hair_trigger: bool,
+}
+
+impl Default for TriggerSettings {
+    fn default() -> Self {
+        Self {
+            mode: TriggerMode::default(),
+            deadzone: 0.0,
+            outer_deadzone: 0.0,
+            curve: Curve::default(),
+            output_min: 0.0,
+            output_max: 1.0,
+            soft_pull: None,
+            full_pull: None,
+            threshold: 0.5,
+            full_pull_threshold: 0.95,
+            exclusive: false,
+            hair_trigger: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerMode {
+    /// Drives the trigger of the same side on the emulated gamepad
+    #[default]
+    Gamepad,
+    /// Only drives `soft_pull` and `full_pull`
+    None,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Trackpads {
+    pub left: TrackpadSettings,
+    pub right: TrackpadSettings,
+}
+
+/// How a trackpad drives the output, see [`crate::processing::TrackpadMouse`]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrackpadSettings {
+    pub mode: TrackpadMode,
+    /// Pointer speed multiplier, at 1.0 swiping across the whole pad moves the pointer 600 pixels
+    #[serde(deserialize_with = "validate::positive")]
+    pub sensitivity: f32,
+    /// Extra gain per pad width per second of finger speed, 0.0 disables acceleration
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub acceleration: f32,
+    /// Keeps the pointer gliding after a flick like a trackball
+    pub inertia: bool,
+    /// Fraction of the glide speed lost every second, above 0.0 so glides come to a stop
+    #[serde(deserialize_with = "validate::positive_fraction")]
+    pub friction: f32,
+    /// Pressed while the pad is clicked down, on top of the pad click bindings
+    pub click: Option<DigitalOutput>,
+    /// Rotates finger motion clockwise by this many degrees, to match how the thumb moves on an
+    /// angled pad
+    #[serde(deserialize_with = "validate::finite")]
+    pub rotation: f32,
+    /// Plays a haptic tick on every scroll notch, d-pad direction, grid cell or edge crossing
+    pub haptic_ticks: bool,
+    pub scroll_wheel: ScrollWheelSettings,
+    pub dpad: DpadSettings,
+    pub joystick: JoystickSettings,
+    pub grid: GridSettings,
+    /// Menu opened in `radial_menu` mode
+    pub menu: Option<String>,
+}
+
+impl Default for TrackpadSettings {
+    fn default() -> Self {
+        Self {
+            mode: TrackpadMode::default(),
+            sensitivity: 1.0,
+            acceleration: 0.0,
+            inertia: true,
+            friction: 0.95,
+            click: Some(DigitalOutput::Mouse(MouseButton::Left)),
+            rotation: 0.0,
+            haptic_ticks: true,
+            scroll_wheel: ScrollWheelSettings::default(),
+            dpad: DpadSettings::default(),
+            joystick: JoystickSettings::default(),
+            grid: GridSettings::default(),
+            menu: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackpadMode {
+    #[default]
+    None,
+    /// Moves the pointer, clicking the pad presses `click`
+    Mouse,
+    /// Circling the finger along the edge scrolls, see `scroll_wheel`
+    ScrollWheel,
+    /// Four or eight directional outputs, see `dpad`
+    Dpad,
+    /// Drives a stick of the emulated gamepad, see `joystick`
+    Joystick,
+    /// Splits the pad into cells that each trigger an action, see `grid`
+    Grid,
+    /// Points at the slices of `menu`
+    RadialMenu,
+}
+
+/// Whether a pad mode reacts to the finger touching the pad or only to clicking it down
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PadActivation {
+    #[default]
+    Click,
+    Touch,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScrollWheelSettings {
+    /// How far the finger circles for one notch
+    #[serde(deserialize_with = "validate::positive")]
+    pub degrees_per_notch: f32,
+    /// Touches closer to the center than this are ignored, the wheel is the ring around it
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub inner_radius: f32,
+    /// Scrolls sideways instead of up and down
+    pub horizontal: bool,
+    /// Clockwise scrolls down (or right) unless inverted
+    pub invert: bool,
+}
+
+impl Default for ScrollWheelSettings {
+    fn default() -> Self {
+        Self {
+            degrees_per_notch: 30.0,
+            inner_radius: 0.3,
+            horizontal: false,
+            invert: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DpadSettings {
+    /// `4` or `8`, in 8-way mode the diagonals press both neighbouring directions
+    #[serde(deserialize_with = "validate::dpad_ways")]
+    pub ways: u8,
+    /// Widens every direction by this fraction of its sector, so directions next to each other
+    /// overlap around the diagonals
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub overlap: f32,
+    /// Touches closer to the center than this press nothing
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub deadzone: f32,
+    pub activation: PadActivation,
+    pub up: DigitalOutput,
+    pub down: DigitalOutput,
+    pub left: DigitalOutput,
+    pub right: DigitalOutput,
+}
+
+impl Default for DpadSettings {
+    fn default() -> Self {
+        Self {
+            ways: 4,
+            overlap: 0.0,
+            deadzone: 0.3,
+            activation: PadActivation::default(),
+            up: DigitalOutput::Gamepad(GamepadButton::DpadUp),
+            down: DigitalOutput::Gamepad(GamepadButton::DpadDown),
+            left: DigitalOutput::Gamepad(GamepadButton::DpadLeft),
+            right: DigitalOutput::Gamepad(GamepadButton::DpadRight),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JoystickSettings {
+    /// Stick of the emulated gamepad the pad drives while touched
+    pub stick: Side,
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub deadzone: f32,
+    /// Distance from the center where the edge begins
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub edge_radius: f32,
+    /// Held while the finger is on the edge, e.g. to sprint when the virtual stick is pushed all
+    /// the way
+    pub edge: Option<Action>,
+}
+
+impl Default for JoystickSettings {
+    fn default() -> Self {
+        Self {
+            stick: Side::Right,
+            deadzone: 0.1,
+            edge_radius: 0.9,
+            edge: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    #[default]
+    Left,
+    Right,
+}
+
+/// Cells are numbered row by row from the top left, e.g. a 2x2 grid:
+///
+/// ```toml
+/// [trackpads.right.grid]
+/// rows = 2
+/// columns = 2
+/// actions = ["key:num1", "key:num2", "key:num3", { macro = "reload" }]
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GridSettings {
+    #[serde(deserialize_with = "validate::non_zero")]
+    pub rows: u8,
+    #[serde(deserialize_with = "validate::non_zero")]
+    pub columns: u8,
+    pub activation: PadActivation,
+    /// One action per cell
+    pub actions: Vec<Action>,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        Self {
+            rows: 2,
+            columns: 2,
+            activation: PadActivation::default(),
+            actions: Vec::new(),
+        }
+    }
+}
+
+/// Aiming with the gyro, see [`crate::processing::GyroAim`], e.g.
+///
+/// ```toml
+/// [gyro]
+/// mode = "mouse"
+/// space = "player_space"
+/// activation = "right_stick_touch"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GyroSettings {
+    pub mode: GyroMode,
+    /// Which rotations of the controller turn into horizontal output
+    pub space: GyroSpace,
+    pub activation: GyroActivation,
+    /// At 1.0 turning the controller by one degree moves the pointer 10 pixels, or turning it at
+    /// 90°/s pushes the stick all the way
+    #[serde(deserialize_with = "validate::positive")]
+    pub sensitivity: f32,
+    /// Extra sensitivity reached at `acceleration_end`, as a multiple of `sensitivity`. 0.0
+    /// disables acceleration.
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub acceleration: f32,
+    /// Turning speed in °/s where acceleration begins
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub acceleration_start: f32,
+    /// Turning speed in °/s where acceleration is at its full amount
+    #[serde(deserialize_with = "validate::positive")]
+    pub acceleration_end: f32,
+    /// How acceleration ramps up between `acceleration_start` and `acceleration_end`
+    pub acceleration_curve: Curve,
+    /// Turning speed in °/s below which motion is averaged over `smoothing_ms` to hide hand
+    /// tremor, 0.0 disables smoothing
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub smoothing_threshold: f32,
+    pub smoothing_ms: u64,
+    /// Turning speed in °/s below which motion is scaled down towards zero, holding the aim
+    /// steady without a hard deadzone. 0.0 disables tightening.
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub tightening: f32,
+    pub invert_x: bool,
+    pub invert_y: bool,
+    /// Stick of the emulated gamepad driven in `stick` mode, on top of the physical stick
+    pub stick: Side,
+    /// Smallest stick deflection in `stick` mode, to get past the game's own deadzone
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub anti_deadzone: f32,
+    /// Keeps correcting drift whenever the controller lies still
+    pub auto_calibrate: bool,
+    /// How the orientation used by `player_space` and `world_space` is tracked
+    pub filter: FusionFilter,
+}
+
+impl Default for GyroSettings {
+    fn default() -> Self {
+        Self {
+            mode: GyroMode::default(),
+            space: GyroSpace::default(),
+            activation: GyroActivation::default(),
+            sensitivity: 1.0,
+            acceleration: 0.0,
+            acceleration_start: 0.0,
+            acceleration_end: 75.0,
+            acceleration_curve: Curve::default(),
+            smoothing_threshold: 0.0,
+            smoothing_ms: 125,
+            tightening: 0.0,
+            invert_x: false,
+            invert_y: false,
+            stick: Side::Right,
+            anti_deadzone: 0.0,
+            auto_calibrate: true,
+            filter: FusionFilter::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GyroMode {
+    #[default]
+    None,
+    Mouse,
+    Stick,
+}
+
+/// Vertical output always follows pitch, i.e. tilting the top of the controller towards or away
+/// from the player
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GyroSpace {
+    /// Turning the controller around the axis through its screen
+    #[default]
+    Yaw,
+    /// Leaning the controller left and right like a steering wheel
+    Roll,
+    /// Turning around the vertical axis, relaxed so both yaw and roll turn however the
+    /// controller is held
+    PlayerSpace,
+    /// Turning around the vertical axis only
+    WorldSpace,
+}
+
+/// Filter combining gyro and accelerometer into an orientation, see
+/// [`crate::processing::SensorFusion`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FusionFilter {
+    /// Integrates the gyro and pulls the tilt towards the accelerometer at a fixed rate
+    #[default]
+    Complementary,
+    /// Madgwick's gradient descent filter, a little steadier while the controller shakes
+    Madgwick,
+}
+
+/// When the gyro aims, e.g. `activation = { hold = "r5" }`.
+///
+/// The buttons keep their own bindings, so a button used here is usually left unbound.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GyroActivation {
+    #[default]
+    Always,
+    LeftStickTouch,
+    RightStickTouch,
+    /// While the button is held
+    Hold(DeckButton),
+    /// Every press of the button turns the gyro on or off, it starts off
+    Toggle(DeckButton),
+}
+
+/// Holding the controller at an angle pushes an analog output, e.g. steering by leaning it like a
+/// wheel:
+///
+/// ```toml
+/// [tilt]
+/// recenter = "view"
+/// roll = { output = "left_stick_x", max_angle = 30.0 }
+/// pitch = { output = "right_trigger", max_angle = 20.0 }
+/// ```
+///
+/// Angles are measured from the orientation the controller had when tracking started.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TiltSettings {
+    /// Takes the current orientation as the new center while held. The button keeps its own
+    /// bindings.
+    pub recenter: Option<DeckButton>,
+    /// Leaning the controller left and right
+    pub roll: Option<TiltAxis>,
+    /// Tilting the top of the controller towards or away from the player
+    pub pitch: Option<TiltAxis>,
+}
+
+/// How one tilt angle drives an axis. Stick axes add to what the sticks already output, triggers
+/// only use tilting right or up and take whichever of trigger and tilt is further.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TiltAxis {
+    pub output: GamepadAxis,
+    /// Angle in degrees that pushes the output all the way
+    #[serde(default = "TiltAxis::default_max_angle", deserialize_with = "validate::positive")]
+    pub max_angle: f32,
+    /// Angle in degrees around the center that is ignored
+    #[serde(default, deserialize_with = "validate::non_negative")]
+    pub deadzone: f32,
+    #[serde(default)]
+    pub curve: Curve,
+    #[serde(default)]
+    pub invert: bool,
+}
+
+impl TiltAxis {
+    fn default_max_angle() -> f32 {
+        45.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HapticSettings {
+    pub enabled: bool,
+    /// Scales the strength of every haptic pulse
+    #[serde(deserialize_with = "validate::unit_interval")]
+    pub intensity: f32,
+}
+
+impl Default for HapticSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            intensity: 0.5,
+        }
+    }
+}
@@ -0,0 +1,435 @@
+// Range checks run while deserializing so the errors carry the span of the offending value,
+// which is what lets `ProfileError` point at an exact line and column. Checks that need the whole
+// profile run afterwards and are located through their key path instead.
+
+use crate::deck::DeckButton;
+use crate::mapping::{Action, Activator, Binding};
+use crate::output;
+use crate::profile::{
+    GridSettings, GyroSettings, Profile, StickMode, StickSettings, TiltSettings, TrackpadMode, TrackpadSettings,
+    TriggerSettings,
+};
+use serde::de::{self, Deserialize, Deserializer};
+use std::collections::BTreeMap;
+
+/// Accepts `0.0..=1.0`
+pub(crate) fn unit_interval<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let value: f32 = f32::deserialize(deserializer)?;
+    if !(0.0..=1.0).contains(&value) {
+        return Err(de::Error::custom(format!("{value} is out of range, expected a value between 0.0 and 1.0")));
+    }
+    Ok(value)
+}
+
+/// Accepts `0.0..=1.0` without `0.0`, e.g. a fraction that has to make progress
+pub(crate) fn positive_fraction<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let value: f32 = f32::deserialize(deserializer)?;
+    if !(0.0..=1.0).contains(&value) || value == 0.0 {
+        return Err(de::Error::custom(format!("{value} is out of range, expected a value above 0.0 up to 1.0")));
+    }
+    Ok(value)
+}
+
+/// Accepts any finite value above `0.0`
+pub(crate) fn positive<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let value: f32 = f32::deserialize(deserializer)?;
+    if !value.is_finite() || value <= 0.0 {
+        return Err(de::Error::custom(format!("{value} is out of range, expected a value above 0.0")));
+    }
+    Ok(value)
+}
+
+/// Accepts any finite value of `0.0` or above
+pub(crate) fn non_negative<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let value: f32 = f32::deserialize(deserializer)?;
+    if !value.is_finite() || value < 0.0 {
+        return Err(de::Error::custom(format!("{value} is out of range, expected a value of 0.0 or above")));
+    }
+    Ok(value)
+}
+
+/// Accepts any finite value, e.g. an angle
+pub(crate) fn finite<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let value: f32 = f32::deserialize(deserializer)?;
+    if !value.is_finite() {
+        return Err(de::Error::custom(format!("{value} is out of range, expected a finite value")));
+    }
+    Ok(value)
+}
+
+/// Accepts any list with at least one element
+pub(crate) fn non_empty<'de, D: Deserializer<'de>, T: Deserialize<'de>>(deserializer: D) -> Result<Vec<T>, D::Error> {
+    let values: Vec<T> = Vec::deserialize(deserializer)?;
+    if values.is_empty() {
+        return Err(de::Error::custom("expected at least one element"));
+    }
+    Ok(values)
+}
+
+/// Accepts any count above `0`
+pub(crate) fn non_zero<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+    let count: u8 = u8::deserialize(deserializer)?;
+    if count == 0 {
+        return Err(de::Error::custom("0 is out of range, expected at least 1"));
+    }
+    Ok(count)
+}
+
+/// Accepts `4` or `8`
+pub(crate) fn dpad_ways<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+    let ways: u8 = u8::deserialize(deserializer)?;
+    if ways != 4 && ways != 8 {
+        return Err(de::Error::custom(format!("a d-pad has 4 or 8 ways, not {ways}")));
+    }
+    Ok(ways)
+}
+
+/// Accepts `[x, y]` points inside the unit square with strictly increasing `x`
+pub(crate) fn curve_points<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<[f32; 2]>, D::Error> {
+    let points: Vec<[f32; 2]> = Vec::deserialize(deserializer)?;
+    if points.is_empty() {
+        return Err(de::Error::custom("expected at least one point"));
+    }
+    for (index, [x, y]) in points.iter().enumerate() {
+        if !(0.0..=1.0).contains(x) || !(0.0..=1.0).contains(y) {
+            return Err(de::Error::custom(format!(
+                "point {index} ([{x}, {y}]) is out of range, both coordinates must be between 0.0 and 1.0"
+            )));
+        }
+    }
+    if points.windows(2).any(|pair| pair[0][0] >= pair[1][0]) {
+        return Err(de::Error::custom("points must be sorted by strictly increasing x"));
+    }
+    Ok(points)
+}
+
+/// A problem found after deserializing, located by the path of keys leading to it
+pub(crate) struct SchemaError {
+    pub key_path: Vec<String>,
+    pub message: String,
+}
+
+/// Checks what serde can't, i.e. references between different parts of the profile
+pub(crate) fn check(profile: &Profile) -> Result<(), SchemaError> {
+    check_bindings(profile, &profile.bindings, &["bindings"])?;
+    for (name, layer) in &profile.layers {
+        check_bindings(profile, &layer.bindings, &["layers", name, "bindings"])?;
+    }
+    for (name, menu) in &profile.menus {
+        if let Some(message) = menu.slices.iter().find_map(|action| check_action(profile, action)) {
+            return Err(SchemaError {
+                key_path: vec!["menus".into(), name.clone(), "slices".into()],
+                message,
+            });
+        }
+    }
+    check_stick(&profile.sticks.left, "left")?;
+    check_stick(&profile.sticks.right, "right")?;
+    check_trackpad(profile, &profile.trackpads.left, "left")?;
+    check_trackpad(profile, &profile.trackpads.right, "right")?;
+    check_trigger(profile, &profile.triggers.left, "left")?;
+    check_trigger(profile, &profile.triggers.right, "right")?;
+    check_gyro(&profile.gyro)?;
+    check_tilt(&profile.tilt)?;
+    for (section, side, is_menu, menu) in [
+        ("sticks", "left", profile.sticks.left.mode == StickMode::RadialMenu, &profile.sticks.left.menu),
+        ("sticks", "right", profile.sticks.right.mode == StickMode::RadialMenu, &profile.sticks.right.menu),
+        ("trackpads", "left", profile.trackpads.left.mode == TrackpadMode::RadialMenu, &profile.trackpads.left.menu),
+        ("trackpads", "right", profile.trackpads.right.mode == TrackpadMode::RadialMenu, &profile.trackpads.right.menu),
+    ] {
+        check_menu_reference(profile, is_menu, menu, &[section, side])?;
+    }
+    Ok(())
+}
+
+/// `radial_menu` mode needs a `menu` and a `menu` has to exist
+fn check_menu_reference(
+    profile: &Profile,
+    is_menu: bool,
+    menu: &Option<String>,
+    key_path: &[&str],
+) -> Result<(), SchemaError> {
+    let mut key_path: Vec<String> = key_path.iter().map(|key| key.to_string()).collect();
+    let message: String = match menu {
+        None if is_menu => {
+            key_path.push("mode".into());
+            "`radial_menu` mode needs a `menu`".into()
+        }
+        Some(name) if !profile.menus.contains_key(name) => {
+            key_path.push("menu".into());
+            format!("unknown menu `{name}`")
+        }
+        _ => return Ok(()),
+    };
+    Err(SchemaError { key_path, message })
+}
+
+/// Every grid cell needs an action and actions must reference existing layers and macros
+fn check_trackpad(profile: &Profile, settings: &TrackpadSettings, side: &str) -> Result<(), SchemaError> {
+    let grid: &GridSettings = &settings.grid;
+    let cells: usize = grid.rows as usize * grid.columns as usize;
+    if !grid.actions.is_empty() && grid.actions.len() != cells {
+        return Err(SchemaError {
+            key_path: vec!["trackpads".into(), side.into(), "grid".into(), "actions".into()],
+            message: format!(
+                "a {}x{} grid needs {cells} actions, found {}",
+                grid.rows,
+                grid.columns,
+                grid.actions.len()
+            ),
+        });
+    }
+
+    for (key, action) in grid
+        .actions
+        .iter()
+        .map(|action| ("grid", action))
+        .chain(settings.joystick.edge.iter().map(|action| ("joystick", action)))
+    {
+        if let Some(message) = check_action(profile, action) {
+            return Err(SchemaError {
+                key_path: vec!["trackpads".into(), side.into(), key.into()],
+                message,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The ranges of a trigger must not be empty and its actions must reference existing layers and
+/// macros
+fn check_trigger(profile: &Profile, settings: &TriggerSettings, side: &str) -> Result<(), SchemaError> {
+    let error = |key: &str, message: String| SchemaError {
+        key_path: vec!["triggers".into(), side.into(), key.into()],
+        message,
+    };
+    if settings.deadzone + settings.outer_deadzone >= 1.0 {
+        return Err(error(
+            "deadzone",
+            format!(
+                "`deadzone` and `outer_deadzone` add up to {}, leaving no travel",
+                settings.deadzone + settings.outer_deadzone
+            ),
+        ));
+    }
+    if settings.output_min > settings.output_max {
+        return Err(error(
+            "output_min",
+            format!(
+                "`output_min` ({}) is above `output_max` ({})",
+                settings.output_min, settings.output_max
+            ),
+        ));
+    }
+    if settings.soft_pull.is_some() && settings.full_pull.is_some() && settings.threshold >= settings.full_pull_threshold
+    {
+        return Err(error(
+            "threshold",
+            format!(
+                "`threshold` ({}) has to be below `full_pull_threshold` ({})",
+                settings.threshold, settings.full_pull_threshold
+            ),
+        ));
+    }
+    for (key, action) in [("soft_pull", &settings.soft_pull), ("full_pull", &settings.full_pull)] {
+        if let Some(message) = action.as_ref().and_then(|action| check_action(profile, action)) {
+            return Err(error(key, message));
+        }
+    }
+    Ok(())
+}
+
+/// Acceleration needs a speed range to ramp up over
+fn check_gyro(settings: &GyroSettings) -> Result<(), SchemaError> {
+    if settings.acceleration > 0.0 && settings.acceleration_start >= settings.acceleration_end {
+        return Err(SchemaError {
+            key_path: vec!["gyro".into(), "acceleration_start".into()],
+            message: format!(
+                "`acceleration_start` ({}) has to be below `acceleration_end` ({})",
+                settings.acceleration_start, settings.acceleration_end
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// The deadzone has to end before the output is pushed all the way
+fn check_tilt(settings: &TiltSettings) -> Result<(), SchemaError> {
+    for (key, axis) in [("roll", &settings.roll), ("pitch", &settings.pitch)] {
+        if let Some(axis) = axis
+            && axis.deadzone >= axis.max_angle
+        {
+            return Err(SchemaError {
+                key_path: vec!["tilt".into(), key.into(), "deadzone".into()],
+                message: format!(
+                    "`deadzone` ({}) has to be below `max_angle` ({})",
+                    axis.deadzone, axis.max_angle
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The inner and outer deadzones must leave some travel between them
+fn check_stick(settings: &StickSettings, side: &str) -> Result<(), SchemaError> {
+    for (inner, outer, key) in [
+        (settings.deadzone, settings.outer_deadzone, "deadzone"),
+        (settings.axial_deadzone, settings.outer_axial_deadzone, "axial_deadzone"),
+    ] {
+        if inner + outer >= 1.0 {
+            return Err(SchemaError {
+                key_path: vec!["sticks".into(), side.into(), key.into()],
+                message: format!("`{key}` and `outer_{key}` add up to {}, leaving no travel", inner + outer),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn check_bindings(
+    profile: &Profile,
+    bindings: &BTreeMap<DeckButton, Vec<Binding>>,
+    key_path: &[&str],
+) -> Result<(), SchemaError> {
+    for (button, button_bindings) in bindings {
+        // The first long press to fire takes over the press, a second one could never fire
+        let long_presses: usize = button_bindings
+            .iter()
+            .filter(|binding| matches!(binding.activator, Activator::LongPress { .. }))
+            .count();
+        if long_presses > 1 {
+            let mut key_path: Vec<String> = key_path.iter().map(|key| key.to_string()).collect();
+            key_path.push(output::snake_case(button));
+            let message: String = "a button can only have one `long_press` binding".into();
+            return Err(SchemaError { key_path, message });
+        }
+        for binding in button_bindings {
+            let message: String = match (check_action(profile, &binding.action), binding.activator) {
+                (Some(message), _) => message,
+                (None, Activator::Chord { with }) if with == *button => "a button can't chord with itself".into(),
+                _ => continue,
+            };
+
+            let mut key_path: Vec<String> = key_path.iter().map(|key| key.to_string()).collect();
+            key_path.push(output::snake_case(button));
+            return Err(SchemaError { key_path, message });
+        }
+    }
+    Ok(())
+}
+
+/// Layers and macros an action refers to must exist
+fn check_action(profile: &Profile, action: &Action) -> Option<String> {
+    match action {
+        Action::Layer { layer, .. } if !profile.layers.contains_key(layer) => Some(format!("unknown layer `{layer}`")),
+        Action::Macro(name) if !profile.macros.contains_key(name) => Some(format!("unknown macro `{name}`")),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::profile::{self, Profile, ProfileError};
+
+    fn error(text: &str) -> String {
+        match profile::parse(text) {
+            Err(ProfileError::Invalid { message, .. }) => message,
+            other => panic!("expected an invalid profile, got {other:?}"),
+        }
+    }
+
+    /// Line, column and message of the error
+    fn located(text: &str) -> (usize, usize, String) {
+        match profile::parse(text) {
+            Err(ProfileError::Invalid {
+                line, column, message, ..
+            }) => (line, column, message),
+            other => panic!("expected an invalid profile, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn bad_values_point_at_their_line_and_column() {
+        let (line, column, message) = located("name = \"aim\"\n\n[sticks.left]\ndeadzone = \"far\"\n");
+        assert_eq!((line, column), (4, 12));
+        assert!(message.contains("invalid type"), "{message}");
+
+        let (line, column, message) = located("[bindings]\nb = \"key:e\"\nx = \"key:nope\"");
+        assert_eq!((line, column), (3, 5));
+        assert!(message.contains("unknown variant `nope`"), "{message}");
+    }
+
+    #[test]
+    fn unknown_keys_are_reported() {
+        let (line, column, message) = located("[sticks.right]\ndeadzone = 0.1\ndeadzon = 0.2");
+        assert_eq!((line, column), (3, 1));
+        assert!(message.contains("unknown field `deadzon`"), "{message}");
+        assert!(error("colour = \"red\"").contains("unknown field `colour`"));
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        let (line, column, message) = located("[haptics]\nintensity = 1.5");
+        assert_eq!((line, column), (2, 13));
+        assert_eq!(message, "1.5 is out of range, expected a value between 0.0 and 1.0");
+        assert!(error("[sticks.left]\nanti_deadzone = -0.1").contains("out of range"));
+    }
+
+    #[test]
+    fn binding_checks_point_at_the_button() {
+        let long_press: &str = r#"{ action = "key:e", activator = "long_press" }"#;
+        let text: String = format!("[bindings]\na = \"key:q\"\nleft_stick_click = [{long_press}, {long_press}]");
+        let (line, _, message) = located(&text);
+        assert_eq!(line, 3);
+        assert!(message.contains("only have one `long_press` binding"), "{message}");
+    }
+
+    #[test]
+    fn trackpad_rotation_must_be_finite() {
+        let profile: Profile = profile::parse("[trackpads.left]\nrotation = -15.0").unwrap();
+        assert_eq!(profile.trackpads.left.rotation, -15.0);
+        for value in ["nan", "inf", "-inf"] {
+            let message: String = error(&format!("[trackpads.left]\nrotation = {value}"));
+            assert!(message.contains("expected a finite value"), "{value}: {message}");
+        }
+    }
+
+    #[test]
+    fn menu_start_angle_must_be_finite() {
+        let menu: &str = "[menus.weapons]\nslices = [\"key:num1\"]\nstart_angle = ";
+        let profile: Profile = profile::parse(&format!("{menu}45.0")).unwrap();
+        assert_eq!(profile.menus["weapons"].start_angle, 45.0);
+        let message: String = error(&format!("{menu}nan"));
+        assert!(message.contains("expected a finite value"), "{message}");
+    }
+
+    #[test]
+    fn trackpad_friction_must_slow_glides_down() {
+        let profile: Profile = profile::parse("[trackpads.left]\nfriction = 1.0").unwrap();
+        assert_eq!(profile.trackpads.left.friction, 1.0);
+        for value in ["0.0", "-0.5", "1.5", "nan"] {
+            let message: String = error(&format!("[trackpads.left]\nfriction = {value}"));
+            assert!(message.contains("expected a value above 0.0 up to 1.0"), "{value}: {message}");
+        }
+    }
+
+    #[test]
+    fn one_long_press_per_button() {
+        let long_press = |threshold_ms: u32| {
+            format!(r#"{{ action = "key:e", activator = "long_press", threshold_ms = {threshold_ms} }}"#)
+        };
+        let bindings: String = format!("[bindings]\na = [{}, {}]", long_press(400), long_press(800));
+        assert!(error(&bindings).contains("only have one `long_press` binding"));
+    }
+
+    #[test]
+    fn grid_needs_rows_and_columns() {
+        let profile: Profile = profile::parse("[trackpads.right.grid]\nrows = 1\ncolumns = 3").unwrap();
+        assert_eq!((profile.trackpads.right.grid.rows, profile.trackpads.right.grid.columns), (1, 3));
+        for key in ["rows", "columns"] {
+            let message: String = error(&format!("[trackpads.right.grid]\n{key} = 0"));
+            assert!(message.contains("expected at least 1"), "{key}: {message}");
+        }
+    }
+}
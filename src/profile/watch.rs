@@ -0,0 +1,94 @@
+use crate::prelude::*;
+use crate::profile::{self, Profile};
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Watches a profile file and hands every valid new version to a callback.
+///
+/// The file is polled rather than subscribed to, which survives editors that save by replacing
+/// the file. A change is only picked up once the file stopped changing for one poll, so a
+/// half-written save isn't loaded. Invalid versions are logged and skipped, leaving whatever
+/// profile the callback last received in place.
+pub struct ProfileWatcher {
+    thread: Option<JoinHandle<()>>,
+    stop_flag: Arc<Mutex<bool>>,
+}
+
+impl ProfileWatcher {
+    pub fn spawn<F>(path: PathBuf, on_reload: F) -> Self
+    where
+        F: Fn(Profile) + Send + 'static,
+    {
+        let stop_flag: Arc<Mutex<bool>> = Arc::new(Mutex::new(false));
+        let thread_stop_flag: Arc<Mutex<bool>> = stop_flag.clone();
+
+        trace!("Entering thread `profile_watch`...");
+        let thread: JoinHandle<()> = thread::Builder::new()
+            .name("profile_watch".into())
+            .spawn(move || {
+                trace!("Entered thread");
+
+                let mut loaded: Option<(SystemTime, u64)> = stamp(&path);
+                let mut pending: Option<(SystemTime, u64)> = None;
+                loop {
+                    thread::sleep(POLL_INTERVAL);
+                    if *thread_stop_flag.lock().unwrap() {
+                        break;
+                    }
+
+                    let current: Option<(SystemTime, u64)> = stamp(&path);
+                    if current.is_none() || current == loaded {
+                        pending = None;
+                        continue;
+                    } else if current != pending {
+                        // Still being written, wait for it to settle
+                        pending = current;
+                        continue;
+                    }
+
+                    loaded = current;
+                    pending = None;
+                    match profile::load(&path) {
+                        Ok(profile) => {
+                            info!("Reloaded profile `{}` from {}", profile.name, path.display());
+                            on_reload(profile);
+                        }
+                        Err(err) => error!("Keeping the current profile, the new one is invalid: {}", err),
+                    }
+                }
+
+                trace!("Exiting thread...");
+            })
+            .unwrap();
+
+        Self {
+            thread: Some(thread),
+            stop_flag,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        *self.stop_flag.lock().unwrap() = true;
+        if let Some(handle) = self.thread.take() {
+            handle.join().ok();
+            trace!("Exited thread `profile_watch`");
+        }
+    }
+}
+
+impl Drop for ProfileWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Modification time and size, `None` while the file is missing (e.g. mid-replace)
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata: Metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
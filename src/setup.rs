@@ -1,5 +1,6 @@
 use crate::cli_parser::Args;
 use crate::prelude::*;
+use clap::error::ErrorKind;
 use chrono::format::{DelayedFormat, StrftimeItems};
 use chrono::Local;
 use colored::*;
@@ -7,7 +8,6 @@ use env_logger::fmt::Formatter;
 use env_logger::Builder;
 use log::{LevelFilter, Record};
 use std::io::Write;
-use std::process;
 use std::thread::{self, Thread};
 
 /// Sets up the program by:
@@ -17,11 +17,8 @@ use std::thread::{self, Thread};
 pub fn setup_logger_and_args() -> Args {
     let _args: Args = match Args::parse() {
         Ok(arguments) => arguments,
-        Err(error) => {
-            println!("Error: {}", error);
-            Args::print_help();
-            process::exit(1);
-        }
+        // Printed to stderr with the usage, like the errors clap catches itself
+        Err(error) => Args::command().error(ErrorKind::ValueValidation, error).exit(),
     };
 
     init_logger(_args.verbose);